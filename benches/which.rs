@@ -0,0 +1,82 @@
+// Criterion pulls in rayon, which doesn't build for WASI, so there's nothing to run there.
+#[cfg(target_os = "wasi")]
+fn main() {}
+
+#[cfg(not(target_os = "wasi"))]
+criterion::criterion_main!(bench::benches);
+
+#[cfg(not(target_os = "wasi"))]
+mod bench {
+    use criterion::{criterion_group, Criterion};
+    use std::env;
+    use std::ffi::OsString;
+    use std::fs;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    /// A `PATH` made of many empty directories followed by one holding the binary, so every lookup
+    /// has to expand (and on Windows, append every `PATHEXT` extension to) each entry before a hit.
+    struct LongPath {
+        _tempdir: TempDir,
+        paths: OsString,
+        cwd: PathBuf,
+    }
+
+    const PATH_ENTRIES: usize = 64;
+    const BIN_NAME: &str = "bench-bin";
+
+    impl LongPath {
+        fn new() -> LongPath {
+            let tempdir = tempfile::tempdir().unwrap();
+            let dirs: Vec<PathBuf> = (0..PATH_ENTRIES)
+                .map(|i| {
+                    let dir = tempdir.path().join(i.to_string());
+                    fs::create_dir(&dir).unwrap();
+                    dir
+                })
+                .collect();
+
+            let bin = dirs
+                .last()
+                .unwrap()
+                .join(BIN_NAME)
+                .with_extension(env::consts::EXE_EXTENSION);
+            fs::write(&bin, "").unwrap();
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(&bin, fs::Permissions::from_mode(0o755)).unwrap();
+            }
+
+            LongPath {
+                cwd: tempdir.path().to_path_buf(),
+                paths: env::join_paths(dirs).unwrap(),
+                _tempdir: tempdir,
+            }
+        }
+    }
+
+    fn bench_which(c: &mut Criterion) {
+        let fixture = LongPath::new();
+
+        c.bench_function("which_in last entry", |b| {
+            b.iter(|| which::which_in(BIN_NAME, Some(&fixture.paths), &fixture.cwd).unwrap())
+        });
+
+        c.bench_function("which_in missing", |b| {
+            b.iter(|| {
+                which::which_in("missing-bin", Some(&fixture.paths), &fixture.cwd).unwrap_err()
+            })
+        });
+
+        c.bench_function("which_in_all count", |b| {
+            b.iter(|| {
+                which::which_in_all(BIN_NAME, Some(&fixture.paths), &fixture.cwd)
+                    .unwrap()
+                    .count()
+            })
+        });
+    }
+
+    criterion_group!(benches, bench_which);
+}
@@ -0,0 +1,21 @@
+/* C API of the `which` crate, built with the `ffi` feature. */
+
+#ifndef WHICH_H
+#define WHICH_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Returns the path of the executable `name` resolves to, or NULL if there is none. The result
+ * must be released with which_free. */
+char *which_find(const char *name);
+
+/* Releases a path returned by which_find. Does nothing if `path` is NULL. */
+void which_free(char *path);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* WHICH_H */
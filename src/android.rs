@@ -0,0 +1,132 @@
+//! Finding the Android SDK and NDK tools, such as `adb`, `aapt2` and `clang`, which live in the
+//! SDK and NDK directories rather than on `PATH`.
+//!
+//! The directories are found the way the Android Gradle plugin does, from `ANDROID_HOME` and
+//! `ANDROID_NDK_HOME` or the locations Android Studio installs to.
+
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The Android SDK directory: `ANDROID_HOME`, the deprecated `ANDROID_SDK_ROOT`, or where
+/// Android Studio installs it, if it exists.
+pub fn sdk_dir() -> Option<PathBuf> {
+    if let Some(dir) = var("ANDROID_HOME").or_else(|| var("ANDROID_SDK_ROOT")) {
+        return Some(dir);
+    }
+    let default = if cfg!(windows) {
+        var("LOCALAPPDATA")?.join("Android").join("Sdk")
+    } else if cfg!(target_os = "macos") {
+        var("HOME")?.join("Library").join("Android").join("sdk")
+    } else {
+        var("HOME")?.join("Android").join("Sdk")
+    };
+    default.is_dir().then_some(default)
+}
+
+/// The Android NDK directory: `ANDROID_NDK_HOME`, `ANDROID_NDK_ROOT`, or the newest NDK
+/// installed in `sdk_dir`, if any.
+pub fn ndk_dir(sdk_dir: Option<&Path>) -> Option<PathBuf> {
+    if let Some(dir) = var("ANDROID_NDK_HOME").or_else(|| var("ANDROID_NDK_ROOT")) {
+        return Some(dir);
+    }
+    let sdk_dir = sdk_dir?;
+    crate::versions::newest(&sdk_dir.join("ndk")).or_else(|| {
+        let bundle = sdk_dir.join("ndk-bundle");
+        bundle.is_dir().then_some(bundle)
+    })
+}
+
+/// The directories with the tools of the SDK at `sdk_dir` and the NDK at `ndk_dir`, in search
+/// order. Only those that exist are returned.
+///
+/// These are the SDK's `platform-tools`, its newest `build-tools`, `cmdline-tools/latest/bin`
+/// and `emulator`, then the NDK's LLVM toolchain and the NDK itself, for `ndk-build`.
+pub fn tool_dirs(sdk_dir: Option<&Path>, ndk_dir: Option<&Path>) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(sdk_dir) = sdk_dir {
+        dirs.push(sdk_dir.join("platform-tools"));
+        dirs.extend(crate::versions::newest(&sdk_dir.join("build-tools")));
+        dirs.push(sdk_dir.join("cmdline-tools").join("latest").join("bin"));
+        dirs.push(sdk_dir.join("emulator"));
+    }
+    if let Some(ndk_dir) = ndk_dir {
+        // One per host, e.g. `linux-x86_64`, though normally only that of the current one.
+        let prebuilt = ndk_dir.join("toolchains").join("llvm").join("prebuilt");
+        if let Ok(entries) = fs::read_dir(&prebuilt) {
+            let mut hosts: Vec<PathBuf> = entries
+                .flatten()
+                .map(|entry| entry.path().join("bin"))
+                .collect();
+            hosts.sort();
+            dirs.extend(hosts);
+        }
+        dirs.push(ndk_dir.to_path_buf());
+    }
+    dirs.retain(|dir| dir.is_dir());
+    dirs
+}
+
+/// Finds the Android tool `name`, such as `adb`, `aapt2` or `aarch64-linux-android34-clang`, in
+/// the [`tool_dirs`] of the [`sdk_dir`] and [`ndk_dir`], then on `PATH`.
+///
+/// # Example
+///
+/// ```no_run
+/// let adb = which::android::find_tool("adb").unwrap();
+/// ```
+pub fn find_tool<T: AsRef<OsStr>>(name: T) -> crate::Result<PathBuf> {
+    let sdk_dir = sdk_dir();
+    let ndk_dir = ndk_dir(sdk_dir.as_deref());
+    let mut dirs = tool_dirs(sdk_dir.as_deref(), ndk_dir.as_deref());
+    if let Some(path) = crate::environment::var_os("PATH") {
+        dirs.extend(env::split_paths(&path));
+    }
+    let paths = env::join_paths(dirs).map_err(|_| crate::Error::CannotFindBinaryPath)?;
+    crate::WhichConfig::new()
+        .custom_path_list(paths)
+        .system_cwd(false)
+        .binary_name(name.as_ref().to_os_string())
+        .first_result()
+}
+
+/// A non-empty environment variable, as a path.
+fn var(name: &str) -> Option<PathBuf> {
+    crate::environment::var_os(name)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tool_dirs() {
+        let root = tempfile::tempdir().unwrap();
+        let sdk = root.path().join("sdk");
+        for dir in [
+            "platform-tools",
+            "build-tools/9.0.0",
+            "build-tools/34.0.0",
+            "build-tools/docs",
+            "ndk/26.1.10909125/toolchains/llvm/prebuilt/linux-x86_64/bin",
+        ] {
+            fs::create_dir_all(sdk.join(dir)).unwrap();
+        }
+
+        let ndk = crate::versions::newest(&sdk.join("ndk")).unwrap();
+        assert_eq!(ndk, sdk.join("ndk").join("26.1.10909125"));
+        assert_eq!(
+            tool_dirs(Some(&sdk), Some(&ndk)),
+            vec![
+                sdk.join("platform-tools"),
+                sdk.join("build-tools").join("34.0.0"),
+                ndk.join("toolchains/llvm/prebuilt/linux-x86_64/bin"),
+                ndk.clone(),
+            ]
+        );
+        assert_eq!(tool_dirs(None, None), Vec::<PathBuf>::new());
+    }
+}
@@ -0,0 +1,339 @@
+//! Caching of filesystem state shared between lookups.
+
+use crate::error::{Error, Result};
+use crate::listing::DirListing;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+#[cfg(feature = "notify")]
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "notify")]
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Listings of directories modified this recently aren't cached, since another change within
+/// the filesystem's timestamp granularity would go unnoticed.
+const RACY_WINDOW: Duration = Duration::from_secs(2);
+
+/// What a [`which_cached`](crate::which_cached) result depends on.
+#[derive(Hash, PartialEq, Eq)]
+struct LookupKey {
+    binary_name: OsString,
+    paths: Option<OsString>,
+    /// Which extensions names expand with.
+    #[cfg(windows)]
+    path_extensions: Option<OsString>,
+    /// Only set for names containing a separator, which are resolved against it.
+    cwd: Option<PathBuf>,
+    /// That of the default configuration the lookup was made with.
+    config: u64,
+}
+
+static LOOKUPS: Lazy<Mutex<HashMap<LookupKey, PathBuf>>> = Lazy::new(Default::default);
+
+/// Failed lookups.
+static MISSES: Lazy<Mutex<HashMap<LookupKey, Miss>>> = Lazy::new(Default::default);
+
+struct Miss {
+    /// `None` if it's too far off to represent, in which case the failure never expires.
+    expires: Option<Instant>,
+    error: Error,
+}
+
+impl Miss {
+    fn expired(&self) -> bool {
+        self.expires
+            .is_some_and(|expires| Instant::now() >= expires)
+    }
+}
+
+static NEGATIVE_TTL: Mutex<Option<Duration>> = Mutex::new(None);
+
+/// Forgets every result remembered by [`which_cached`](crate::which_cached), including cached
+/// failures.
+///
+/// Call this after installing or removing tools, since cached results are never revalidated.
+pub fn clear() {
+    LOOKUPS.lock().unwrap().clear();
+    MISSES.lock().unwrap().clear();
+}
+
+/// Sets how long [`which_cached`](crate::which_cached) remembers failed lookups, or disables
+/// caching them with `None`, which is the default.
+///
+/// Unlike successful lookups, which are kept until [`clear`] is called, failures expire on their
+/// own, so a tool installed later is eventually picked up. A TTL too large to represent, such as
+/// [`Duration::MAX`], keeps them until [`clear`] is called.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// which::cache::set_negative_ttl(Some(Duration::from_secs(30)));
+/// for tool in ["gcc", "clang", "icc", "tcc"] {
+///     let _ = which::which_cached(tool);
+/// }
+/// ```
+pub fn set_negative_ttl(ttl: Option<Duration>) {
+    *NEGATIVE_TTL.lock().unwrap() = ttl;
+    if ttl.is_none() {
+        MISSES.lock().unwrap().clear();
+    }
+}
+
+pub(crate) fn cached_lookup<F>(binary_name: &OsStr, lookup: F) -> Result<PathBuf>
+where
+    F: FnOnce() -> Result<PathBuf>,
+{
+    let cwd = if Path::new(binary_name).components().count() > 1 {
+        crate::environment::current_dir()
+    } else {
+        None
+    };
+    let key = LookupKey {
+        binary_name: binary_name.to_os_string(),
+        paths: crate::environment::var_os("PATH"),
+        #[cfg(windows)]
+        path_extensions: crate::environment::var_os("PATHEXT"),
+        cwd,
+        config: crate::config_generation(),
+    };
+
+    if let Some(path) = LOOKUPS.lock().unwrap().get(&key) {
+        return Ok(path.clone());
+    }
+
+    {
+        let mut misses = MISSES.lock().unwrap();
+        match misses.get(&key) {
+            Some(miss) if !miss.expired() => return Err(miss.error.clone()),
+            Some(_) => {
+                misses.remove(&key);
+            }
+            None => {}
+        }
+    }
+
+    match lookup() {
+        Ok(path) => {
+            LOOKUPS.lock().unwrap().insert(key, path.clone());
+            Ok(path)
+        }
+        Err(error) => {
+            if let Some(ttl) = *NEGATIVE_TTL.lock().unwrap() {
+                let miss = Miss {
+                    expires: Instant::now().checked_add(ttl),
+                    error: error.clone(),
+                };
+                MISSES.lock().unwrap().insert(key, miss);
+            }
+            Err(error)
+        }
+    }
+}
+
+/// A cache of directory listings, shared by every lookup it's passed to.
+///
+/// Listings are keyed by directory and invalidated whenever the directory's modification time
+/// changes, which happens whenever an entry is added, removed or renamed. Only searches that list
+/// whole directories, such as regex searches, consult it.
+///
+/// With the `notify` feature, [`WhichCache::watching`] creates a cache that is instead invalidated
+/// by filesystem events, which suits long-lived processes such as language servers.
+///
+/// Cloning a `WhichCache` is cheap and yields a handle to the same cache.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "regex")] {
+/// use regex::Regex;
+/// use which::{WhichCache, WhichConfig};
+///
+/// let cache = WhichCache::new();
+/// for pattern in ["^cargo-", "^rustc"] {
+///     let found: Vec<_> = WhichConfig::new()
+///         .cache(cache.clone())
+///         .system_cwd(false)
+///         .regex(Regex::new(pattern).unwrap())
+///         .all_results()
+///         .unwrap()
+///         .collect();
+/// }
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct WhichCache {
+    listings: Arc<Mutex<HashMap<PathBuf, CachedListing>>>,
+    #[cfg(feature = "notify")]
+    watcher: Option<Arc<DirWatcher>>,
+}
+
+struct CachedListing {
+    /// `None` for listings of watched directories, which stay valid until an event says otherwise.
+    modified: Option<SystemTime>,
+    listing: Arc<DirListing>,
+}
+
+#[cfg(feature = "notify")]
+struct DirWatcher {
+    watcher: Mutex<notify::RecommendedWatcher>,
+    /// Directories with a watch in place, forgotten once they're removed or renamed away, since
+    /// the watch goes with them.
+    watched: Arc<Mutex<HashSet<PathBuf>>>,
+    /// Bumped on every event, so a listing read while an event came in is never stored.
+    events: Arc<AtomicU64>,
+}
+
+#[cfg(feature = "notify")]
+impl DirWatcher {
+    fn watch(&self, dir: &Path) -> bool {
+        use notify::Watcher;
+
+        // Holding the watcher serializes this, so nobody sees a directory as watched before its
+        // watch is armed. `watched` itself isn't held across the call, since arming a watch waits
+        // on the event thread, which takes it to forget directories. The directory is recorded
+        // first, so a removal reported right after the watch is armed isn't undone.
+        let mut watcher = self.watcher.lock().unwrap();
+        if !self.watched.lock().unwrap().insert(dir.to_path_buf()) {
+            return true;
+        }
+        let ok = watcher
+            .watch(dir, notify::RecursiveMode::NonRecursive)
+            .is_ok();
+        if !ok {
+            self.watched.lock().unwrap().remove(dir);
+        }
+        ok
+    }
+}
+
+impl WhichCache {
+    pub fn new() -> WhichCache {
+        WhichCache::default()
+    }
+
+    /// Creates a cache that watches every directory it lists and drops a listing as soon as an
+    /// entry in it is added, removed or renamed.
+    ///
+    /// Cached listings are then used without checking the directory's modification time at all.
+    /// Directories that can't be watched, e.g. because the watch limit was reached, fall back to
+    /// modification time validation.
+    #[cfg(feature = "notify")]
+    pub fn watching() -> notify::Result<WhichCache> {
+        let listings: Arc<Mutex<HashMap<PathBuf, CachedListing>>> = Default::default();
+        let events = Arc::new(AtomicU64::new(0));
+        let watched: Arc<Mutex<HashSet<PathBuf>>> = Default::default();
+
+        let handler = {
+            let listings = listings.clone();
+            let events = events.clone();
+            let watched = watched.clone();
+            move |event: notify::Result<notify::Event>| {
+                use notify::event::{EventKind, ModifyKind};
+
+                events.fetch_add(1, Ordering::SeqCst);
+                let mut listings = listings.lock().unwrap();
+                match event {
+                    Ok(event) => {
+                        if let EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(_)) =
+                            event.kind
+                        {
+                            let mut watched = watched.lock().unwrap();
+                            for path in &event.paths {
+                                watched.remove(path);
+                            }
+                        }
+                        for path in &event.paths {
+                            listings.remove(path);
+                            if let Some(parent) = path.parent() {
+                                listings.remove(parent);
+                            }
+                        }
+                    }
+                    // Events may have been lost, so nothing cached can be trusted anymore.
+                    Err(_) => listings.clear(),
+                }
+            }
+        };
+
+        Ok(WhichCache {
+            listings,
+            watcher: Some(Arc::new(DirWatcher {
+                watcher: Mutex::new(notify::recommended_watcher(handler)?),
+                watched,
+                events,
+            })),
+        })
+    }
+
+    /// Forgets every cached listing.
+    pub fn clear(&self) {
+        self.listings.lock().unwrap().clear();
+    }
+
+    pub(crate) fn listing(&self, dir: &Path) -> io::Result<Arc<DirListing>> {
+        #[cfg(feature = "notify")]
+        if let Some(watcher) = &self.watcher {
+            if let Some(cached) = self.listings.lock().unwrap().get(dir) {
+                if cached.modified.is_none() {
+                    return Ok(cached.listing.clone());
+                }
+            }
+
+            // The watch has to be in place before reading, or changes in between would be missed.
+            if watcher.watch(dir) {
+                let events = watcher.events.load(Ordering::SeqCst);
+                let listing = Arc::new(DirListing::read(dir)?);
+
+                let mut listings = self.listings.lock().unwrap();
+                if watcher.events.load(Ordering::SeqCst) == events {
+                    listings.insert(
+                        dir.to_path_buf(),
+                        CachedListing {
+                            modified: None,
+                            listing: listing.clone(),
+                        },
+                    );
+                }
+                return Ok(listing);
+            }
+        }
+
+        let modified = match fs::metadata(dir).and_then(|m| m.modified()) {
+            Ok(modified) => modified,
+            // Without a modification time there's nothing to validate a cached listing against.
+            Err(_) => return DirListing::read(dir).map(Arc::new),
+        };
+
+        if let Some(cached) = self.listings.lock().unwrap().get(dir) {
+            if cached.modified == Some(modified) {
+                return Ok(cached.listing.clone());
+            }
+        }
+
+        let listing = Arc::new(DirListing::read(dir)?);
+        let settled = SystemTime::now()
+            .duration_since(modified)
+            .is_ok_and(|age| age >= RACY_WINDOW);
+
+        let mut listings = self.listings.lock().unwrap();
+        if settled {
+            listings.insert(
+                dir.to_path_buf(),
+                CachedListing {
+                    modified: Some(modified),
+                    listing: listing.clone(),
+                },
+            );
+        } else {
+            listings.remove(dir);
+        }
+        Ok(listing)
+    }
+}
@@ -0,0 +1,105 @@
+//! Finding binaries built in a Cargo workspace, so test harnesses and tools run the artifact
+//! that was just built rather than an older one installed on `PATH`.
+//!
+//! Integration tests of the package that builds the binary don't need this: Cargo passes its
+//! path to the compiler as `CARGO_BIN_EXE_<name>`, to be read with `env!`.
+
+use crate::{Origin, WhichConfig};
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// The directory Cargo builds the workspace at `workspace_root` in: `CARGO_TARGET_DIR`,
+/// resolved against the working directory if relative as Cargo does, or else the root's
+/// `target`.
+pub fn target_dir<P: AsRef<Path>>(workspace_root: P) -> PathBuf {
+    match crate::environment::var_os("CARGO_TARGET_DIR").filter(|dir| !dir.is_empty()) {
+        Some(dir) => match crate::environment::current_dir() {
+            Some(cwd) => cwd.join(dir),
+            None => PathBuf::from(dir),
+        },
+        None => workspace_root.as_ref().join("target"),
+    }
+}
+
+/// The directories Cargo builds the binaries of the workspace at `workspace_root` in, that
+/// exist, see [`profile_dirs_in`].
+pub fn profile_dirs<P: AsRef<Path>>(workspace_root: P) -> Vec<PathBuf> {
+    profile_dirs_in(target_dir(workspace_root))
+}
+
+/// The directories Cargo builds binaries in within the target directory `target_dir`, that
+/// exist: `debug` and `release`, then those of each target triple built for with `--target`,
+/// like `x86_64-unknown-linux-musl/release`.
+pub fn profile_dirs_in<P: AsRef<Path>>(target_dir: P) -> Vec<PathBuf> {
+    let target_dir = target_dir.as_ref();
+    let mut triples: Vec<PathBuf> = fs::read_dir(target_dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|dir| !PROFILES.iter().any(|profile| dir.ends_with(profile)))
+        .collect();
+    triples.sort();
+    std::iter::once(target_dir.to_path_buf())
+        .chain(triples)
+        .flat_map(|dir| PROFILES.iter().map(move |profile| dir.join(profile)))
+        .filter(|dir| dir.is_dir())
+        .collect()
+}
+
+/// The profiles binaries are built with unless a custom one is asked for.
+const PROFILES: [&str; 2] = ["debug", "release"];
+
+/// Finds the binary `binary_name` of the workspace at `workspace_root`, or else on `PATH`.
+///
+/// The most recently modified of the binaries in the [`profile_dirs`] is taken, so that it
+/// doesn't matter which profile or target was built last.
+///
+/// # Example
+///
+/// ```no_run
+/// let cli = which::cargo::find_built("my-cli", env!("CARGO_MANIFEST_DIR")).unwrap();
+/// ```
+pub fn find_built<T, P>(binary_name: T, workspace_root: P) -> crate::Result<PathBuf>
+where
+    T: AsRef<OsStr>,
+    P: AsRef<Path>,
+{
+    find_built_in(binary_name, target_dir(workspace_root))
+}
+
+/// Like [`find_built`], but in the target directory `target_dir`.
+pub fn find_built_in<T, P>(binary_name: T, target_dir: P) -> crate::Result<PathBuf>
+where
+    T: AsRef<OsStr>,
+    P: AsRef<Path>,
+{
+    let results = profile_dirs_in(target_dir)
+        .into_iter()
+        .fold(WhichConfig::new(), WhichConfig::project_dir)
+        .binary_name(binary_name.as_ref().to_owned())
+        .all_results_with_origin()?;
+
+    let mut built = Vec::new();
+    for (path, origin) in results {
+        match origin {
+            Origin::Project(_) => built.push(path),
+            // Installed elsewhere, and only taken if nothing was built.
+            _ if built.is_empty() => return Ok(path),
+            _ => break,
+        }
+    }
+    // Stable, so debug wins a tie.
+    built.sort_by_key(|path| std::cmp::Reverse(modified(path)));
+    built
+        .into_iter()
+        .next()
+        .ok_or(crate::Error::CannotFindBinaryPath)
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    path.metadata()
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
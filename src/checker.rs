@@ -1,60 +1,661 @@
-use crate::finder::Checker;
-#[cfg(any(unix, target_os = "wasi"))]
-use std::ffi::CString;
+use crate::finder::{Checker, Cost, PathExtensions};
+use crate::vfs::Filesystem;
+#[cfg(unix)]
+use once_cell::sync::Lazy;
+use std::cell::OnceCell;
 use std::fs;
+use std::io::{self, Read};
 #[cfg(unix)]
-use std::os::unix::ffi::OsStrExt;
-#[cfg(target_os = "wasi")]
-use std::os::wasi::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
 use std::path::Path;
 
-pub struct ExecutableChecker;
+/// The kind of file a candidate turned out to be.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum FileKind {
+    File,
+    Dir,
+    Symlink,
+    Other,
+}
+
+/// The parts of a file's metadata the checkers look at.
+#[derive(Clone, Debug)]
+pub struct FileInfo {
+    kind: FileKind,
+    #[cfg(unix)]
+    mode: u32,
+    #[cfg(unix)]
+    uid: u32,
+    #[cfg(unix)]
+    gid: u32,
+    #[cfg(windows)]
+    attributes: u32,
+    size: Option<u64>,
+}
+
+impl FileInfo {
+    pub(crate) fn from_metadata(metadata: &fs::Metadata) -> FileInfo {
+        let file_type = metadata.file_type();
+        let kind = if file_type.is_file() {
+            FileKind::File
+        } else if file_type.is_dir() {
+            FileKind::Dir
+        } else if file_type.is_symlink() {
+            FileKind::Symlink
+        } else {
+            FileKind::Other
+        };
+
+        FileInfo {
+            kind,
+            #[cfg(unix)]
+            mode: metadata.mode(),
+            #[cfg(unix)]
+            uid: metadata.uid(),
+            #[cfg(unix)]
+            gid: metadata.gid(),
+            #[cfg(windows)]
+            attributes: std::os::windows::fs::MetadataExt::file_attributes(metadata),
+            size: Some(metadata.len()),
+        }
+    }
+
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    pub(crate) fn from_statx(statx: &libc::statx) -> FileInfo {
+        let mode = u32::from(statx.stx_mode);
+        let kind = match mode & libc::S_IFMT {
+            libc::S_IFREG => FileKind::File,
+            libc::S_IFDIR => FileKind::Dir,
+            libc::S_IFLNK => FileKind::Symlink,
+            _ => FileKind::Other,
+        };
+
+        FileInfo {
+            kind,
+            mode,
+            uid: statx.stx_uid,
+            gid: statx.stx_gid,
+            size: Some(statx.stx_size),
+        }
+    }
+
+    /// Metadata for a file of the given kind, e.g. for a [`VirtualFs`](crate::VirtualFs).
+    ///
+    /// On Unix, it's owned by root with mode `0o755`, so anyone may run it. Its size is
+    /// unknown.
+    pub fn new(kind: FileKind) -> FileInfo {
+        FileInfo {
+            kind,
+            #[cfg(unix)]
+            mode: file_type_bits(kind) | 0o755,
+            #[cfg(unix)]
+            uid: 0,
+            #[cfg(unix)]
+            gid: 0,
+            #[cfg(windows)]
+            attributes: 0,
+            size: None,
+        }
+    }
+
+    /// Replaces the permission bits, keeping the file type.
+    #[cfg(unix)]
+    pub fn with_mode(mut self, mode: u32) -> FileInfo {
+        self.mode = file_type_bits(self.kind) | (mode & 0o7777);
+        self
+    }
+
+    /// Replaces the owner's user and group IDs.
+    #[cfg(unix)]
+    pub fn with_owner(mut self, uid: u32, gid: u32) -> FileInfo {
+        self.uid = uid;
+        self.gid = gid;
+        self
+    }
+
+    /// Replaces the file's attributes, as in `dwFileAttributes`.
+    #[cfg(windows)]
+    pub fn with_attributes(mut self, attributes: u32) -> FileInfo {
+        self.attributes = attributes;
+        self
+    }
+
+    /// Sets the size of the file, in bytes.
+    pub fn with_size(mut self, size: u64) -> FileInfo {
+        self.size = Some(size);
+        self
+    }
+
+    /// The kind of file this is. Symlinks are only reported on Windows, everywhere else they
+    /// are followed.
+    pub fn kind(&self) -> FileKind {
+        self.kind
+    }
+
+    /// The size of the file in bytes, if known. For symlinks, that's the size of the link.
+    pub fn size(&self) -> Option<u64> {
+        self.size
+    }
+
+    /// The file's attributes, as in `dwFileAttributes`.
+    #[cfg(windows)]
+    pub fn attributes(&self) -> u32 {
+        self.attributes
+    }
+
+    /// The file's permission bits, as in `st_mode`.
+    #[cfg(unix)]
+    pub fn mode(&self) -> u32 {
+        self.mode
+    }
+
+    /// The file owner's user ID.
+    #[cfg(unix)]
+    pub fn uid(&self) -> u32 {
+        self.uid
+    }
+
+    /// The file owner's group ID.
+    #[cfg(unix)]
+    pub fn gid(&self) -> u32 {
+        self.gid
+    }
+}
+
+// `mode_t` is narrower than `st_mode` on some platforms, e.g. macOS.
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast)]
+fn file_type_bits(kind: FileKind) -> u32 {
+    match kind {
+        FileKind::File => libc::S_IFREG as u32,
+        FileKind::Dir => libc::S_IFDIR as u32,
+        FileKind::Symlink => libc::S_IFLNK as u32,
+        FileKind::Other => 0,
+    }
+}
+
+/// A path under consideration, whose metadata is fetched at most once and shared by every checker.
+#[derive(Debug)]
+pub struct Candidate<'a> {
+    path: &'a Path,
+    info: OnceCell<Option<FileInfo>>,
+    /// Whether `info` was handed in rather than looked up by the candidate itself.
+    prefetched: bool,
+    /// The kind of file the candidate is, if known without `info`.
+    kind: Option<FileKind>,
+    /// Why `info` couldn't be looked up, if it was and failed.
+    error: OnceCell<io::Error>,
+    /// Where the candidate is looked up, if not the real filesystem.
+    filesystem: Option<&'a dyn Filesystem>,
+}
+
+impl<'a> Candidate<'a> {
+    pub fn new(path: &'a Path) -> Candidate<'a> {
+        Candidate {
+            path,
+            info: OnceCell::new(),
+            prefetched: false,
+            kind: None,
+            error: OnceCell::new(),
+            filesystem: None,
+        }
+    }
+
+    /// Creates a candidate whose kind is already known, so that checkers which look at nothing
+    /// else don't need its metadata.
+    pub(crate) fn with_kind(path: &'a Path, kind: FileKind) -> Candidate<'a> {
+        Candidate {
+            kind: Some(kind),
+            ..Candidate::new(path)
+        }
+    }
+
+    /// Creates a candidate whose metadata was already fetched, `None` meaning it doesn't exist.
+    pub(crate) fn with_info(path: &'a Path, info: Option<FileInfo>) -> Candidate<'a> {
+        Candidate {
+            path,
+            info: OnceCell::from(info),
+            prefetched: true,
+            kind: None,
+            error: OnceCell::new(),
+            filesystem: None,
+        }
+    }
+
+    /// Creates a candidate looked up in `filesystem` rather than the real filesystem.
+    pub(crate) fn in_filesystem(path: &'a Path, filesystem: &'a dyn Filesystem) -> Candidate<'a> {
+        Candidate {
+            filesystem: Some(filesystem),
+            ..Candidate::with_info(path, filesystem.metadata(path))
+        }
+    }
+
+    /// The path being checked.
+    pub fn path(&self) -> &Path {
+        self.path
+    }
+
+    /// Returns the candidate's metadata, or `None` if it doesn't exist or can't be queried.
+    pub fn info(&self) -> Option<&FileInfo> {
+        self.info
+            .get_or_init(|| match stat(self.path) {
+                Ok(metadata) => Some(FileInfo::from_metadata(&metadata)),
+                Err(error) => {
+                    let _ = self.error.set(error);
+                    None
+                }
+            })
+            .as_ref()
+    }
+
+    /// The kind of file the candidate is, or `None` if it doesn't exist. Unlike
+    /// [`Candidate::info`], this doesn't need a lookup when the directory listing the candidate
+    /// came from said what it is.
+    pub fn kind(&self) -> Option<FileKind> {
+        self.kind.or_else(|| self.info().map(FileInfo::kind))
+    }
+
+    /// Opens the candidate for reading, from the filesystem it's looked up in. Anything but a
+    /// regular file is refused, since opening a FIFO blocks until it has a writer, and opening a
+    /// device may have side effects.
+    pub(crate) fn open(&self) -> io::Result<Box<dyn Read>> {
+        let kind = match self.kind() {
+            // Listings don't say what symlinks point to.
+            Some(FileKind::Symlink) => match self.filesystem {
+                Some(filesystem) => filesystem.metadata(self.path).map(|info| info.kind()),
+                None => fs::metadata(self.path)
+                    .ok()
+                    .map(|metadata| FileInfo::from_metadata(&metadata).kind()),
+            },
+            kind => kind,
+        };
+        if kind != Some(FileKind::File) {
+            return Err(io::Error::other("not a regular file"));
+        }
+        match self.filesystem {
+            Some(filesystem) => filesystem.open(self.path),
+            None => Ok(Box::new(fs::File::open(self.path)?)),
+        }
+    }
+
+    /// Whether the candidate's metadata was handed in rather than looked up.
+    pub(crate) fn prefetched(&self) -> bool {
+        self.prefetched
+    }
+
+    /// Why [`Candidate::info`] couldn't be looked up, if it was and failed.
+    pub(crate) fn into_error(self) -> Option<io::Error> {
+        self.error.into_inner()
+    }
+
+    /// Whether [`Candidate::info`] had to be looked up for this candidate alone.
+    pub(crate) fn fetched(&self) -> bool {
+        !self.prefetched && self.info.get().is_some()
+    }
+}
+
+// Windows doesn't follow symlinks here so that app execution aliases, which can't be opened
+// with `metadata`, are still found.
+#[cfg(target_os = "windows")]
+fn stat(path: &Path) -> std::io::Result<fs::Metadata> {
+    fs::symlink_metadata(path)
+}
+
+#[cfg(not(target_os = "windows"))]
+fn stat(path: &Path) -> std::io::Result<fs::Metadata> {
+    fs::metadata(path)
+}
+
+pub struct ExecutableChecker {
+    /// Names with these extensions are Windows executables, which don't need execute bits.
+    #[cfg(unix)]
+    windows_extensions: Option<PathExtensions>,
+}
+
+/// The real user and group IDs and the supplementary groups of the process, which `access`
+/// checks against, captured by the first lookup rather than by every one.
+#[cfg(unix)]
+struct Credentials {
+    uid: u32,
+    gids: Vec<u32>,
+}
+
+#[cfg(unix)]
+static CREDENTIALS: Lazy<Credentials> = Lazy::new(|| {
+    let (uid, mut gids) = unsafe { (libc::getuid(), vec![libc::getgid()]) };
+
+    let count = unsafe { libc::getgroups(0, std::ptr::null_mut()) };
+    if count > 0 {
+        let mut groups: Vec<libc::gid_t> = vec![0; count as usize];
+        let count = unsafe { libc::getgroups(count, groups.as_mut_ptr()) };
+        groups.truncate(count.max(0) as usize);
+        gids.extend(groups);
+    }
+
+    Credentials { uid, gids }
+});
 
 impl ExecutableChecker {
+    #[cfg(unix)]
+    pub fn new() -> ExecutableChecker {
+        ExecutableChecker {
+            windows_extensions: None,
+        }
+    }
+
+    #[cfg(not(unix))]
     pub fn new() -> ExecutableChecker {
-        ExecutableChecker
+        ExecutableChecker {}
+    }
+
+    /// Accepts files whose names end in one of `extensions` whatever their permissions, the way
+    /// Windows does. Only has an effect on Unix.
+    #[cfg(unix)]
+    pub fn windows_extensions(mut self, extensions: Option<PathExtensions>) -> ExecutableChecker {
+        self.windows_extensions = extensions;
+        self
+    }
+
+    #[cfg(not(unix))]
+    pub fn windows_extensions(self, _extensions: Option<PathExtensions>) -> ExecutableChecker {
+        self
     }
 }
 
 impl Checker for ExecutableChecker {
-    #[cfg(any(unix, target_os = "wasi"))]
-    fn is_valid(&self, path: &Path) -> bool {
-        CString::new(path.as_os_str().as_bytes())
-            .map(|c| unsafe { libc::access(c.as_ptr(), libc::X_OK) == 0 })
-            .unwrap_or(false)
+    // The permission bits, evaluated the way `access(path, X_OK)` does against the real user
+    // and group IDs, with root allowed to run anything with at least one execute bit set, rule
+    // out most candidates from the shared metadata alone. Those left are confirmed with
+    // `access` itself, which also knows about `noexec` mounts, ACLs and security modules.
+    #[cfg(unix)]
+    fn is_valid(&self, candidate: &Candidate<'_>) -> bool {
+        let info = match candidate.info() {
+            Some(info) => info,
+            None => return false,
+        };
+
+        if let Some(extensions) = &self.windows_extensions {
+            if info.kind() == FileKind::File && extensions.matches(candidate.path()) {
+                return true;
+            }
+        }
+
+        let credentials = &*CREDENTIALS;
+        let bits = if credentials.uid == 0 {
+            0o111
+        } else if info.uid == credentials.uid {
+            0o100
+        } else if credentials.gids.contains(&info.gid) {
+            0o010
+        } else {
+            0o001
+        };
+        if info.mode & bits == 0 {
+            return false;
+        }
+        // Files of another filesystem aren't where their paths say, so their bits are all
+        // there is to go by.
+        candidate.filesystem.is_some() || access(candidate.path())
     }
 
-    #[cfg(windows)]
-    fn is_valid(&self, _path: &Path) -> bool {
+    // WASI has no users, so any execute bit will do. Runtimes that don't pass permissions
+    // through report none at all, and then every file is taken to be runnable, as are those
+    // of other filesystems, whose info has no mode.
+    #[cfg(target_os = "wasi")]
+    fn is_valid(&self, candidate: &Candidate<'_>) -> bool {
+        if candidate.filesystem.is_some() {
+            return true;
+        }
+        match libc_stat(candidate.path()) {
+            Ok(stat) => stat.st_mode & 0o777 == 0 || stat.st_mode & 0o111 != 0,
+            Err(_) => false,
+        }
+    }
+
+    #[cfg(target_os = "wasi")]
+    fn cost(&self) -> Cost {
+        Cost::Io
+    }
+
+    // Checks on Windows happen by extension when candidates are built, so nothing is left to
+    // look up here. Elsewhere there are no execute permissions to check.
+    #[cfg(not(any(unix, target_os = "wasi")))]
+    fn is_valid(&self, _candidate: &Candidate<'_>) -> bool {
         true
     }
+
+    #[cfg(not(any(unix, target_os = "wasi")))]
+    fn cost(&self) -> Cost {
+        Cost::Free
+    }
+}
+
+/// Whether the process may execute `path`, as `access(path, X_OK)` says.
+#[cfg(unix)]
+fn access(path: &Path) -> bool {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    match CString::new(path.as_os_str().as_bytes()) {
+        // SAFETY: `path` is nul terminated.
+        Ok(path) => unsafe { libc::access(path.as_ptr(), libc::X_OK) == 0 },
+        Err(_) => false,
+    }
 }
 
-pub struct ExistedChecker;
+/// `stat` through WASI libc, for the permission bits and file IDs `std` has no stable way to
+/// get at there.
+#[cfg(target_os = "wasi")]
+pub(crate) fn libc_stat(path: &Path) -> io::Result<libc::stat> {
+    use std::ffi::CString;
+    use std::mem::MaybeUninit;
+
+    // WASI paths are always UTF-8.
+    let path = path
+        .to_str()
+        .and_then(|path| CString::new(path).ok())
+        .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+    let mut stat = MaybeUninit::<libc::stat>::uninit();
+    // SAFETY: `path` is nul terminated and `stat` is large enough.
+    if unsafe { libc::stat(path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: the call succeeded, so it filled `stat` in.
+    Ok(unsafe { stat.assume_init() })
+}
+
+/// Accepts regular files, and on Windows, where they aren't followed, symlinks.
+///
+/// FIFOs, sockets and device nodes are rejected even if their execute bit is set, since opening
+/// one can block indefinitely, unless they are allowed with [`ExistedChecker::special_files`].
+pub struct ExistedChecker {
+    special_files: bool,
+}
 
 impl ExistedChecker {
     pub fn new() -> ExistedChecker {
-        ExistedChecker
+        ExistedChecker {
+            special_files: false,
+        }
+    }
+
+    /// Whether to accept files that are neither regular files nor directories as well.
+    pub fn special_files(mut self, special_files: bool) -> ExistedChecker {
+        self.special_files = special_files;
+        self
     }
 }
 
 impl Checker for ExistedChecker {
     #[cfg(target_os = "windows")]
-    fn is_valid(&self, path: &Path) -> bool {
-        fs::symlink_metadata(path)
-            .map(|metadata| {
-                let file_type = metadata.file_type();
-                file_type.is_file() || file_type.is_symlink()
+    fn is_valid(&self, candidate: &Candidate<'_>) -> bool {
+        candidate
+            .kind()
+            .map(|kind| match kind {
+                FileKind::File | FileKind::Symlink => true,
+                FileKind::Other => self.special_files,
+                FileKind::Dir => false,
             })
             .unwrap_or(false)
     }
 
     #[cfg(not(target_os = "windows"))]
-    fn is_valid(&self, path: &Path) -> bool {
-        fs::metadata(path)
-            .map(|metadata| metadata.is_file())
+    fn is_valid(&self, candidate: &Candidate<'_>) -> bool {
+        candidate
+            .kind()
+            .map(|kind| match kind {
+                FileKind::File => true,
+                FileKind::Other => self.special_files,
+                FileKind::Dir | FileKind::Symlink => false,
+            })
+            .unwrap_or(false)
+    }
+}
+
+/// Rejects empty files, which are usually leftovers of broken installs rather than anything
+/// runnable. Files of unknown size, and symlinks on Windows, are accepted.
+pub struct NonEmptyChecker;
+
+impl NonEmptyChecker {
+    pub fn new() -> NonEmptyChecker {
+        NonEmptyChecker
+    }
+}
+
+impl Checker for NonEmptyChecker {
+    fn is_valid(&self, candidate: &Candidate<'_>) -> bool {
+        candidate
+            .info()
+            .map(|info| info.kind() == FileKind::Symlink || info.size() != Some(0))
+            .unwrap_or(false)
+    }
+}
+
+/// The signatures [`SignatureChecker`] recognizes executables by.
+const SIGNATURES: &[&[u8]] = &[
+    // ELF
+    b"\x7fELF",
+    // Mach-O, 32 and 64-bit in either byte order, and universal binaries
+    b"\xfe\xed\xfa\xce",
+    b"\xfe\xed\xfa\xcf",
+    b"\xce\xfa\xed\xfe",
+    b"\xcf\xfa\xed\xfe",
+    b"\xca\xfe\xba\xbe",
+    // PE, which starts with a DOS header
+    b"MZ",
+    // Scripts
+    b"#!",
+];
+
+/// Rejects files that don't start with the signature of a known executable format: ELF, Mach-O,
+/// PE or a `#!` line. This catches data files that merely have their execute bit set, but also
+/// rejects scripts without a `#!` line, such as batch files on Windows.
+pub struct SignatureChecker;
+
+impl SignatureChecker {
+    pub fn new() -> SignatureChecker {
+        SignatureChecker
+    }
+}
+
+impl Checker for SignatureChecker {
+    fn is_valid(&self, candidate: &Candidate<'_>) -> bool {
+        let mut head = [0; 4];
+        let mut len = 0;
+        let mut file = match candidate.open() {
+            Ok(file) => file,
+            Err(_) => return false,
+        };
+        // Short reads are allowed, so read until the buffer is full or the file ends.
+        while len < head.len() {
+            match file.read(&mut head[len..]) {
+                Ok(0) => break,
+                Ok(n) => len += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(_) => return false,
+            }
+        }
+        SIGNATURES
+            .iter()
+            .any(|signature| head[..len].starts_with(signature))
+    }
+
+    fn cost(&self) -> Cost {
+        Cost::Io
+    }
+}
+
+/// Rejects hidden files: those whose name starts with a dot on Unix, and those with the hidden
+/// or system attribute on Windows.
+pub struct VisibleChecker;
+
+impl VisibleChecker {
+    pub fn new() -> VisibleChecker {
+        VisibleChecker
+    }
+}
+
+impl Checker for VisibleChecker {
+    #[cfg(windows)]
+    fn is_valid(&self, candidate: &Candidate<'_>) -> bool {
+        use windows_sys::Win32::Storage::FileSystem::{
+            FILE_ATTRIBUTE_HIDDEN, FILE_ATTRIBUTE_SYSTEM,
+        };
+        candidate
+            .info()
+            .map(|info| info.attributes() & (FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM) == 0)
             .unwrap_or(false)
     }
+
+    #[cfg(not(windows))]
+    fn is_valid(&self, candidate: &Candidate<'_>) -> bool {
+        !candidate
+            .path()
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+    }
+
+    #[cfg(not(windows))]
+    fn cost(&self) -> Cost {
+        Cost::Free
+    }
+}
+
+pub struct DirChecker;
+
+impl DirChecker {
+    pub fn new() -> DirChecker {
+        DirChecker
+    }
+}
+
+impl Checker for DirChecker {
+    #[cfg(target_os = "windows")]
+    fn is_valid(&self, candidate: &Candidate<'_>) -> bool {
+        match candidate.kind() {
+            Some(FileKind::Dir) => true,
+            // Unlike elsewhere, symlinks aren't followed when fetching metadata on Windows.
+            Some(FileKind::Symlink) => candidate.path().is_dir(),
+            _ => false,
+        }
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    fn is_valid(&self, candidate: &Candidate<'_>) -> bool {
+        candidate.kind() == Some(FileKind::Dir)
+    }
+}
+
+/// Adapts a closure into a [`Checker`].
+pub struct PredicateChecker<F>(pub F);
+
+impl<F> Checker for PredicateChecker<F>
+where
+    F: Fn(&Candidate<'_>) -> bool + Send + Sync,
+{
+    fn is_valid(&self, candidate: &Candidate<'_>) -> bool {
+        (self.0)(candidate)
+    }
 }
 
 pub struct CompositeChecker {
@@ -68,14 +669,28 @@ impl CompositeChecker {
         }
     }
 
+    /// Adds a checker, keeping the list ordered by cost. Checkers of equal cost run in the
+    /// order they were added.
     pub fn add_checker(mut self, checker: Box<dyn Checker>) -> CompositeChecker {
-        self.checkers.push(checker);
+        let cost = checker.cost();
+        let index = self.checkers.partition_point(|c| c.cost() <= cost);
+        self.checkers.insert(index, checker);
         self
     }
 }
 
 impl Checker for CompositeChecker {
-    fn is_valid(&self, path: &Path) -> bool {
-        self.checkers.iter().all(|checker| checker.is_valid(path))
+    fn is_valid(&self, candidate: &Candidate<'_>) -> bool {
+        self.checkers
+            .iter()
+            .all(|checker| checker.is_valid(candidate))
+    }
+
+    fn cost(&self) -> Cost {
+        self.checkers
+            .iter()
+            .map(|checker| checker.cost())
+            .max()
+            .unwrap_or(Cost::Free)
     }
 }
@@ -0,0 +1,52 @@
+use crate::finder::Origin;
+use std::ffi::{OsStr, OsString};
+use std::path::PathBuf;
+
+/// Executables in one directory whose names differ only by case, as returned by
+/// [`WhichConfig::case_collisions`](crate::WhichConfig::case_collisions).
+///
+/// A case-sensitive filesystem keeps them apart, so which one a lookup finds depends on how the
+/// name is spelled, and on a case-insensitive one, such as macOS' by default, only one of them
+/// could exist at all.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CaseCollision {
+    /// The directory they're in.
+    pub dir: PathBuf,
+    /// Where the directory is in the search.
+    pub origin: Origin,
+    /// The executables, sorted by name.
+    pub paths: Vec<PathBuf>,
+}
+
+/// `name` as it's compared: lowercased, Unicode-aware if it's valid Unicode.
+pub(crate) fn fold(name: &OsStr) -> OsString {
+    match name.to_str() {
+        Some(name) => name.to_lowercase().into(),
+        None => name.to_ascii_lowercase(),
+    }
+}
+
+/// Groups `results` by their directory, in search order, keeping those with more than one.
+pub(crate) fn collisions<I>(results: I) -> Vec<CaseCollision>
+where
+    I: IntoIterator<Item = (PathBuf, Origin)>,
+{
+    let mut groups: Vec<CaseCollision> = Vec::new();
+    for (path, origin) in results {
+        let dir = path.parent().map(PathBuf::from).unwrap_or_default();
+        match groups.last_mut() {
+            Some(group) if group.origin == origin && group.dir == dir => group.paths.push(path),
+            _ => groups.push(CaseCollision {
+                dir,
+                origin,
+                paths: vec![path],
+            }),
+        }
+    }
+    groups.retain(|group| group.paths.len() > 1);
+    for group in &mut groups {
+        group.paths.sort();
+    }
+    groups
+}
@@ -0,0 +1,170 @@
+//! Commands that run executables that were found, for [`Path::to_command`](crate::Path::to_command).
+
+use std::ffi::OsString;
+use std::path::Path;
+use std::process::Command;
+
+/// Where an executable was searched for, so that its interpreter is searched for there too.
+#[derive(Clone, Debug)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub(crate) enum FoundIn {
+    /// `PATH`, as [`which`](crate::which) searches it.
+    Env,
+    /// A path list given explicitly, or none.
+    Paths(Option<OsString>),
+}
+
+/// A command that runs the executable at `path`, through the interpreter it needs if it can't
+/// be started directly.
+///
+/// Only Windows needs this: Unix kernels follow `#!` lines themselves, and the standard library
+/// runs batch files through `cmd.exe`. Other files Windows searches `%PATHEXT%` for, though, are
+/// opened through file associations, which `CreateProcess` doesn't follow. Those that start with
+/// a `#!` line are run with the interpreter it names, found by its file name where `path` was
+/// found since the Unix path rarely exists. Windows Script Host, PowerShell and Python scripts
+/// and management consoles are run with their usual hosts.
+pub(crate) fn command(path: &Path, found_in: &FoundIn) -> Command {
+    #[cfg(windows)]
+    if let Some((interpreter, args)) = interpreter(path) {
+        let mut command =
+            Command::new(find(&interpreter, found_in).unwrap_or_else(|| interpreter.into()));
+        command.args(args).arg(path);
+        return command;
+    }
+    #[cfg(not(windows))]
+    let _ = found_in;
+    Command::new(path)
+}
+
+/// The interpreter `name` where the executable it runs was found.
+#[cfg(windows)]
+fn find(name: &str, found_in: &FoundIn) -> Option<std::path::PathBuf> {
+    match found_in {
+        FoundIn::Env => crate::which(name).ok(),
+        FoundIn::Paths(paths) => crate::which_in_global(name, paths.as_ref()).ok()?.next(),
+    }
+}
+
+/// The interpreter and its arguments that a file needs, or `None` if it can be started directly.
+#[cfg(any(windows, test))]
+fn interpreter(path: &Path) -> Option<(String, Vec<String>)> {
+    use std::io::{BufRead, BufReader, Read};
+
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    if ["exe", "com", "bat", "cmd"].contains(&extension.as_str()) {
+        return None;
+    }
+
+    let mut line = String::new();
+    let file = std::fs::File::open(path).ok()?;
+    // Binary files may not be valid UTF-8, and then simply have no `#!` line.
+    let _ = BufReader::new(file.take(512)).read_line(&mut line);
+    if let Some((interpreter, args)) = parse_shebang(&line) {
+        return Some((
+            interpreter.to_owned(),
+            args.into_iter().map(str::to_owned).collect(),
+        ));
+    }
+
+    let (host, args): (&str, &[&str]) = match extension.as_str() {
+        "vbs" | "vbe" | "js" | "jse" | "wsf" | "wsh" => ("cscript", &["//nologo"]),
+        "ps1" => ("powershell", &["-NoProfile", "-File"]),
+        "msc" => ("mmc", &[]),
+        "py" => ("py", &[]),
+        _ => return None,
+    };
+    Some((
+        host.to_owned(),
+        args.iter().map(|&arg| arg.to_owned()).collect(),
+    ))
+}
+
+/// The file name of the interpreter a `#!` line names, and the arguments to pass before the
+/// script, seeing through `/usr/bin/env` and its `-S`.
+#[cfg(any(windows, test))]
+fn parse_shebang(line: &str) -> Option<(&str, Vec<&str>)> {
+    let mut words = line.strip_prefix("#!")?.split_whitespace();
+    let mut interpreter = file_name(words.next()?);
+    if interpreter == "env" {
+        interpreter = words.next()?;
+        if interpreter == "-S" {
+            interpreter = words.next()?;
+        }
+    }
+    Some((interpreter, words.collect()))
+}
+
+/// The last component of a Unix path.
+#[cfg(any(windows, test))]
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_shebang() {
+        assert_eq!(parse_shebang("#!/bin/sh\n"), Some(("sh", vec![])));
+        assert_eq!(
+            parse_shebang("#! /usr/bin/python3 -u\n"),
+            Some(("python3", vec!["-u"]))
+        );
+        assert_eq!(
+            parse_shebang("#!/usr/bin/env node\r\n"),
+            Some(("node", vec![]))
+        );
+        assert_eq!(
+            parse_shebang("#!/usr/bin/env -S deno run --allow-read\n"),
+            Some(("deno", vec!["run", "--allow-read"]))
+        );
+        assert_eq!(parse_shebang("#!/usr/bin/env\n"), None);
+        assert_eq!(parse_shebang("echo hi\n"), None);
+    }
+
+    #[test]
+    fn test_interpreter() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = |name: &str, contents: &str| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, contents).unwrap();
+            path
+        };
+        let owned = |host: &str, args: &[&str]| {
+            Some((
+                host.to_owned(),
+                args.iter().map(|&a| a.to_owned()).collect(),
+            ))
+        };
+
+        assert_eq!(
+            interpreter(&file("build.ps1", "Write-Host hi\r\n")),
+            owned("powershell", &["-NoProfile", "-File"])
+        );
+        assert_eq!(
+            interpreter(&file("setup.VBS", "WScript.Echo 1\r\n")),
+            owned("cscript", &["//nologo"])
+        );
+        assert_eq!(
+            interpreter(&file("tool.py", "#!/usr/bin/env python3 -u\n")),
+            owned("python3", &["-u"])
+        );
+        assert_eq!(
+            interpreter(&file("tool.py", "print(1)\n")),
+            owned("py", &[])
+        );
+        assert_eq!(
+            interpreter(&file("serve", "#!/usr/bin/env node\n")),
+            owned("node", &[])
+        );
+        assert_eq!(interpreter(&file("tool.exe", "#!/bin/sh\n")), None);
+        assert_eq!(interpreter(&file("tool.cmd", "@echo off\r\n")), None);
+        assert_eq!(interpreter(&file("data", "\u{0}\u{ff}")), None);
+        assert_eq!(interpreter(&dir.path().join("missing.ps1")), None);
+    }
+}
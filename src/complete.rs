@@ -0,0 +1,130 @@
+use crate::checker::Candidate;
+use crate::finder::{Checker, Finder};
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::path::PathBuf;
+
+/// Lists the executables on the path whose names start with a prefix, for shell completion.
+///
+/// Each directory is read once, and by default nothing else is: directories are left out using
+/// the file types directory listings carry on most platforms, but other files aren't checked
+/// for execute permissions unless [`Self::verify`] is enabled. On Windows, only files with a
+/// `%PATHEXT%` extension count, which needs no checking, and names are completed without it.
+///
+/// # Example
+///
+/// ```no_run
+/// use which::Completer;
+///
+/// for completion in Completer::new().complete("car") {
+///     println!("{}", completion.name.to_string_lossy());
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Completer {
+    paths: Option<OsString>,
+    verify: bool,
+}
+
+/// A name [`Completer`] completed to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Completion {
+    /// The name, like `cargo`.
+    pub name: OsString,
+    /// The executable it runs, from the first directory that has one.
+    pub path: PathBuf,
+}
+
+impl Completer {
+    /// Completes from `$PATH`.
+    pub fn new() -> Completer {
+        Completer::default()
+    }
+
+    /// Completes from the path list `paths` instead.
+    pub fn paths(mut self, paths: OsString) -> Completer {
+        self.paths = Some(paths);
+        self
+    }
+
+    /// Whether every candidate is checked like a lookup checks it, which costs a `stat` per
+    /// file but leaves out those that couldn't run. Disabled by default. Names whose
+    /// executable in one directory fails the check can still complete to one in a later
+    /// directory.
+    pub fn verify(mut self, verify: bool) -> Completer {
+        self.verify = verify;
+        self
+    }
+
+    /// The executables whose names start with `prefix`, case-insensitively on Windows, sorted
+    /// by name. Each name is listed once, with the executable that takes precedence.
+    pub fn complete<T: AsRef<OsStr>>(&self, prefix: T) -> Vec<Completion> {
+        let paths = match self
+            .paths
+            .clone()
+            .or_else(|| crate::environment::var_os("PATH"))
+        {
+            Some(paths) => paths,
+            None => return Vec::new(),
+        };
+        let prefix = fold(prefix.as_ref());
+        let checker = self.verify.then(crate::build_binary_checker);
+        #[cfg(windows)]
+        let extensions = crate::win::path_extensions();
+
+        let mut seen = HashSet::new();
+        let mut completions = Vec::new();
+        for dir in Finder::new().search_dirs(Some(paths)) {
+            let entries = match fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            for entry in entries.flatten() {
+                let file_name = entry.file_name();
+                #[cfg(windows)]
+                let name = {
+                    let path = std::path::Path::new(&file_name);
+                    if !crate::win::has_executable_extension(path, &extensions) {
+                        continue;
+                    }
+                    path.file_stem().unwrap_or_default().to_owned()
+                };
+                #[cfg(not(windows))]
+                let name = file_name;
+                let key = fold(&name);
+                if !key
+                    .as_encoded_bytes()
+                    .starts_with(prefix.as_encoded_bytes())
+                    || seen.contains(&key)
+                {
+                    continue;
+                }
+                // Free from the listing where the platform provides it, a `stat` otherwise.
+                if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+                    continue;
+                }
+                let path = entry.path();
+                if let Some(checker) = &checker {
+                    if !checker.is_valid(&Candidate::new(&path)) {
+                        continue;
+                    }
+                }
+                seen.insert(key);
+                completions.push(Completion { name, path });
+            }
+        }
+        completions.sort_by(|a, b| a.name.cmp(&b.name));
+        completions
+    }
+}
+
+/// `name` as it's compared: lowercased on Windows, where file names are case-insensitive.
+fn fold(name: &OsStr) -> OsString {
+    if cfg!(windows) {
+        name.to_ascii_lowercase()
+    } else {
+        name.to_owned()
+    }
+}
@@ -0,0 +1,94 @@
+//! The names cross-compilation toolchains give their tools, for
+//! [`which_tool_for_target`](crate::which_tool_for_target).
+
+use std::path::PathBuf;
+
+/// The prefixes the tools of a toolchain for the Rust target triple `target` may have, in order
+/// of preference, without the trailing `-`.
+///
+/// Besides the triple itself, these are the GNU spellings of it that distributions use, like
+/// `aarch64-linux-gnu` for `aarch64-unknown-linux-gnu`, `x86_64-w64-mingw32` for
+/// `x86_64-pc-windows-gnu` and `arm-none-eabi` for `thumbv7em-none-eabihf`.
+pub fn prefixes(target: &str) -> Vec<String> {
+    let parts: Vec<&str> = target.split('-').collect();
+    let mut prefixes = vec![target.to_owned()];
+    let arch = parts[0];
+    let gnu_arch = gnu_arch(arch);
+    match parts[1..] {
+        [_, "windows", "gnu" | "gnullvm"] => {
+            prefixes.push(format!("{}-w64-mingw32", gnu_arch));
+        }
+        [vendor, os, env] => {
+            if gnu_arch != arch {
+                prefixes.push(format!("{}-{}-{}-{}", gnu_arch, vendor, os, env));
+            }
+            prefixes.push(format!("{}-{}-{}", gnu_arch, os, env));
+        }
+        ["none", _] if gnu_arch == "arm" => prefixes.push("arm-none-eabi".to_owned()),
+        [vendor, os] if gnu_arch != arch => {
+            prefixes.push(format!("{}-{}-{}", gnu_arch, vendor, os));
+        }
+        _ => {}
+    }
+
+    let mut unique: Vec<String> = Vec::new();
+    for prefix in prefixes {
+        if !unique.contains(&prefix) {
+            unique.push(prefix);
+        }
+    }
+    unique
+}
+
+/// The directories toolchains with `prefixes` keep their unprefixed tools in, such as
+/// `/usr/aarch64-linux-gnu/bin`.
+pub fn sysroot_bin_dirs(prefixes: &[String]) -> Vec<PathBuf> {
+    prefixes
+        .iter()
+        .map(|prefix| PathBuf::from("/usr").join(prefix).join("bin"))
+        .collect()
+}
+
+/// The architecture as GNU toolchains name it, where that's less specific than Rust's.
+fn gnu_arch(arch: &str) -> &str {
+    if arch.starts_with("arm") && arch != "arm64" || arch.starts_with("thumb") {
+        "arm"
+    } else if arch.starts_with("riscv64") {
+        "riscv64"
+    } else if arch.starts_with("riscv32") {
+        "riscv32"
+    } else {
+        arch
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_prefixes() {
+        assert_eq!(
+            prefixes("aarch64-unknown-linux-gnu"),
+            ["aarch64-unknown-linux-gnu", "aarch64-linux-gnu"]
+        );
+        assert_eq!(
+            prefixes("armv7-unknown-linux-gnueabihf"),
+            [
+                "armv7-unknown-linux-gnueabihf",
+                "arm-unknown-linux-gnueabihf",
+                "arm-linux-gnueabihf"
+            ]
+        );
+        assert_eq!(
+            prefixes("x86_64-pc-windows-gnu"),
+            ["x86_64-pc-windows-gnu", "x86_64-w64-mingw32"]
+        );
+        assert_eq!(
+            prefixes("thumbv7em-none-eabihf"),
+            ["thumbv7em-none-eabihf", "arm-none-eabi"]
+        );
+        assert_eq!(prefixes("aarch64-apple-darwin"), ["aarch64-apple-darwin"]);
+        assert_eq!(prefixes("wasm32"), ["wasm32"]);
+    }
+}
@@ -0,0 +1,128 @@
+use crate::build_binary_checker;
+use crate::error::*;
+use crate::finder::Finder;
+use crate::listing::DirListing;
+use crate::split::PATH_SEPARATOR;
+use std::collections::BTreeSet;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+
+/// Where [`path_diff`] puts the new directory.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Placement {
+    /// Before every other entry, so it takes precedence.
+    Prepend,
+    /// After every other entry.
+    Append,
+}
+
+/// How adding a directory to `PATH` changes what names resolve to, as computed by [`path_diff`].
+///
+/// Each list is sorted by name.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PathDiff {
+    /// Names that couldn't be found before, with the path inside the new directory they now
+    /// resolve to.
+    pub reachable: Vec<(OsString, PathBuf)>,
+    /// Names that now resolve into the new directory instead of where they resolved before,
+    /// with their old and new targets.
+    pub changed: Vec<(OsString, PathBuf, PathBuf)>,
+    /// Names in the new directory that keep resolving to an earlier entry, with the path that
+    /// still wins and the one in the new directory it shadows.
+    pub shadowed: Vec<(OsString, PathBuf, PathBuf)>,
+}
+
+/// Previews what adding `dir` to the path list `paths` (`$PATH` if `None`) would change, e.g. for
+/// an installer to warn before modifying `PATH`.
+///
+/// Only names of executables in `dir` can be affected, so those are the ones looked up, with and
+/// without `dir`. The entry is added the way a shell would, without any quoting.
+///
+/// # Example
+///
+/// ```no_run
+/// use which::{path_diff, Placement};
+///
+/// let diff = path_diff(None::<&str>, "/opt/tool/bin", Placement::Prepend).unwrap();
+/// for (name, before, after) in diff.changed {
+///     println!("{:?} would change from {} to {}", name, before.display(), after.display());
+/// }
+/// ```
+pub fn path_diff<U, D>(paths: Option<U>, dir: D, placement: Placement) -> Result<PathDiff>
+where
+    U: AsRef<OsStr>,
+    D: AsRef<Path>,
+{
+    let dir = dir.as_ref();
+    let before = paths
+        .map(|p| p.as_ref().to_os_string())
+        .or_else(|| crate::environment::var_os("PATH"))
+        .unwrap_or_default();
+    let after = match placement {
+        _ if before.is_empty() => dir.as_os_str().to_os_string(),
+        Placement::Prepend => join(dir.as_os_str(), &before),
+        Placement::Append => join(&before, dir.as_os_str()),
+    };
+
+    let names = match DirListing::read(dir) {
+        Ok(listing) => names(&listing),
+        Err(_) => return Ok(PathDiff::default()),
+    };
+
+    let finder = Finder::new();
+    let find = |paths: &OsStr| {
+        finder.find_many(
+            &names,
+            Some(paths),
+            Option::<&Path>::None,
+            build_binary_checker(),
+        )
+    };
+    let own = find(dir.as_os_str())?;
+    let before = find(&before)?;
+    let after = find(&after)?;
+
+    let mut diff = PathDiff::default();
+    for (i, name) in names.into_iter().enumerate() {
+        // Entries of `dir` that aren't executables don't resolve to anything.
+        let own = match own[i].clone() {
+            Some(own) => own,
+            None => continue,
+        };
+        match (before[i].clone(), after[i].clone()) {
+            (None, Some(after)) => diff.reachable.push((name, after)),
+            (Some(before), Some(after)) if before != after => {
+                diff.changed.push((name, before, after));
+            }
+            (Some(before), _) if before != own => diff.shadowed.push((name, before, own)),
+            _ => {}
+        }
+    }
+    Ok(diff)
+}
+
+fn join(first: &OsStr, second: &OsStr) -> OsString {
+    let mut joined = first.to_os_string();
+    joined.push(String::from(char::from(PATH_SEPARATOR)));
+    joined.push(second);
+    joined
+}
+
+/// The names the entries of `listing` would be looked up by.
+#[cfg(not(windows))]
+fn names(listing: &DirListing) -> Vec<OsString> {
+    let names: BTreeSet<_> = listing.names().map(OsStr::to_os_string).collect();
+    names.into_iter().collect()
+}
+
+// Executables are looked up without their extension, and case-insensitively.
+#[cfg(windows)]
+fn names(listing: &DirListing) -> Vec<OsString> {
+    let names: BTreeSet<_> = listing
+        .names()
+        .filter_map(|name| Path::new(name).file_stem())
+        .map(OsStr::to_ascii_lowercase)
+        .collect();
+    names.into_iter().collect()
+}
@@ -0,0 +1,102 @@
+//! Enumerating the entries of a directory that share a prefix with a single `FindFirstFileExW`
+//! query, so that probing a name with every `%PATHEXT%` extension takes one round-trip per
+//! directory rather than one per extension.
+
+use crate::checker::{FileInfo, FileKind};
+use std::ffi::{OsStr, OsString};
+use std::io;
+use std::mem::MaybeUninit;
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+use std::path::Path;
+use windows_sys::Win32::Foundation::{
+    GetLastError, ERROR_FILE_NOT_FOUND, ERROR_NO_MORE_FILES, INVALID_HANDLE_VALUE,
+};
+use windows_sys::Win32::Storage::FileSystem::{
+    FindClose, FindExInfoBasic, FindExSearchNameMatch, FindFirstFileExW, FindNextFileW,
+    FILE_ATTRIBUTE_DIRECTORY, FILE_ATTRIBUTE_REPARSE_POINT, FIND_FIRST_EX_LARGE_FETCH,
+    WIN32_FIND_DATAW,
+};
+
+/// Reparse tags with this bit set are name surrogates, i.e. links to another file.
+const REPARSE_TAG_NAME_SURROGATE: u32 = 0x2000_0000;
+
+/// Returns the name and kind of every entry in `dir` whose name starts with `prefix`.
+///
+/// Matching follows the filesystem's rules, so it's case-insensitive. Entries that only matched
+/// by their short (8.3) name, like `PROGRA~1` for `Program Files`, are left out.
+pub fn query_prefixed(dir: &Path, prefix: &OsStr) -> io::Result<Vec<(OsString, FileInfo)>> {
+    let mut pattern = dir.join(prefix).into_os_string();
+    pattern.push("*");
+    let pattern: Vec<u16> = pattern.encode_wide().chain(Some(0)).collect();
+
+    let mut data = MaybeUninit::<WIN32_FIND_DATAW>::uninit();
+    // SAFETY: `pattern` is nul terminated and `data` is large enough for the requested info level.
+    let handle = unsafe {
+        FindFirstFileExW(
+            pattern.as_ptr(),
+            FindExInfoBasic,
+            data.as_mut_ptr().cast(),
+            FindExSearchNameMatch,
+            std::ptr::null(),
+            FIND_FIRST_EX_LARGE_FETCH,
+        )
+    };
+    if handle == INVALID_HANDLE_VALUE {
+        return match unsafe { GetLastError() } {
+            ERROR_FILE_NOT_FOUND => Ok(Vec::new()),
+            _ => Err(io::Error::last_os_error()),
+        };
+    }
+
+    let prefix = crate::win::fold_case(prefix);
+    let mut entries = Vec::new();
+    let result = loop {
+        // SAFETY: the data was filled in by the successful call before.
+        let found = unsafe { data.assume_init_ref() };
+        let name = file_name(found);
+        if crate::win::fold_case(&name)
+            .as_encoded_bytes()
+            .starts_with(prefix.as_encoded_bytes())
+        {
+            entries.push((name, file_info(found)));
+        }
+        if unsafe { FindNextFileW(handle, data.as_mut_ptr()) } == 0 {
+            break match unsafe { GetLastError() } {
+                ERROR_NO_MORE_FILES => Ok(entries),
+                _ => Err(io::Error::last_os_error()),
+            };
+        }
+    };
+    unsafe { FindClose(handle) };
+
+    result
+}
+
+fn file_name(data: &WIN32_FIND_DATAW) -> OsString {
+    let len = data
+        .cFileName
+        .iter()
+        .position(|&c| c == 0)
+        .unwrap_or(data.cFileName.len());
+    OsString::from_wide(&data.cFileName[..len])
+}
+
+// Mirrors how `fs::symlink_metadata` classifies files, so the result is the same as checking
+// the entry on its own.
+fn file_info(data: &WIN32_FIND_DATAW) -> FileInfo {
+    let attributes = data.dwFileAttributes;
+    // For reparse points, `dwReserved0` holds the reparse tag.
+    let kind = if attributes & FILE_ATTRIBUTE_REPARSE_POINT != 0
+        && data.dwReserved0 & REPARSE_TAG_NAME_SURROGATE != 0
+    {
+        FileKind::Symlink
+    } else if attributes & FILE_ATTRIBUTE_DIRECTORY != 0 {
+        FileKind::Dir
+    } else {
+        FileKind::File
+    };
+    let size = (u64::from(data.nFileSizeHigh) << 32) | u64::from(data.nFileSizeLow);
+    FileInfo::new(kind)
+        .with_attributes(attributes)
+        .with_size(size)
+}
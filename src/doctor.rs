@@ -0,0 +1,530 @@
+//! Checking that the tools a program needs are installed, for "doctor" commands and startup
+//! checks.
+//!
+//! Declare each tool as a [`Requirement`], with alternatives and an optional [`VersionReq`],
+//! then [`Requirements::check`] them all at once to get a [`Report`] saying which were found,
+//! where, and which are missing or outdated.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use which::doctor::{Requirement, Requirements, VersionReq};
+//!
+//! let node = VersionReq::parse(">= 18, < 21").unwrap();
+//! let report = Requirements::new()
+//!     .require(Requirement::new("git").min_version("2.30").probe_version(true))
+//!     .require(Requirement::new("node").version(node).probe_version(true))
+//!     .require(Requirement::new("cc").or("gcc").or("clang"))
+//!     .require(Requirement::new("docker").optional(true))
+//!     .check();
+//! for check in &report.checks {
+//!     println!("{:?}: {:?}", check.name, check.status);
+//! }
+//! if !report.is_satisfied() {
+//!     std::process::exit(1);
+//! }
+//! ```
+
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A tool that's needed, by one of several names.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Requirement {
+    names: Vec<OsString>,
+    version: Option<VersionReq>,
+    probe_version: bool,
+    version_args: Vec<OsString>,
+    optional: bool,
+}
+
+impl Requirement {
+    /// Requires the tool `name`.
+    pub fn new<T: Into<OsString>>(name: T) -> Requirement {
+        Requirement {
+            names: vec![name.into()],
+            version: None,
+            probe_version: false,
+            version_args: vec!["--version".into()],
+            optional: false,
+        }
+    }
+
+    /// Accepts `alternative` instead if the names before it can't be found.
+    pub fn or<T: Into<OsString>>(mut self, alternative: T) -> Requirement {
+        self.names.push(alternative.into());
+        self
+    }
+
+    /// Requires at least `version`, such as `1.70` or `2.30.1`, like `>= version` with
+    /// [`Self::version`].
+    ///
+    /// # Panics
+    ///
+    /// If `version` isn't a version this will panic.
+    pub fn min_version(mut self, version: &str) -> Requirement {
+        match VersionReq::parse(&format!(">={}", version)) {
+            Some(version) => self.version = Some(version),
+            None => panic!("which's minimum version must be a version, like `1.70`!"),
+        }
+        self
+    }
+
+    /// Requires a version matching `version`.
+    ///
+    /// The version of the tool found is taken from its file name, like `18` for `clang-18` or
+    /// `3.12` for `python3.12`, unless [`Self::probe_version`] is enabled. Tools without one are
+    /// reported with [`Status::UnknownVersion`].
+    pub fn version(mut self, version: VersionReq) -> Requirement {
+        self.version = Some(version);
+        self
+    }
+
+    /// Runs the tool found with the [`Self::version_args`] to ask for its version, taking the
+    /// first version-like word it prints, and only falls back to its file name if that fails.
+    /// Disabled by default, since it runs whatever was found.
+    pub fn probe_version(mut self, probe_version: bool) -> Requirement {
+        self.probe_version = probe_version;
+        self
+    }
+
+    /// The arguments that make the tool print its version for [`Self::probe_version`],
+    /// `--version` by default.
+    pub fn version_args<I, T>(mut self, args: I) -> Requirement
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString>,
+    {
+        self.version_args = args.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Whether the tool is merely nice to have, so the report is satisfied without it. Its
+    /// status is still reported.
+    pub fn optional(mut self, optional: bool) -> Requirement {
+        self.optional = optional;
+        self
+    }
+}
+
+/// A set of tools that are needed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Requirements {
+    requirements: Vec<Requirement>,
+}
+
+impl Requirements {
+    pub fn new() -> Requirements {
+        Requirements::default()
+    }
+
+    /// Adds `requirement`, reported after those added before.
+    pub fn require(mut self, requirement: Requirement) -> Requirements {
+        self.requirements.push(requirement);
+        self
+    }
+
+    /// Looks every tool up in `$PATH` and checks the versions of those that need to be recent
+    /// enough.
+    pub fn check(&self) -> Report {
+        self.check_with(|names| crate::which_many(names))
+    }
+
+    /// Like [`Self::check`], but looks the tools up in the path list `paths`, using `cwd` to
+    /// resolve relative paths.
+    pub fn check_in<U: AsRef<OsStr>, V: AsRef<Path>>(&self, paths: U, cwd: V) -> Report {
+        self.check_with(|names| crate::which_many_in(names, Some(paths.as_ref()), cwd.as_ref()))
+    }
+
+    /// Like [`Self::check`], but looks each name up separately with the search `config` returns,
+    /// which is called once per name.
+    pub fn check_config<F: Fn() -> crate::WhichConfig>(&self, config: F) -> Report {
+        self.check_with(|names| {
+            Ok(names
+                .iter()
+                .map(|name| config().binary_name(name.clone()).first_result().ok())
+                .collect())
+        })
+    }
+
+    fn check_with<F>(&self, find: F) -> Report
+    where
+        F: Fn(&[OsString]) -> crate::Result<Vec<Option<PathBuf>>>,
+    {
+        let names: Vec<OsString> = self
+            .requirements
+            .iter()
+            .flat_map(|requirement| requirement.names.iter().cloned())
+            .collect();
+        // One pass over the path list for every name of every tool.
+        let mut found = find(&names)
+            .unwrap_or_else(|_| vec![None; names.len()])
+            .into_iter();
+
+        let checks = self
+            .requirements
+            .iter()
+            .map(|requirement| {
+                let hits: Vec<Option<PathBuf>> =
+                    found.by_ref().take(requirement.names.len()).collect();
+                check(requirement, hits)
+            })
+            .collect();
+        Report { checks }
+    }
+}
+
+/// The outcome of checking [`Requirements`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Report {
+    /// One check per requirement, in the order they were added.
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    /// Whether every requirement that isn't optional was found, recent enough.
+    pub fn is_satisfied(&self) -> bool {
+        self.checks
+            .iter()
+            .all(|check| check.optional || check.status.is_ok())
+    }
+
+    /// The checks of the requirements that weren't met, optional ones included.
+    pub fn problems(&self) -> impl Iterator<Item = &Check> {
+        self.checks.iter().filter(|check| !check.status.is_ok())
+    }
+}
+
+/// The outcome of checking one [`Requirement`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Check {
+    /// The name the requirement was declared with, or the alternative that was found.
+    pub name: OsString,
+    /// Where the tool was found, if it was.
+    pub path: Option<PathBuf>,
+    /// The version the tool reported or its file name gave, if a version was required.
+    pub version: Option<String>,
+    /// Whether the requirement was met.
+    pub status: Status,
+    /// Whether the requirement was optional.
+    pub optional: bool,
+}
+
+impl Check {
+    /// How to install the tool, by the name it was declared with, according to `hints`, if it's
+    /// [`Status::Missing`].
+    pub fn hint(&self, hints: &dyn crate::hints::InstallHint) -> Option<String> {
+        if self.status != Status::Missing {
+            return None;
+        }
+        hints.hint(&self.name)
+    }
+}
+
+/// Whether a [`Requirement`] was met.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Status {
+    /// Found, and recent enough if that was required.
+    Found,
+    /// None of the names could be found.
+    Missing,
+    /// Found, but older than required.
+    Outdated,
+    /// Found, but its version couldn't be told, e.g. because it failed to run.
+    UnknownVersion,
+}
+
+impl Status {
+    /// Whether the requirement was met.
+    pub fn is_ok(self) -> bool {
+        self == Status::Found
+    }
+}
+
+fn check(requirement: &Requirement, hits: Vec<Option<PathBuf>>) -> Check {
+    let hit = requirement
+        .names
+        .iter()
+        .zip(hits)
+        .find_map(|(name, hit)| Some((name, hit?)));
+    let (name, path) = match hit {
+        Some(hit) => hit,
+        None => {
+            return Check {
+                name: requirement.names[0].clone(),
+                path: None,
+                version: None,
+                status: Status::Missing,
+                optional: requirement.optional,
+            }
+        }
+    };
+
+    let (version, status) = match &requirement.version {
+        None => (None, Status::Found),
+        Some(req) => {
+            let version = if requirement.probe_version {
+                tool_version(&path, &requirement.version_args)
+            } else {
+                None
+            };
+            match version.or_else(|| file_name_version(&path)) {
+                Some(version) => {
+                    let status = match parse_version(&version) {
+                        Some(parsed) if req.matches_parts(&parsed) => Status::Found,
+                        Some(_) => Status::Outdated,
+                        None => Status::UnknownVersion,
+                    };
+                    (Some(version), status)
+                }
+                None => (None, Status::UnknownVersion),
+            }
+        }
+    };
+    Check {
+        name: name.clone(),
+        path: Some(path),
+        version,
+        status,
+        optional: requirement.optional,
+    }
+}
+
+/// Runs `tool` with `args` and picks the first version-like word out of what it prints.
+fn tool_version(tool: &Path, args: &[OsString]) -> Option<String> {
+    let output = Command::new(tool)
+        .args(args)
+        // Some tools localize their output, and the version is easier to find in English.
+        .env("LC_ALL", "C")
+        .current_dir(env::temp_dir())
+        .output()
+        .ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    find_version(&stdout).or_else(|| find_version(&stderr))
+}
+
+/// The version at the end of the file name of `path`, like `18` in `clang-18` or `3.12` in
+/// `python3.12`, ignoring the extensions of Windows executables.
+fn file_name_version(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_str()?;
+    let name = [".exe", ".cmd", ".bat", ".com"]
+        .iter()
+        .find_map(|ext| {
+            let split = name.len().checked_sub(ext.len())?;
+            let (stem, suffix) = (name.get(..split)?, name.get(split..)?);
+            suffix.eq_ignore_ascii_case(ext).then_some(stem)
+        })
+        .unwrap_or(name);
+    let start = name
+        .rfind(|c: char| !c.is_ascii_digit() && c != '.')
+        .map_or(0, |i| i + 1);
+    let version = name[start..].trim_matches('.');
+    (!version.is_empty()).then(|| version.to_owned())
+}
+
+/// The first word of `text` that starts like a version, such as `1.75.0` in
+/// `rustc 1.75.0 (82e1608df 2023-12-21)` or `2.39.2` in `git version 2.39.2.windows.1`.
+fn find_version(text: &str) -> Option<String> {
+    text.split(|c: char| c.is_whitespace() || c == ',' || c == '(' || c == ')')
+        .map(|word| word.trim_start_matches('v'))
+        .find(|word| word.contains('.') && parse_version(word).is_some())
+        .map(str::to_owned)
+}
+
+/// The leading numeric parts of `version`, ignoring whatever follows them, like `-beta` or
+/// `.windows.1`.
+fn parse_version(version: &str) -> Option<Vec<u32>> {
+    let parts: Vec<u32> = version
+        .split('.')
+        .map_while(|part| {
+            let digits = part.bytes().take_while(u8::is_ascii_digit).count();
+            part[..digits].parse().ok()
+        })
+        .collect();
+    (!parts.is_empty()).then_some(parts)
+}
+
+/// A constraint on versions, like `>= 18, < 21`, made of comparisons that must all hold.
+///
+/// Each comparison is a version, with or without its minor and patch parts, after one of
+/// `>=`, `>`, `<=`, `<`, `=`, `~` or `^`, or nothing, which is the same as `^` as in Cargo:
+///
+/// - `^1.2.3` means `>= 1.2.3, < 2.0.0`, and `^0.2.3` means `>= 0.2.3, < 0.3.0`;
+/// - `~1.2.3` means `>= 1.2.3, < 1.3.0`, and `~1` means `>= 1, < 2`;
+/// - `=1.2` matches any `1.2.x`.
+///
+/// Missing parts count as zero in the other comparisons, so `< 21` excludes `21.0.1`.
+///
+/// # Example
+///
+/// ```
+/// use which::doctor::VersionReq;
+///
+/// let req = VersionReq::parse(">= 18, < 21").unwrap();
+/// assert!(req.matches("20.11.0"));
+/// assert!(!req.matches("21.0.0"));
+/// assert!(VersionReq::parse("^1.70").unwrap().matches("1.75.0"));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VersionReq {
+    comparators: Vec<(Op, Vec<u32>)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+    Exact,
+    Tilde,
+    Caret,
+}
+
+impl VersionReq {
+    /// Parses a constraint, or returns `None` if it isn't one.
+    pub fn parse(req: &str) -> Option<VersionReq> {
+        let comparators = req
+            .split(',')
+            .map(|comparator| {
+                let comparator = comparator.trim();
+                let (op, version) = [
+                    (">=", Op::GreaterEq),
+                    ("<=", Op::LessEq),
+                    (">", Op::Greater),
+                    ("<", Op::Less),
+                    ("=", Op::Exact),
+                    ("~", Op::Tilde),
+                    ("^", Op::Caret),
+                ]
+                .iter()
+                .find_map(|&(prefix, op)| Some((op, comparator.strip_prefix(prefix)?)))
+                .unwrap_or((Op::Caret, comparator));
+                let version = version.trim();
+                let parts = version
+                    .split('.')
+                    .map(|part| part.parse().ok())
+                    .collect::<Option<Vec<u32>>>()?;
+                (parts.len() <= 3).then_some((op, parts))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(VersionReq { comparators })
+    }
+
+    /// Whether `version`, such as `1.75.0`, meets every comparison. Anything after its numeric
+    /// parts, like `-beta`, is ignored, and so are versions that don't start with a number.
+    pub fn matches(&self, version: &str) -> bool {
+        parse_version(version).is_some_and(|parts| self.matches_parts(&parts))
+    }
+
+    fn matches_parts(&self, version: &[u32]) -> bool {
+        let version = padded(version);
+        self.comparators.iter().all(|(op, parts)| {
+            let bound = padded(parts);
+            match op {
+                Op::Greater => version > bound,
+                Op::GreaterEq => version >= bound,
+                Op::Less => version < bound,
+                Op::LessEq => version <= bound,
+                Op::Exact => version[..parts.len()] == parts[..],
+                Op::Tilde => {
+                    // The parts given after the major one, up to the minor one, must match.
+                    let fixed = parts.len().clamp(1, 2);
+                    version >= bound && version[..fixed] == parts[..fixed]
+                }
+                Op::Caret => {
+                    // Everything up to and including the first nonzero part given must match.
+                    let fixed = parts
+                        .iter()
+                        .position(|&part| part != 0)
+                        .map_or(parts.len(), |i| i + 1);
+                    version >= bound && version[..fixed] == parts[..fixed]
+                }
+            }
+        })
+    }
+}
+
+/// Parses constraints from strings, like [`VersionReq::parse`].
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for VersionReq {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<VersionReq, D::Error> {
+        let req = String::deserialize(deserializer)?;
+        VersionReq::parse(&req).ok_or_else(|| {
+            serde::de::Error::custom(format!("invalid version constraint `{}`", req))
+        })
+    }
+}
+
+/// `parts` as a full `major.minor.patch` version, with missing parts as zero and any after the
+/// patch one ignored.
+fn padded(parts: &[u32]) -> [u32; 3] {
+    let mut version = [0; 3];
+    for (part, &given) in version.iter_mut().zip(parts) {
+        *part = given;
+    }
+    version
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_find_version() {
+        assert_eq!(
+            find_version("rustc 1.75.0 (82e1608df 2023-12-21)").as_deref(),
+            Some("1.75.0")
+        );
+        assert_eq!(
+            find_version("git version 2.39.2.windows.1").as_deref(),
+            Some("2.39.2.windows.1")
+        );
+        assert_eq!(find_version("node v20.11.0").as_deref(), Some("20.11.0"));
+        assert_eq!(find_version("no version here"), None);
+
+        assert_eq!(parse_version("2.39.2.windows.1"), Some(vec![2, 39, 2]));
+        assert_eq!(parse_version("1.0-beta"), Some(vec![1, 0]));
+        assert!(parse_version("1.10").unwrap() > parse_version("1.9.9").unwrap());
+
+        assert_eq!(
+            file_name_version(Path::new("/usr/bin/clang-18")).as_deref(),
+            Some("18")
+        );
+        assert_eq!(
+            file_name_version(Path::new("python3.12.EXE")).as_deref(),
+            Some("3.12")
+        );
+        assert_eq!(file_name_version(Path::new("/usr/bin/gcc")), None);
+    }
+
+    #[test]
+    fn test_version_req() {
+        let matches = |req: &str, version: &str| VersionReq::parse(req).unwrap().matches(version);
+        assert!(matches(">= 18, < 21", "18.0.0"));
+        assert!(matches(">= 18, < 21", "20.11.1"));
+        assert!(!matches(">= 18, < 21", "21.0.1"));
+        assert!(!matches(">= 18, < 21", "16.20.2"));
+        assert!(matches("1.2", "1.9.0"));
+        assert!(!matches("1.2", "2.0.0"));
+        assert!(matches("^0.2.3", "0.2.9"));
+        assert!(!matches("^0.2.3", "0.3.0"));
+        assert!(matches("~1.2.3", "1.2.7"));
+        assert!(!matches("~1.2.3", "1.3.0"));
+        assert!(matches("~1", "1.9.0"));
+        assert!(matches("=1.2", "1.2.5"));
+        assert!(!matches("=1.2", "1.3.0"));
+        assert!(matches("> 1.2", "1.2.1"));
+        assert!(!matches("<= 1.2", "1.2.1"));
+        assert!(!matches(">= 1", "beta"));
+
+        assert_eq!(VersionReq::parse(">= 18, < x"), None);
+        assert_eq!(VersionReq::parse("1.2.3.4"), None);
+    }
+}
@@ -0,0 +1,111 @@
+use crate::finder::PathExtensions;
+use crate::snapshot::EnvSnapshot;
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::path::PathBuf;
+
+/// Where a search reads environment variables and the working directory from.
+///
+/// Every read a [`WhichConfig`](crate::WhichConfig) search makes goes through this, so that one
+/// made with [`WhichConfig::hermetic`](crate::WhichConfig::hermetic) sees nothing but the values
+/// it was given, and one made with the process's can be redirected for tests with
+/// [`test_util::with_env`](crate::test_util::with_env).
+#[derive(Clone, Debug)]
+pub(crate) enum Environment {
+    /// The process's own.
+    Process,
+    /// The variables and working directory as given, with every other variable unset.
+    Explicit(EnvSnapshot),
+}
+
+impl Environment {
+    pub fn var_os<K: AsRef<OsStr>>(&self, name: K) -> Option<OsString> {
+        let name = name.as_ref();
+        match self {
+            Environment::Process => match substituted() {
+                Some(snapshot) => match snapshot.lookup(name) {
+                    Some(value) => value.map(ToOwned::to_owned),
+                    None => env::var_os(name),
+                },
+                None => env::var_os(name),
+            },
+            Environment::Explicit(snapshot) => snapshot.var_os(name).map(ToOwned::to_owned),
+        }
+    }
+
+    pub fn current_dir(&self) -> Option<PathBuf> {
+        match self {
+            Environment::Process => match substituted() {
+                Some(snapshot) => snapshot.cwd().map(ToOwned::to_owned),
+                None => env::current_dir().ok(),
+            },
+            Environment::Explicit(snapshot) => snapshot.cwd().map(ToOwned::to_owned),
+        }
+    }
+
+    /// The extensions names are expanded with on Windows.
+    pub fn path_extensions(&self) -> PathExtensions {
+        match self {
+            Environment::Process => PathExtensions::from_env(),
+            Environment::Explicit(snapshot) => snapshot.path_extensions().clone(),
+        }
+    }
+}
+
+/// The variable `name` as the process's environment has it, for lookups that don't take a
+/// [`WhichConfig`](crate::WhichConfig).
+pub(crate) fn var_os<K: AsRef<OsStr>>(name: K) -> Option<OsString> {
+    Environment::Process.var_os(name)
+}
+
+/// The working directory as the process's environment has it.
+pub(crate) fn current_dir() -> Option<PathBuf> {
+    Environment::Process.current_dir()
+}
+
+#[cfg(feature = "test-util")]
+thread_local! {
+    static SUBSTITUTED: std::cell::RefCell<Option<EnvSnapshot>> = const {
+        std::cell::RefCell::new(None)
+    };
+}
+
+/// What [`test_util::with_env`](crate::test_util::with_env) substituted for the process's
+/// environment on this thread, if anything.
+#[cfg(feature = "test-util")]
+pub(crate) fn substituted() -> Option<EnvSnapshot> {
+    SUBSTITUTED.with(|substituted| substituted.borrow().clone())
+}
+
+#[cfg(not(feature = "test-util"))]
+pub(crate) fn substituted() -> Option<EnvSnapshot> {
+    None
+}
+
+/// Runs `f` with `snapshot` substituted for the process's environment on this thread, restoring
+/// what was there before once it returns or panics.
+#[cfg(feature = "test-util")]
+pub(crate) fn substitute<R>(snapshot: EnvSnapshot, f: impl FnOnce() -> R) -> R {
+    struct Restore(Option<EnvSnapshot>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            SUBSTITUTED.with(|substituted| *substituted.borrow_mut() = previous);
+        }
+    }
+
+    let previous = SUBSTITUTED.with(|substituted| substituted.replace(Some(snapshot)));
+    let _restore = Restore(previous);
+    f()
+}
+
+/// Whether `a` and `b` name the same variable, which on Windows ignores case.
+pub(crate) fn same_var<B: AsRef<OsStr>>(a: &OsStr, b: B) -> bool {
+    let b = b.as_ref();
+    if cfg!(windows) {
+        a.eq_ignore_ascii_case(b)
+    } else {
+        a == b
+    }
+}
@@ -1,17 +1,154 @@
 use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+/// The errors a lookup can fail with.
+///
+/// Variants caused by an I/O error carry it, and return it from
+/// [`source`](std::error::Error::source). It's shared, so that errors stay cheap to clone, and
+/// two of them compare equal if their [`kind`](io::Error::kind)s do.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[non_exhaustive]
 pub enum Error {
     BadAbsolutePath,
     BadRelativePath,
     CannotFindBinaryPath,
+    /// Not returned by lookups: when the working directory can't be determined, names containing a
+    /// separator are looked up on the path list instead.
     CannotGetCurrentDir,
-    CannotCanonicalize,
+    CannotCanonicalize(IoError),
+    NonUtf8Path,
+    /// The name can't be that of a file: it's empty or contains a nul byte, or a line break if
+    /// those are rejected. On Windows, DOS device names such as `CON` and `NUL` are rejected
+    /// too.
+    InvalidName,
+    /// A path the search couldn't look at stopped it, as configured with
+    /// [`WhichConfig::unreadable_policy`](crate::WhichConfig::unreadable_policy).
+    Unreadable(CandidateError),
+    /// More than one executable matched a lookup that needed a single one, such as
+    /// [`which_unique`](crate::which_unique). They're listed in search order.
+    Ambiguous(Vec<PathBuf>),
 }
 
-impl std::error::Error for Error {}
+/// The category of an [`Error`], as returned by [`Error::kind`].
+///
+/// Matching on this rather than on the error's message keeps working as messages improve, and
+/// as variants are added.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// No executable matched.
+    NotFound,
+    /// The name was a path that couldn't be used, such as one that's rejected in a context
+    /// that only searches the path list.
+    BadPath,
+    /// An I/O error stopped the lookup, see [`std::error::Error::source`] for which.
+    Io,
+    /// A path that had to be UTF-8 wasn't.
+    NonUtf8Path,
+    /// The name couldn't be that of any file.
+    InvalidName,
+    /// More than one executable matched.
+    Ambiguous,
+}
+
+impl Error {
+    /// The category of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::BadAbsolutePath | Error::BadRelativePath => ErrorKind::BadPath,
+            Error::CannotFindBinaryPath => ErrorKind::NotFound,
+            Error::CannotGetCurrentDir | Error::CannotCanonicalize(_) | Error::Unreadable(_) => {
+                ErrorKind::Io
+            }
+            Error::NonUtf8Path => ErrorKind::NonUtf8Path,
+            Error::InvalidName => ErrorKind::InvalidName,
+            Error::Ambiguous(_) => ErrorKind::Ambiguous,
+        }
+    }
+}
+
+/// An [`io::Error`] shared between clones of an [`Error`], which derefs to it.
+#[derive(Clone, Debug)]
+pub struct IoError(Arc<io::Error>);
+
+impl From<io::Error> for IoError {
+    fn from(error: io::Error) -> IoError {
+        IoError(Arc::new(error))
+    }
+}
+
+impl std::ops::Deref for IoError {
+    type Target = io::Error;
+
+    fn deref(&self) -> &io::Error {
+        &self.0
+    }
+}
+
+impl PartialEq for IoError {
+    fn eq(&self, other: &IoError) -> bool {
+        self.0.kind() == other.0.kind()
+    }
+}
+
+impl Eq for IoError {}
+
+/// A path a search couldn't look at, either a candidate or a directory it lists, as returned by
+/// [`WhichConfig::all_results_with_errors`](crate::WhichConfig::all_results_with_errors) rather
+/// than skipped.
+///
+/// Paths that don't exist aren't errors, since most of those a search tries don't.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[non_exhaustive]
+pub struct CandidateError {
+    /// The path.
+    pub path: PathBuf,
+    /// What went wrong looking at it, such as permission being denied.
+    pub error: IoError,
+}
+
+impl CandidateError {
+    /// The error looking at `path` failed with, or `None` if it only means there's nothing there.
+    pub(crate) fn new(path: &Path, error: io::Error) -> Option<CandidateError> {
+        #[cfg(unix)]
+        let not_a_dir = error.raw_os_error() == Some(libc::ENOTDIR);
+        #[cfg(not(unix))]
+        let not_a_dir = false;
+        if error.kind() == io::ErrorKind::NotFound || not_a_dir {
+            return None;
+        }
+        Some(CandidateError {
+            path: path.to_path_buf(),
+            error: error.into(),
+        })
+    }
+}
+
+impl std::error::Error for CandidateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&*self.error.0)
+    }
+}
+
+impl fmt::Display for CandidateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cannot look at {}: {}", self.path.display(), *self.error)
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::CannotCanonicalize(error) => Some(&*error.0),
+            Error::Unreadable(error) => Some(&*error.error.0),
+            _ => None,
+        }
+    }
+}
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -20,7 +157,11 @@ impl fmt::Display for Error {
             Error::BadRelativePath => write!(f, "bad relative path"),
             Error::CannotFindBinaryPath => write!(f, "cannot find binary path"),
             Error::CannotGetCurrentDir => write!(f, "cannot get current directory"),
-            Error::CannotCanonicalize => write!(f, "cannot canonicalize path"),
+            Error::CannotCanonicalize(_) => write!(f, "cannot canonicalize path"),
+            Error::NonUtf8Path => write!(f, "path is not valid UTF-8"),
+            Error::InvalidName => write!(f, "invalid binary name"),
+            Error::Unreadable(error) => write!(f, "cannot look at {}", error.path.display()),
+            Error::Ambiguous(paths) => write!(f, "{} different binaries match", paths.len()),
         }
     }
 }
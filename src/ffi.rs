@@ -0,0 +1,96 @@
+//! A C API, so that tools not written in Rust can resolve executables by the same rules.
+//!
+//! Enabled by the `ffi` feature. Build a shared library with
+//! `cargo rustc --release --features ffi --crate-type cdylib`, and declare the functions as in
+//! `include/which.h`.
+//!
+//! Paths cross the boundary as nul-terminated byte strings: raw bytes on Unix, and UTF-8 on
+//! Windows.
+
+use std::ffi::{CStr, CString, OsStr};
+use std::os::raw::c_char;
+use std::path::PathBuf;
+
+/// Looks `name` up like [`crate::which`], returning the path of the executable found, or null if
+/// there is none.
+///
+/// The result must be released with [`which_free`].
+///
+/// # Safety
+///
+/// `name` must be null or point to a nul-terminated string that stays valid for the duration of
+/// the call.
+#[no_mangle]
+pub unsafe extern "C" fn which_find(name: *const c_char) -> *mut c_char {
+    if name.is_null() {
+        return std::ptr::null_mut();
+    }
+    let name = match os_str_from_c(CStr::from_ptr(name)) {
+        Some(name) => name,
+        None => return std::ptr::null_mut(),
+    };
+
+    crate::which(name)
+        .ok()
+        .and_then(path_to_c)
+        .map_or(std::ptr::null_mut(), CString::into_raw)
+}
+
+/// Releases a path returned by [`which_find`]. Does nothing if `path` is null.
+///
+/// # Safety
+///
+/// `path` must be null or have been returned by [`which_find`], and not have been released yet.
+#[no_mangle]
+pub unsafe extern "C" fn which_free(path: *mut c_char) {
+    if !path.is_null() {
+        drop(CString::from_raw(path));
+    }
+}
+
+#[cfg(unix)]
+fn os_str_from_c(s: &CStr) -> Option<&OsStr> {
+    use std::os::unix::ffi::OsStrExt;
+    Some(OsStr::from_bytes(s.to_bytes()))
+}
+
+#[cfg(not(unix))]
+fn os_str_from_c(s: &CStr) -> Option<&OsStr> {
+    s.to_str().ok().map(OsStr::new)
+}
+
+#[cfg(unix)]
+fn path_to_c(path: PathBuf) -> Option<CString> {
+    use std::os::unix::ffi::OsStringExt;
+    CString::new(path.into_os_string().into_vec()).ok()
+}
+
+#[cfg(not(unix))]
+fn path_to_c(path: PathBuf) -> Option<CString> {
+    CString::new(path.into_os_string().into_string().ok()?).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_which_find_round_trip() {
+        let exe = env::current_exe().unwrap();
+        let name = path_to_c(exe.clone()).unwrap();
+
+        unsafe {
+            let found = which_find(name.as_ptr());
+            assert!(!found.is_null());
+            let path = os_str_from_c(CStr::from_ptr(found)).map(PathBuf::from);
+            assert_eq!(path, Some(exe));
+            which_free(found);
+
+            let missing = CString::new("which-ffi-test-missing").unwrap();
+            assert!(which_find(missing.as_ptr()).is_null());
+            assert!(which_find(std::ptr::null()).is_null());
+            which_free(std::ptr::null_mut());
+        }
+    }
+}
@@ -1,26 +1,335 @@
-use crate::checker::CompositeChecker;
+use crate::cache::WhichCache;
+#[cfg(any(windows, all(target_os = "linux", feature = "io-uring")))]
+use crate::checker::FileInfo;
+use crate::checker::{Candidate, CompositeChecker, FileKind};
 use crate::error::*;
-#[cfg(windows)]
-use crate::helper::has_executable_extension;
-use either::Either;
+use crate::listing::{DirListing, Listed};
+#[cfg(feature = "regex")]
+use crate::regex_pattern::{BytesRegexMatcher, RegexMatcher};
+use crate::split::{normalize, reroot, SplitPaths};
+use crate::stats::{Recorder, SlowDirCallback, Stats};
+use crate::vfs::{respell, CaseInsensitiveFs, Filesystem};
+use crate::win::has_executable_extension;
 #[cfg(feature = "regex")]
 use regex::Regex;
 #[cfg(feature = "regex")]
 use std::borrow::Borrow;
-use std::env;
+#[cfg(any(windows, all(target_os = "linux", feature = "io-uring")))]
+use std::collections::HashMap;
+use std::collections::VecDeque;
 use std::ffi::OsStr;
-#[cfg(any(feature = "regex", target_os = "windows"))]
+#[cfg(windows)]
+use std::ffi::OsString;
+use std::fmt;
+#[cfg(target_os = "windows")]
 use std::fs;
-use std::iter;
+use std::io;
+use std::iter::{self, FusedIterator};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+#[cfg(windows)]
+use std::sync::RwLock;
+use std::time::Duration;
+
+/// A test every candidate path must pass before it is returned.
+///
+/// Checkers are run cheapest first, as declared by [`Checker::cost`], and evaluation of a
+/// candidate stops at the first checker that rejects it. They have to be `Send` and `Sync` so that
+/// searches using them can be moved to and shared with other threads.
+pub trait Checker: Send + Sync {
+    /// Returns whether `candidate` should be accepted.
+    fn is_valid(&self, candidate: &Candidate<'_>) -> bool;
+
+    /// How expensive this checker is to run. Defaults to [`Cost::Metadata`].
+    fn cost(&self) -> Cost {
+        Cost::Metadata
+    }
+}
+
+impl<C: Checker + ?Sized> Checker for Arc<C> {
+    fn is_valid(&self, candidate: &Candidate<'_>) -> bool {
+        (**self).is_valid(candidate)
+    }
+
+    fn cost(&self) -> Cost {
+        (**self).cost()
+    }
+}
+
+/// The cost class of a [`Checker`], used to order checks so the cheap ones run first.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub enum Cost {
+    /// Only looks at the path itself, e.g. its extension, without touching the filesystem.
+    Free,
+    /// Looks at [`Candidate::info`], which is fetched once and shared by all checkers.
+    Metadata,
+    /// Performs its own I/O beyond the shared metadata, such as opening the file.
+    Io,
+}
+
+/// Where a match was found.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[non_exhaustive]
+pub enum Origin {
+    /// In the entry of the path list with this index, counting from zero. Entries are counted
+    /// as `std::env::split_paths` yields them, so empty entries count too.
+    PathEntry(usize),
+    /// A relative name containing a separator, resolved against the working directory rather
+    /// than looked up in the path list.
+    Cwd,
+    /// An absolute path, which depends on neither the path list nor the working directory.
+    Absolute,
+    /// In the active Xcode developer directory, see
+    /// [`WhichConfig::xcode_toolchain`](crate::WhichConfig::xcode_toolchain).
+    Toolchain,
+    /// In the Homebrew prefix, see [`WhichConfig::homebrew`](crate::WhichConfig::homebrew).
+    Homebrew,
+    /// In the project directory with this index among those added with
+    /// [`WhichConfig::project_dir`](crate::WhichConfig::project_dir), counting from zero.
+    Project(usize),
+    /// Named by the environment variable with this index among those added with
+    /// [`WhichConfig::env_override`](crate::WhichConfig::env_override), counting from zero.
+    EnvVar(usize),
+}
+
+/// The form results are returned in, see [`crate::WhichConfig::path_form`].
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub enum PathForm {
+    /// Exactly as the search assembled it from the path list entry or the working directory
+    /// and the name, so `./bin` stays in `./bin/tool`.
+    #[default]
+    AsConstructed,
+    /// Normalized lexically with [`crate::normalize`], without touching the filesystem. The
+    /// candidates are normalized before they're checked, too.
+    Lexical,
+    /// Canonicalized, with every symlink resolved. Results that can't be canonicalized, e.g.
+    /// because they were removed since, are normalized lexically instead, as are those of a
+    /// search through a custom [`Filesystem`].
+    Canonical,
+}
+
+/// Which of the files a name expands to with different executable extensions in the same
+/// directory are matches, such as `tool.exe` and `tool.cmd`, see
+/// [`crate::WhichConfig::extension_policy`].
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+#[non_exhaustive]
+pub enum ExtensionPolicy {
+    /// Every file is a match, in `%PATHEXT%` order, so the first one is the one `cmd.exe`
+    /// would run.
+    #[default]
+    All,
+    /// Only the first file in `%PATHEXT%` order is a match, the way `cmd.exe` decides.
+    PathextOrder,
+    /// Only the first file is a match, trying the extensions in this list first, in its order,
+    /// and then the rest in `%PATHEXT%` order. Extensions are compared case-insensitively, with
+    /// or without their dot. Those not in `%PATHEXT%` aren't tried at all.
+    Priority(Vec<String>),
+}
+
+/// What a search does with the paths it can't look at for another reason than not existing,
+/// such as the candidates in a `PATH` entry the process may not search, see
+/// [`crate::WhichConfig::unreadable_policy`].
+#[derive(Clone, Default)]
+#[non_exhaustive]
+pub enum UnreadablePolicy {
+    /// Skips them, as if they didn't exist.
+    #[default]
+    Skip,
+    /// Skips them after passing each to the callback, to log or collect.
+    Warn(Arc<dyn Fn(&CandidateError) + Send + Sync>),
+    /// Stops the search at the first one. [`crate::WhichConfig::first_result`] and the like
+    /// return it as [`Error::Unreadable`] if there's no result before it.
+    /// [`crate::WhichConfig::all_results_with_errors`] returns it last, while the iterators of
+    /// paths only end there, without saying why.
+    Fail,
+}
+
+impl fmt::Debug for UnreadablePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnreadablePolicy::Skip => f.write_str("Skip"),
+            UnreadablePolicy::Warn(_) => f.write_str("Warn(..)"),
+            UnreadablePolicy::Fail => f.write_str("Fail"),
+        }
+    }
+}
+
+/// How a search deals with the paths it can't look at.
+struct Errors {
+    /// Whether each one stops the search, to be taken with `take` before it carries on, or ends
+    /// it with [`UnreadablePolicy::Fail`].
+    report: bool,
+    policy: UnreadablePolicy,
+    /// Why the search last stopped short of a match, if it did.
+    error: Option<CandidateError>,
+    /// Whether the search is over because of one.
+    failed: bool,
+}
+
+impl Errors {
+    fn new(report: bool, policy: UnreadablePolicy) -> Errors {
+        Errors {
+            report,
+            policy,
+            error: None,
+            failed: false,
+        }
+    }
+
+    /// Whether they're looked at at all.
+    fn wanted(&self) -> bool {
+        self.report || !matches!(self.policy, UnreadablePolicy::Skip)
+    }
+
+    /// Deals with `error`, returning whether the search stops at it.
+    fn stop(&mut self, error: CandidateError) -> bool {
+        if self.report {
+            self.error = Some(error);
+            self.failed = matches!(self.policy, UnreadablePolicy::Fail);
+            return true;
+        }
+        match &self.policy {
+            UnreadablePolicy::Skip => false,
+            UnreadablePolicy::Warn(warn) => {
+                warn(&error);
+                false
+            }
+            UnreadablePolicy::Fail => {
+                self.error = Some(error);
+                self.failed = true;
+                true
+            }
+        }
+    }
+}
 
-pub trait Checker {
-    fn is_valid(&self, path: &Path) -> bool;
+impl ExtensionPolicy {
+    /// Whether only one file per directory is a match.
+    fn first_only(&self) -> bool {
+        !matches!(self, ExtensionPolicy::All)
+    }
+
+    /// `extensions` in the order this policy tries them.
+    fn order(&self, extensions: PathExtensions) -> PathExtensions {
+        let priority = match self {
+            ExtensionPolicy::Priority(priority) => priority,
+            _ => return extensions,
+        };
+        let rank = |extension: &String| {
+            priority
+                .iter()
+                .position(|p| {
+                    p.trim_start_matches('.')
+                        .eq_ignore_ascii_case(extension.trim_start_matches('.'))
+                })
+                .unwrap_or(priority.len())
+        };
+        let mut list = extensions.list.to_vec();
+        // Stable, so the rest keep their order.
+        list.sort_by_key(rank);
+        PathExtensions {
+            list: list.into(),
+            windows: extensions.windows,
+        }
+    }
+}
+
+/// The files a name expands to with different executable extensions in one directory, as
+/// returned by [`WhichConfig::all_extension_variants`](crate::WhichConfig::all_extension_variants).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ExtensionGroup {
+    /// The file that's run when the name is looked up in this directory.
+    pub path: PathBuf,
+    /// Where it was found.
+    pub origin: Origin,
+    /// The other files in the directory, which `path` shadows, in the order they're tried.
+    pub shadowed: Vec<PathBuf>,
+}
+
+/// Groups consecutive `results` from the same directory, the first of each group shadowing the
+/// rest.
+pub(crate) fn group_extensions<I>(results: I) -> Vec<ExtensionGroup>
+where
+    I: IntoIterator<Item = (PathBuf, Origin)>,
+{
+    let mut groups: Vec<ExtensionGroup> = Vec::new();
+    for (path, origin) in results {
+        match groups.last_mut() {
+            Some(group) if group.origin == origin && group.path.parent() == path.parent() => {
+                group.shadowed.push(path)
+            }
+            _ => groups.push(ExtensionGroup {
+                path,
+                origin,
+                shadowed: Vec::new(),
+            }),
+        }
+    }
+    groups
+}
+
+/// How results are presented, as configured on the [`Finder`].
+#[derive(Clone)]
+struct Presentation {
+    form: PathForm,
+    relative_to: Option<PathBuf>,
+    /// Whether results are on the real filesystem, and so can be canonicalized.
+    real_fs: bool,
+}
+
+impl Presentation {
+    /// `path` as it's checked: with [`PathForm::Lexical`], candidates are normalized before
+    /// being looked at, so that observers and checkers see the same paths results have.
+    fn candidate(&self, path: PathBuf) -> PathBuf {
+        match self.form {
+            PathForm::Lexical => normalize(&path),
+            _ => path,
+        }
+    }
+
+    fn apply(&self, path: PathBuf) -> PathBuf {
+        let path = match self.form {
+            PathForm::AsConstructed => path,
+            PathForm::Canonical if self.real_fs => match path.canonicalize() {
+                Ok(canonical) => canonical,
+                Err(_) => normalize(&path),
+            },
+            PathForm::Lexical | PathForm::Canonical => normalize(&path),
+        };
+        match &self.relative_to {
+            Some(base) => relative(path, base),
+            None => path,
+        }
+    }
+}
+
+/// Makes `path` relative to `base` if it's inside it, starting with `.` so that it's never
+/// mistaken for a bare name to look up. Other paths are kept as they are.
+fn relative(path: PathBuf, base: &Path) -> PathBuf {
+    match normalize(&path).strip_prefix(normalize(base)) {
+        Ok(inner) if !inner.as_os_str().is_empty() => Path::new(".").join(inner),
+        _ => path,
+    }
+}
+
+impl Origin {
+    /// Whether the match depends on the working directory.
+    ///
+    /// Security-sensitive callers may want to refuse such matches, as the working directory is
+    /// often controlled by someone else (think of a checked out repository containing a
+    /// `./bin/git`).
+    pub fn is_cwd_relative(&self) -> bool {
+        *self == Origin::Cwd
+    }
 }
 
 trait PathExt {
     fn has_separator(&self) -> bool;
 
+    /// Whether the path doesn't depend on the working directory.
+    fn is_rooted(&self) -> bool;
+
     fn to_absolute<P>(self, cwd: P) -> PathBuf
     where
         P: AsRef<Path>;
@@ -28,14 +337,18 @@ trait PathExt {
 
 impl PathExt for PathBuf {
     fn has_separator(&self) -> bool {
-        self.components().count() > 1
+        self.components().count() > 1 || has_scheme(self)
+    }
+
+    fn is_rooted(&self) -> bool {
+        self.is_absolute() || has_scheme(self)
     }
 
     fn to_absolute<P>(self, cwd: P) -> PathBuf
     where
         P: AsRef<Path>,
     {
-        if self.is_absolute() {
+        if self.is_rooted() {
             self
         } else {
             let mut new_path = PathBuf::from(cwd.as_ref());
@@ -45,11 +358,331 @@ impl PathExt for PathBuf {
     }
 }
 
-pub struct Finder;
+/// Whether `path` starts with a scheme, as in `file:/bin/ls`. Such paths are absolute on Redox,
+/// even though the standard library doesn't consider them so.
+#[cfg(target_os = "redox")]
+fn has_scheme(path: &Path) -> bool {
+    match path.components().next() {
+        Some(std::path::Component::Normal(first)) => first.as_encoded_bytes().contains(&b':'),
+        _ => false,
+    }
+}
+
+#[cfg(not(target_os = "redox"))]
+fn has_scheme(_path: &Path) -> bool {
+    false
+}
+
+/// A callback told about every candidate a search checks, and whether it was accepted.
+pub type Observer = Arc<dyn Fn(&Path, bool) + Send + Sync>;
+
+pub struct Finder {
+    cache: Option<WhichCache>,
+    stats: bool,
+    batch: bool,
+    limit: Option<usize>,
+    extensions: bool,
+    path_extensions: Option<PathExtensions>,
+    depth: usize,
+    separator: Option<u8>,
+    filesystem: Option<Arc<dyn Filesystem>>,
+    observer: Option<Observer>,
+    root: Option<PathBuf>,
+    reject_newlines: bool,
+    resolve_dirs: bool,
+    form: PathForm,
+    relative_to: Option<PathBuf>,
+    leading_dirs: Vec<(PathBuf, Origin)>,
+    extra_dirs: Vec<(PathBuf, Origin)>,
+    overrides: Vec<(PathBuf, Origin)>,
+    fold_case: bool,
+    extension_policy: ExtensionPolicy,
+    every_variant: bool,
+    slow_dirs: Option<(Duration, SlowDirCallback)>,
+    report_errors: bool,
+    unreadable_policy: UnreadablePolicy,
+}
 
 impl Finder {
     pub fn new() -> Finder {
-        Finder
+        Finder {
+            cache: None,
+            stats: false,
+            batch: false,
+            limit: None,
+            extensions: true,
+            path_extensions: None,
+            depth: 0,
+            separator: None,
+            filesystem: None,
+            observer: None,
+            root: None,
+            reject_newlines: false,
+            resolve_dirs: false,
+            form: PathForm::AsConstructed,
+            relative_to: None,
+            leading_dirs: Vec::new(),
+            extra_dirs: Vec::new(),
+            overrides: Vec::new(),
+            fold_case: false,
+            extension_policy: ExtensionPolicy::All,
+            every_variant: false,
+            slow_dirs: None,
+            report_errors: false,
+            unreadable_policy: UnreadablePolicy::Skip,
+        }
+    }
+
+    /// Whether names containing a line break are rejected with [`Error::InvalidName`].
+    pub fn reject_newlines(mut self, reject_newlines: bool) -> Finder {
+        self.reject_newlines = reject_newlines;
+        self
+    }
+
+    /// Fails with [`Error::InvalidName`] if no file could ever be called `binary_name`, including
+    /// DOS device names on Windows.
+    fn validate_name(&self, binary_name: &OsStr) -> Result<()> {
+        // Lossy conversion only replaces invalid sequences, so control characters survive it.
+        let name = binary_name.to_string_lossy();
+        let newline = self.reject_newlines && name.contains(['\n', '\r']);
+        if name.is_empty() || name.contains('\0') || newline {
+            return Err(Error::InvalidName);
+        }
+        // Whichever directory it's looked up in, a device name opens the device.
+        #[cfg(windows)]
+        if crate::win::is_reserved_name(binary_name) {
+            return Err(Error::InvalidName);
+        }
+        Ok(())
+    }
+
+    /// Uses `cache` for every directory listing this finder reads.
+    pub fn cache(mut self, cache: Option<WhichCache>) -> Finder {
+        self.cache = cache;
+        self
+    }
+
+    /// Whether searches should collect [`Stats`].
+    pub fn stats(mut self, stats: bool) -> Finder {
+        self.stats = stats;
+        self
+    }
+
+    /// Whether to fetch the metadata of the candidates in every directory to search at once
+    /// rather than one by one, as the search reaches its first directory.
+    ///
+    /// Only has an effect with the `io-uring` feature on Linux.
+    pub fn batch(mut self, batch: bool) -> Finder {
+        self.batch = batch;
+        self
+    }
+
+    /// Whether names are expanded with executable extensions on Windows. Enabled by default.
+    pub fn extensions(mut self, extensions: bool) -> Finder {
+        self.extensions = extensions;
+        self
+    }
+
+    /// Expands names with `path_extensions` on Windows instead of the process's `%PATHEXT%`.
+    pub fn path_extensions(mut self, path_extensions: Option<PathExtensions>) -> Finder {
+        self.path_extensions = path_extensions;
+        self
+    }
+
+    /// Decides which of the files a name expands to in one directory are matches.
+    pub fn extension_policy(mut self, extension_policy: ExtensionPolicy) -> Finder {
+        self.extension_policy = extension_policy;
+        self
+    }
+
+    /// Whether every file a name expands to in one directory is a match whatever the extension
+    /// policy, which then only decides their order.
+    pub fn every_variant(mut self, every_variant: bool) -> Finder {
+        self.every_variant = every_variant;
+        self
+    }
+
+    /// Whether paths that can't be looked at for another reason than not existing stop the
+    /// search at them, for [`Matches::take_error`] to tell why, rather than being skipped.
+    pub fn report_errors(mut self, report_errors: bool) -> Finder {
+        self.report_errors = report_errors;
+        self
+    }
+
+    /// What to do with the paths that can't be looked at, unless they're reported.
+    pub fn unreadable_policy(mut self, unreadable_policy: UnreadablePolicy) -> Finder {
+        self.unreadable_policy = unreadable_policy;
+        self
+    }
+
+    fn errors(&self) -> Errors {
+        Errors::new(self.report_errors, self.unreadable_policy.clone())
+    }
+
+    /// Searches subdirectories of path list entries up to `depth` levels deep as well.
+    pub fn depth(mut self, depth: usize) -> Finder {
+        self.depth = depth;
+        self
+    }
+
+    /// Splits path lists on `separator` instead of the platform's separator. Double quotes
+    /// group entries when it is `;`, as on Windows.
+    pub fn separator(mut self, separator: Option<u8>) -> Finder {
+        self.separator = separator;
+        self
+    }
+
+    /// Runs searches against `filesystem` instead of the real filesystem. The cache isn't used
+    /// then.
+    pub fn filesystem(mut self, filesystem: Option<Arc<dyn Filesystem>>) -> Finder {
+        self.filesystem = filesystem;
+        self
+    }
+
+    /// Calls `observer` for every candidate searches check.
+    pub fn observer(mut self, observer: Option<Observer>) -> Finder {
+        self.observer = observer;
+        self
+    }
+
+    /// Calls `callback` for every directory that searches spend at least `threshold` in.
+    pub fn slow_dirs(mut self, slow_dirs: Option<(Duration, SlowDirCallback)>) -> Finder {
+        self.slow_dirs = slow_dirs;
+        self
+    }
+
+    /// Resolves every path list entry and the cwd as if `root` was the root directory.
+    pub fn root(mut self, root: Option<PathBuf>) -> Finder {
+        self.root = root;
+        self
+    }
+
+    /// Whether path list entries are canonicalized before being searched. Has no effect when
+    /// searching a [`Filesystem`] other than the real one.
+    pub fn resolve_dirs(mut self, resolve_dirs: bool) -> Finder {
+        self.resolve_dirs = resolve_dirs;
+        self
+    }
+
+    /// Returns results in the given form.
+    pub fn form(mut self, form: PathForm) -> Finder {
+        self.form = form;
+        self
+    }
+
+    /// Returns results inside `relative_to` relative to it.
+    pub fn relative_to(mut self, relative_to: Option<PathBuf>) -> Finder {
+        self.relative_to = relative_to;
+        self
+    }
+
+    /// Searches `leading_dirs` before the path list, with matches from each reported with the
+    /// origin it's paired with. Entries of the path list that are among them are skipped, so
+    /// that each directory is searched once, but still counted.
+    pub fn leading_dirs(mut self, leading_dirs: Vec<(PathBuf, Origin)>) -> Finder {
+        self.leading_dirs = leading_dirs;
+        self
+    }
+
+    /// Searches `extra_dirs` after the path list, with matches from each reported with the
+    /// origin it's paired with. Those the path list or the leading directories have already are
+    /// skipped.
+    pub fn extra_dirs(mut self, extra_dirs: Vec<(PathBuf, Origin)>) -> Finder {
+        self.extra_dirs = extra_dirs;
+        self
+    }
+
+    /// Tries the files `overrides` before searching the path list for a name, with matches from
+    /// each reported with the origin it's paired with. They're taken as they are, without
+    /// executable extensions, and ignored for names resolved against the working directory.
+    pub fn overrides(mut self, overrides: Vec<(PathBuf, Origin)>) -> Finder {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Matches the file names of candidates case-insensitively, as on Windows, even where the
+    /// filesystem doesn't. Results are spelled as the files are.
+    pub fn fold_case(mut self, fold_case: bool) -> Finder {
+        self.fold_case = fold_case;
+        self
+    }
+
+    /// The directories a search over `paths` goes through, in order, without their
+    /// subdirectories.
+    pub fn search_dirs<T: AsRef<OsStr>>(&self, paths: Option<T>) -> Vec<PathBuf> {
+        self.dirs(paths).map(|(dir, _)| dir).collect()
+    }
+
+    /// Where names are looked up, taking `fold_case` into account.
+    fn lookup_filesystem(&self) -> Option<Arc<dyn Filesystem>> {
+        if self.fold_case && !cfg!(windows) {
+            Some(Arc::new(CaseInsensitiveFs::new(self.filesystem.clone())))
+        } else {
+            self.filesystem.clone()
+        }
+    }
+
+    /// The directories to search for the path list `paths`, if any, and the leading and extra
+    /// directories.
+    fn dirs<T: AsRef<OsStr>>(&self, paths: Option<T>) -> Dirs {
+        let split = paths.map(|paths| self.split_paths(paths));
+        let skipped: Vec<PathBuf> = self
+            .leading_dirs
+            .iter()
+            .map(|(dir, _)| dir.clone())
+            .collect();
+        let mut extra = self.extra_dirs.clone();
+        if !extra.is_empty() {
+            let listed: Vec<PathBuf> = split.clone().into_iter().flatten().collect();
+            extra.retain(|(dir, _)| !listed.contains(dir) && !skipped.contains(dir));
+        }
+        Dirs {
+            leading: self.leading_dirs.clone().into_iter(),
+            skipped,
+            split,
+            index: 0,
+            extra: extra.into_iter(),
+        }
+    }
+
+    fn presentation(&self) -> Presentation {
+        Presentation {
+            form: self.form,
+            relative_to: self.relative_to.clone(),
+            real_fs: self.filesystem.is_none(),
+        }
+    }
+
+    fn split_paths<T: AsRef<OsStr>>(&self, paths: T) -> SplitPaths {
+        let split = match self.separator {
+            Some(separator) => SplitPaths::with_separator(paths, separator, separator == b';'),
+            None => SplitPaths::new(paths),
+        };
+        split
+            .under(self.root.clone())
+            .resolved(self.resolve_dirs && self.filesystem.is_none())
+    }
+
+    /// Resolves a name containing a separator against `cwd`, both inside the root if any.
+    fn resolve_in_cwd(&self, path: PathBuf, cwd: &Path) -> (PathBuf, PathBuf) {
+        match &self.root {
+            Some(root) => {
+                let cwd = reroot(root, cwd);
+                let path = if path.is_rooted() {
+                    reroot(root, &path)
+                } else {
+                    reroot(&cwd, &path)
+                };
+                (cwd, path)
+            }
+            None => (cwd.to_path_buf(), path.to_absolute(cwd)),
+        }
+    }
+
+    /// Stops searches after `limit` results, without looking at any further candidates.
+    pub fn limit(mut self, limit: Option<usize>) -> Finder {
+        self.limit = limit;
+        self
     }
 
     pub fn find<T, U, V>(
@@ -58,155 +691,1076 @@ impl Finder {
         paths: Option<U>,
         cwd: Option<V>,
         binary_checker: CompositeChecker,
-    ) -> Result<impl Iterator<Item = PathBuf>>
+    ) -> Result<Matches>
     where
         T: AsRef<OsStr>,
         U: AsRef<OsStr>,
         V: AsRef<Path>,
     {
+        self.validate_name(binary_name.as_ref())?;
         let path = PathBuf::from(&binary_name);
+        // Joining the name as given would look for files Windows never creates.
+        #[cfg(windows)]
+        let path = crate::win::trim_trailing_dots_and_spaces(&path);
+        let filesystem = self.lookup_filesystem();
 
-        let binary_path_candidates = match cwd {
+        let mut overrides = Vec::new();
+        let groups = match cwd {
             Some(cwd) if path.has_separator() => {
                 // Search binary in cwd if the path have a path separator.
-                Either::Left(Self::cwd_search_candidates(path, cwd).into_iter())
+                let origin = if path.is_rooted() {
+                    Origin::Absolute
+                } else {
+                    Origin::Cwd
+                };
+                let (cwd, path) = self.resolve_in_cwd(path, cwd.as_ref());
+                Groups::Cwd(Some((cwd, path, origin)))
             }
             _ => {
                 // Search binary in PATHs(defined in environment variable).
-                let p = paths.ok_or(Error::CannotFindBinaryPath)?;
-                let paths: Vec<_> = env::split_paths(&p).collect();
-
-                Either::Right(Self::path_search_candidates(path, paths).into_iter())
+                overrides = self.overrides.clone();
+                if paths.is_none() && self.leading_dirs.is_empty() && overrides.is_empty() {
+                    return Err(Error::CannotFindBinaryPath);
+                }
+                Groups::Path {
+                    dirs: self.dirs(paths),
+                    binary_name: path,
+                    depth: self.depth,
+                    subdirs: VecDeque::new(),
+                    filesystem: filesystem.clone(),
+                }
             }
         };
 
-        Ok(binary_path_candidates
-            .filter(move |p| binary_checker.is_valid(p))
-            .map(correct_casing))
+        Ok(Matches {
+            groups,
+            overrides: overrides.into_iter(),
+            current: None,
+            origin: Origin::Cwd,
+            checker: binary_checker,
+            recorder: Recorder::new(self.stats).slow_dirs(self.slow_dirs.clone()),
+            // Fetched metadata only tells whether candidates exist.
+            batch: self.batch
+                && cfg!(all(target_os = "linux", feature = "io-uring"))
+                && filesystem.is_none()
+                && !self.errors().wanted(),
+            prefetched: Prefetched::default(),
+            remaining: self.limit,
+            extensions: self
+                .extensions
+                .then(|| self.extension_policy.order(self.expansions())),
+            first_only: self.extension_policy.first_only() && !self.every_variant,
+            respell: self.fold_case && !cfg!(windows),
+            filesystem,
+            observer: self.observer.clone(),
+            presentation: self.presentation(),
+            errors: self.errors(),
+        })
     }
 
     #[cfg(feature = "regex")]
-    pub fn find_re<T>(
+    pub fn find_re<T, R>(
         &self,
-        binary_regex: impl Borrow<Regex>,
+        binary_regex: R,
         paths: Option<T>,
         binary_checker: CompositeChecker,
-    ) -> Result<impl Iterator<Item = PathBuf>>
+    ) -> Result<ListingMatches<RegexMatcher<R>>>
     where
         T: AsRef<OsStr>,
+        R: Borrow<Regex>,
     {
-        let p = paths.ok_or(Error::CannotFindBinaryPath)?;
-        // Collect needs to happen in order to not have to
-        // change the API to borrow on `paths`.
-        #[allow(clippy::needless_collect)]
-        let paths: Vec<_> = env::split_paths(&p).collect();
+        self.find_listed(RegexMatcher(binary_regex), paths, binary_checker)
+    }
 
-        let matching_re = paths
-            .into_iter()
-            .flat_map(fs::read_dir)
-            .flatten()
-            .flatten()
-            .map(|e| e.path())
-            .filter(move |p| {
-                if let Some(unicode_file_name) = p.file_name().unwrap().to_str() {
-                    binary_regex.borrow().is_match(unicode_file_name)
-                } else {
-                    false
-                }
-            })
-            .filter(move |p| binary_checker.is_valid(p));
+    #[cfg(feature = "regex")]
+    pub fn find_re_bytes<T, R>(
+        &self,
+        binary_regex: R,
+        paths: Option<T>,
+        binary_checker: CompositeChecker,
+    ) -> Result<ListingMatches<BytesRegexMatcher<R>>>
+    where
+        T: AsRef<OsStr>,
+        R: Borrow<regex::bytes::Regex>,
+    {
+        self.find_listed(BytesRegexMatcher(binary_regex), paths, binary_checker)
+    }
 
-        Ok(matching_re)
+    /// Searches every directory in `paths` for entries accepted by `matcher`, listing each
+    /// directory once.
+    pub fn find_listed<T, M>(
+        &self,
+        matcher: M,
+        paths: Option<T>,
+        binary_checker: CompositeChecker,
+    ) -> Result<ListingMatches<M>>
+    where
+        T: AsRef<OsStr>,
+        M: NameMatcher,
+    {
+        if paths.is_none() && self.leading_dirs.is_empty() {
+            return Err(Error::CannotFindBinaryPath);
+        }
+
+        Ok(ListingMatches {
+            dirs: self.dirs(paths),
+            origin: Origin::PathEntry(0),
+            matcher,
+            // Listings are cached by their modification time, which only the real filesystem has.
+            cache: match self.filesystem {
+                Some(_) => None,
+                None => self.cache.clone(),
+            },
+            current: Vec::new().into_iter(),
+            checker: binary_checker,
+            recorder: Recorder::new(self.stats).slow_dirs(self.slow_dirs.clone()),
+            remaining: self.limit,
+            filesystem: self.filesystem.clone(),
+            observer: self.observer.clone(),
+            presentation: self.presentation(),
+            errors: self.errors(),
+        })
+    }
+
+    /// The extensions names are expanded with: those given, or else the process's.
+    fn expansions(&self) -> PathExtensions {
+        self.path_extensions
+            .clone()
+            .unwrap_or_else(PathExtensions::from_env)
     }
 
-    fn cwd_search_candidates<C>(binary_name: PathBuf, cwd: C) -> impl IntoIterator<Item = PathBuf>
+    pub fn find_many<T, U, V>(
+        &self,
+        binary_names: &[T],
+        paths: Option<U>,
+        cwd: Option<V>,
+        binary_checker: CompositeChecker,
+    ) -> Result<Vec<Option<PathBuf>>>
     where
-        C: AsRef<Path>,
+        T: AsRef<OsStr>,
+        U: AsRef<OsStr>,
+        V: AsRef<Path>,
     {
-        let path = binary_name.to_absolute(cwd);
+        let is_valid = |p: &PathBuf| binary_checker.is_valid(&Candidate::new(p));
+        let extensions = self.expansions();
+        let candidates = |path: PathBuf| CandidatePaths::with_extensions(path, Some(&extensions));
+        let mut found = vec![None; binary_names.len()];
+        let mut pending = Vec::new();
+
+        for (i, binary_name) in binary_names.iter().enumerate() {
+            // Invalid names can't be found, without spoiling the rest of the batch.
+            if self.validate_name(binary_name.as_ref()).is_err() {
+                continue;
+            }
+            let path = PathBuf::from(binary_name);
+            #[cfg(windows)]
+            let path = crate::win::trim_trailing_dots_and_spaces(&path);
+            match cwd {
+                Some(ref cwd) if path.has_separator() => {
+                    found[i] = candidates(self.resolve_in_cwd(path, cwd.as_ref()).1)
+                        .find(is_valid)
+                        .map(correct_casing);
+                }
+                _ => pending.push((i, path)),
+            }
+        }
+
+        // Without a path list, the rest just aren't found.
+        let p = match paths {
+            Some(p) if !pending.is_empty() => p,
+            _ => return Ok(found),
+        };
+
+        // Names that still contain a separator can't be matched against a single listing.
+        pending.retain(|(i, path)| {
+            if !path.has_separator() {
+                return true;
+            }
+            found[*i] = self
+                .split_paths(&p)
+                .flat_map(|dir| candidates(dir.join(path)))
+                .find(is_valid)
+                .map(correct_casing);
+            false
+        });
+
+        for dir in self.split_paths(&p) {
+            if pending.is_empty() {
+                break;
+            }
+            let listing = match read_listing(self.cache.as_ref(), &dir) {
+                Ok(listing) => listing,
+                // Directories that may be searched but not listed, such as execute-only ones,
+                // are looked into name by name.
+                Err(_) => {
+                    pending.retain(|(i, path)| {
+                        found[*i] = candidates(dir.join(path))
+                            .find(is_valid)
+                            .map(correct_casing);
+                        found[*i].is_none()
+                    });
+                    continue;
+                }
+            };
+
+            let prefetched = Prefetched::fetch(|| {
+                pending
+                    .iter()
+                    .flat_map(|(_, path)| listed_candidates(&listing, path, &extensions))
+                    .collect()
+            });
 
-        Self::append_extension(iter::once(path))
+            pending.retain(|(i, path)| {
+                let hit = listed_candidates(&listing, path, &extensions)
+                    .find(|p| {
+                        prefetched
+                            .candidate(p)
+                            .is_some_and(|candidate| binary_checker.is_valid(&candidate))
+                    })
+                    .or_else(|| {
+                        candidates(path.clone())
+                            .filter(|candidate| listing.has_other_case(candidate.as_os_str()))
+                            .map(|candidate| listing.dir().join(candidate))
+                            .find(is_valid)
+                    });
+
+                match hit {
+                    Some(hit) => {
+                        found[*i] = Some(hit);
+                        false
+                    }
+                    None => true,
+                }
+            });
+        }
+
+        Ok(found)
     }
+}
 
-    fn path_search_candidates<P>(
+/// Where the candidates of a name search come from, one directory at a time.
+enum Groups {
+    /// A name containing a separator, resolved once against the given cwd.
+    Cwd(Option<(PathBuf, PathBuf, Origin)>),
+    /// A name looked up in every entry of a path list.
+    Path {
+        dirs: Dirs,
         binary_name: PathBuf,
-        paths: P,
-    ) -> impl IntoIterator<Item = PathBuf>
+        /// How many levels of subdirectories of each entry to search as well.
+        depth: usize,
+        /// Subdirectories of the current entry still to be searched, with their level and the
+        /// origin of the entry.
+        subdirs: VecDeque<(PathBuf, usize, Origin)>,
+        /// Where to list subdirectories, if not the real filesystem.
+        filesystem: Option<Arc<dyn Filesystem>>,
+    },
+}
+
+/// The directories of a path list, between any leading and extra ones, with where matches from
+/// each originate.
+#[derive(Clone)]
+struct Dirs {
+    leading: std::vec::IntoIter<(PathBuf, Origin)>,
+    /// The entries of `split` to skip, because they're leading directories.
+    skipped: Vec<PathBuf>,
+    split: Option<SplitPaths>,
+    /// The index of the next entry in `split`.
+    index: usize,
+    extra: std::vec::IntoIter<(PathBuf, Origin)>,
+}
+
+impl Iterator for Dirs {
+    type Item = (PathBuf, Origin);
+
+    fn next(&mut self) -> Option<(PathBuf, Origin)> {
+        if let Some(dir) = self.leading.next() {
+            return Some(dir);
+        }
+        for dir in self.split.iter_mut().flatten() {
+            self.index += 1;
+            if !self.skipped.contains(&dir) {
+                return Some((dir, Origin::PathEntry(self.index - 1)));
+            }
+        }
+        self.extra.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self
+            .split
+            .as_ref()
+            .map_or((0, Some(0)), SplitPaths::size_hint);
+        let known = self.leading.len() + self.extra.len();
+        // Skipped entries may still be to come.
+        let lower = lower.saturating_sub(self.skipped.len());
+        (
+            lower.saturating_add(known),
+            upper.and_then(|upper| upper.checked_add(known)),
+        )
+    }
+}
+
+impl Groups {
+    /// An upper bound on the number of directories left, if it fits in a `usize`.
+    fn remaining(&self) -> usize {
+        match self {
+            Groups::Cwd(group) => group.is_some() as usize,
+            Groups::Path { dirs, depth: 0, .. } => dirs.size_hint().1.unwrap_or(usize::MAX),
+            Groups::Path { .. } => usize::MAX,
+        }
+    }
+
+    /// The paths of the binary inside every directory left, or `None` if they aren't known
+    /// before the search reaches them.
+    fn upcoming(&self) -> Option<Vec<PathBuf>> {
+        match self {
+            Groups::Cwd(group) => Some(group.iter().map(|(_, path, _)| path.clone()).collect()),
+            Groups::Path {
+                dirs,
+                binary_name,
+                depth: 0,
+                ..
+            } => Some(dirs.clone().map(|(dir, _)| dir.join(binary_name)).collect()),
+            Groups::Path { .. } => None,
+        }
+    }
+
+    /// Returns the next directory to search, along with the path of the binary inside it and
+    /// where matches from it originate.
+    fn next(&mut self) -> Option<(PathBuf, PathBuf, Origin)> {
+        match self {
+            Groups::Cwd(group) => group.take(),
+            Groups::Path {
+                dirs,
+                binary_name,
+                depth,
+                subdirs,
+                filesystem,
+            } => {
+                let (dir, level, origin) = match subdirs.pop_front() {
+                    Some(subdir) => subdir,
+                    None => {
+                        let (dir, origin) = dirs.next()?;
+                        (dir, 0, origin)
+                    }
+                };
+                if level < *depth {
+                    let found = list_subdirs(filesystem.as_deref(), &dir);
+                    subdirs.extend(found.into_iter().map(|d| (d, level + 1, origin)));
+                }
+                let path = dir.join(&*binary_name);
+                Some((dir, path, origin))
+            }
+        }
+    }
+}
+
+/// The subdirectories of `dir`, sorted by name. Symlinks aren't followed, which keeps them from
+/// leading the search in circles.
+fn list_subdirs(filesystem: Option<&dyn Filesystem>, dir: &Path) -> Vec<PathBuf> {
+    let mut subdirs: Vec<_> = match filesystem {
+        Some(filesystem) => filesystem
+            .read_dir(dir)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|name| dir.join(name))
+            .filter(|path| {
+                filesystem
+                    .metadata(path)
+                    .is_some_and(|info| info.kind() == FileKind::Dir)
+            })
+            .collect(),
+        None => match dir.read_dir() {
+            Ok(entries) => entries
+                .flatten()
+                .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+                .map(|entry| entry.path())
+                .collect(),
+            Err(_) => Vec::new(),
+        },
+    };
+    subdirs.sort();
+    subdirs
+}
+
+/// The results of searching for a binary by name.
+pub struct Matches {
+    groups: Groups,
+    /// Files to try before `groups`.
+    overrides: std::vec::IntoIter<(PathBuf, Origin)>,
+    current: Option<CandidatePaths>,
+    checker: CompositeChecker,
+    recorder: Recorder,
+    /// Where matches from `current` originate.
+    origin: Origin,
+    /// Whether the metadata of candidates is still to be fetched in one go on entering the next
+    /// directory.
+    batch: bool,
+    prefetched: Prefetched,
+    /// How many more results may be returned, if limited.
+    remaining: Option<usize>,
+    /// The executable extensions to try on Windows, if any.
+    extensions: Option<PathExtensions>,
+    /// Whether to move on to the next directory after a match, leaving the rest of `current`.
+    first_only: bool,
+    /// Whether matches may be spelled differently than the files, and need correcting.
+    respell: bool,
+    /// Where to look candidates up, if not the real filesystem.
+    filesystem: Option<Arc<dyn Filesystem>>,
+    observer: Option<Observer>,
+    presentation: Presentation,
+    errors: Errors,
+}
+
+impl Matches {
+    pub fn into_stats(self) -> Option<Stats> {
+        self.recorder.into_stats()
+    }
+
+    /// Why the search last stopped without a match, if it did because of a path it couldn't
+    /// look at.
+    pub fn take_error(&mut self) -> Option<CandidateError> {
+        self.errors.error.take()
+    }
+
+    pub fn next_with_origin(&mut self) -> Option<(PathBuf, Origin)> {
+        let respelling = self.filesystem.clone().filter(|_| self.respell);
+        self.advance(|candidate, origin| {
+            let found = candidate.path().to_path_buf();
+            // Prefetched candidates are spelled correctly already.
+            let found = match respelling {
+                Some(filesystem) => respell(&*filesystem, found),
+                None if candidate.prefetched() => found,
+                None => correct_casing(found),
+            };
+            (found, origin)
+        })
+        .map(|(found, origin)| (self.presentation.apply(found), origin))
+    }
+
+    /// Whether there is another match, without building its path.
+    pub fn has_match(mut self) -> bool {
+        self.advance(|_, _| ()).is_some()
+    }
+
+    /// Searches up to the next match and returns what `found` makes of it.
+    fn advance<R, F>(&mut self, found: F) -> Option<R>
     where
-        P: IntoIterator<Item = PathBuf>,
+        F: FnOnce(&Candidate<'_>, Origin) -> R,
     {
-        let new_paths = paths.into_iter().map(move |p| p.join(binary_name.clone()));
+        if self.remaining == Some(0) || self.errors.failed {
+            return None;
+        }
+        self.recorder.begin();
+        let found = loop {
+            if let Some(path) = self.current.as_mut().and_then(Iterator::next) {
+                let candidate = match &self.filesystem {
+                    Some(filesystem) => Candidate::in_filesystem(&path, &**filesystem),
+                    None => match self.prefetched.candidate(&path) {
+                        Some(candidate) => candidate,
+                        None => {
+                            self.recorder.candidate(false);
+                            notify(self.observer.as_ref(), &path, false);
+                            continue;
+                        }
+                    },
+                };
+                let valid = self.checker.is_valid(&candidate);
+                self.recorder.candidate(candidate.fetched());
+                notify(self.observer.as_ref(), candidate.path(), valid);
+                if valid {
+                    let found = found(&candidate, self.origin);
+                    if self.first_only {
+                        self.current = None;
+                    }
+                    break Some(found);
+                }
+                let error = candidate
+                    .into_error()
+                    .filter(|_| self.errors.wanted())
+                    .and_then(|error| CandidateError::new(&path, error));
+                if let Some(error) = error {
+                    if self.errors.stop(error) {
+                        break None;
+                    }
+                }
+            } else if let Some((path, origin)) = self.overrides.next() {
+                self.origin = origin;
+                self.current = Some(CandidatePaths::exact(self.presentation.candidate(path)));
+            } else if let Some((dir, path, origin)) = self.groups.next() {
+                self.recorder.enter_dir(&dir, origin);
+                self.origin = origin;
+                let path = self.presentation.candidate(path);
+                if self.batch {
+                    // The candidates of every directory left are fetched in one go where they're
+                    // known up front, and those of each directory on entering it otherwise.
+                    let upcoming = self.groups.upcoming();
+                    self.batch = upcoming.is_none();
+                    let extensions = self.extensions.as_ref();
+                    let presentation = &self.presentation;
+                    self.prefetched = Prefetched::fetch(|| {
+                        iter::once(path.clone())
+                            .chain(
+                                upcoming
+                                    .into_iter()
+                                    .flatten()
+                                    .map(|p| presentation.candidate(p)),
+                            )
+                            .flat_map(|path| CandidatePaths::with_extensions(path, extensions))
+                            .collect()
+                    });
+                }
+                #[cfg(windows)]
+                if self.filesystem.is_none() {
+                    self.recorder.listing();
+                    self.prefetched = Prefetched::query(&path);
+                }
+                self.current = Some(CandidatePaths::with_extensions(
+                    path,
+                    self.extensions.as_ref(),
+                ));
+            } else {
+                self.current = None;
+                break None;
+            }
+        };
+        self.recorder.end();
+        count_result(&mut self.remaining, &found);
+        found
+    }
+}
+
+impl Iterator for Matches {
+    type Item = PathBuf;
+
+    fn next(&mut self) -> Option<PathBuf> {
+        self.next_with_origin().map(|(path, _)| path)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let current = self.current.as_ref().map_or(0, |c| c.size_hint().0);
+        let upper = self
+            .groups
+            .remaining()
+            .checked_mul(CandidatePaths::per_path(self.extensions.as_ref()))
+            .and_then(|later| later.checked_add(current))
+            .and_then(|upper| upper.checked_add(self.overrides.len()));
+        (0, limit_upper(self.remaining, upper))
+    }
+}
+
+impl FusedIterator for Matches {}
+
+fn notify(observer: Option<&Observer>, path: &Path, valid: bool) {
+    if let Some(observer) = observer {
+        observer(path, valid);
+    }
+}
+
+fn count_result<T>(remaining: &mut Option<usize>, found: &Option<T>) {
+    if let (Some(remaining), Some(_)) = (remaining, found) {
+        *remaining -= 1;
+    }
+}
+
+fn limit_upper(remaining: Option<usize>, upper: Option<usize>) -> Option<usize> {
+    match (remaining, upper) {
+        (Some(remaining), Some(upper)) => Some(remaining.min(upper)),
+        (remaining, upper) => remaining.or(upper),
+    }
+}
+
+/// Decides which entries of a directory listing are worth checking.
+pub trait NameMatcher {
+    fn matches(&self, file_name: &OsStr) -> bool;
+}
+
+/// Adapts a closure into a [`NameMatcher`].
+pub struct PredicateMatcher<F>(pub F);
+
+impl<F: Fn(&OsStr) -> bool> NameMatcher for PredicateMatcher<F> {
+    fn matches(&self, file_name: &OsStr) -> bool {
+        (self.0)(file_name)
+    }
+}
+
+/// The results of matching the listings of every directory in a path list.
+pub struct ListingMatches<M> {
+    dirs: Dirs,
+    /// Where matches from `current` originate.
+    origin: Origin,
+    matcher: M,
+    cache: Option<WhichCache>,
+    /// The matched entries of the current directory, with what its listing said about them.
+    current: std::vec::IntoIter<(PathBuf, Listed)>,
+    checker: CompositeChecker,
+    recorder: Recorder,
+    /// How many more results may be returned, if limited.
+    remaining: Option<usize>,
+    /// Where to list directories and look candidates up, if not the real filesystem.
+    filesystem: Option<Arc<dyn Filesystem>>,
+    observer: Option<Observer>,
+    presentation: Presentation,
+    errors: Errors,
+}
+
+impl<M> ListingMatches<M> {
+    #[cfg(feature = "regex")]
+    pub fn into_stats(self) -> Option<Stats> {
+        self.recorder.into_stats()
+    }
+
+    /// Why the search last stopped without a match, if it did because of a path it couldn't
+    /// look at.
+    pub fn take_error(&mut self) -> Option<CandidateError> {
+        self.errors.error.take()
+    }
+}
+
+impl<M: NameMatcher> ListingMatches<M> {
+    pub fn next_with_origin(&mut self) -> Option<(PathBuf, Origin)> {
+        if self.remaining == Some(0) || self.errors.failed {
+            return None;
+        }
+        self.recorder.begin();
+        let found = loop {
+            if let Some((path, listed)) = self.current.next() {
+                let candidate = match (&self.filesystem, listed) {
+                    (Some(filesystem), _) => Candidate::in_filesystem(&path, &**filesystem),
+                    #[cfg(windows)]
+                    (None, Listed::Info(info)) => Candidate::with_info(&path, Some(info)),
+                    (None, Listed::Kind(kind)) => Candidate::with_kind(&path, kind),
+                    (None, Listed::Name) => Candidate::new(&path),
+                };
+                let valid = self.checker.is_valid(&candidate);
+                self.recorder.candidate(candidate.fetched());
+                notify(self.observer.as_ref(), candidate.path(), valid);
+                if valid {
+                    break Some((path, self.origin));
+                }
+                let error = candidate
+                    .into_error()
+                    .filter(|_| self.errors.wanted())
+                    .and_then(|error| CandidateError::new(&path, error));
+                if let Some(error) = error {
+                    if self.errors.stop(error) {
+                        break None;
+                    }
+                }
+            } else if let Some((dir, origin)) = self.dirs.next() {
+                self.origin = origin;
+                let dir = self.presentation.candidate(dir);
+                self.recorder.enter_dir(&dir, origin);
+                self.recorder.listing();
+                let listing = match &self.filesystem {
+                    Some(filesystem) => filesystem
+                        .read_dir(&dir)
+                        .map(|names| Arc::new(DirListing::new(&dir, names))),
+                    None => read_listing(self.cache.as_ref(), &dir),
+                };
+                let listing = match listing {
+                    Ok(listing) => listing,
+                    Err(error) if self.errors.wanted() => {
+                        if let Some(error) = CandidateError::new(&dir, error) {
+                            if self.errors.stop(error) {
+                                break None;
+                            }
+                        }
+                        continue;
+                    }
+                    Err(_) => continue,
+                };
+                let matcher = &self.matcher;
+                // Cached listings may be older than the files in them.
+                let cached = self.cache.is_some();
+                self.current = listing
+                    .entries()
+                    .filter(|(name, _)| matcher.matches(name))
+                    .map(|(name, listed)| {
+                        let listed = if cached {
+                            listed.kind_only()
+                        } else {
+                            listed.clone()
+                        };
+                        (listing.dir().join(name), listed)
+                    })
+                    .collect::<Vec<_>>()
+                    .into_iter();
+            } else {
+                break None;
+            }
+        };
+        self.recorder.end();
+        count_result(&mut self.remaining, &found);
+        found.map(|(found, origin)| (self.presentation.apply(found), origin))
+    }
+}
+
+impl<M: NameMatcher> Iterator for ListingMatches<M> {
+    type Item = PathBuf;
+
+    fn next(&mut self) -> Option<PathBuf> {
+        self.next_with_origin().map(|(path, _)| path)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let upper = match self.dirs.size_hint() {
+            (_, Some(0)) => Some(self.current.len()),
+            _ => None,
+        };
+        (0, limit_upper(self.remaining, upper))
+    }
+}
+
+impl<M: NameMatcher> FusedIterator for ListingMatches<M> {}
+
+/// Either kind of search a [`crate::WhichConfig`] can run.
+pub enum Search {
+    Name(Matches),
+    #[cfg(feature = "regex")]
+    Regex(ListingMatches<RegexMatcher<Regex>>),
+}
 
-        Self::append_extension(new_paths)
+impl Search {
+    pub fn into_stats(self) -> Option<Stats> {
+        match self {
+            Search::Name(matches) => matches.into_stats(),
+            #[cfg(feature = "regex")]
+            Search::Regex(matches) => matches.into_stats(),
+        }
+    }
+
+    pub fn next_with_origin(&mut self) -> Option<(PathBuf, Origin)> {
+        match self {
+            Search::Name(matches) => matches.next_with_origin(),
+            #[cfg(feature = "regex")]
+            Search::Regex(matches) => matches.next_with_origin(),
+        }
+    }
+    pub fn take_error(&mut self) -> Option<CandidateError> {
+        match self {
+            Search::Name(matches) => matches.take_error(),
+            #[cfg(feature = "regex")]
+            Search::Regex(matches) => matches.take_error(),
+        }
+    }
+
+    /// Why the search ended without another match: [`Error::Unreadable`] if it failed at a path
+    /// it couldn't look at.
+    pub fn failure(&mut self) -> Error {
+        match self.take_error() {
+            Some(error) => Error::Unreadable(error),
+            None => Error::CannotFindBinaryPath,
+        }
+    }
+}
+
+impl Iterator for Search {
+    type Item = PathBuf;
+
+    fn next(&mut self) -> Option<PathBuf> {
+        match self {
+            Search::Name(matches) => matches.next(),
+            #[cfg(feature = "regex")]
+            Search::Regex(matches) => matches.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            Search::Name(matches) => matches.size_hint(),
+            #[cfg(feature = "regex")]
+            Search::Regex(matches) => matches.size_hint(),
+        }
+    }
+}
+
+impl FusedIterator for Search {}
+
+/// The executable extensions names are expanded with, as listed by `%PATHEXT%`. Only Windows has
+/// those, so this is empty everywhere else unless set explicitly.
+#[derive(Clone)]
+pub struct PathExtensions {
+    // Sample %PATHEXT%: .COM;.EXE;.BAT;.CMD;.VBS;.VBE;.JS;.JSE;.WSF;.WSH;.MSC
+    // The list is then [".COM", ".EXE", ".BAT", …].
+    // (In one use of the list we skip the dot, but in the other we need it;
+    // hence its retention.)
+    list: Arc<[String]>,
+    /// Whether names without an extension are only found with one of these, as on Windows.
+    windows: bool,
+}
+
+/// The process's `%PATHEXT%` as last read, or `None` if it's to be read again.
+#[cfg(windows)]
+static PATH_EXTENSIONS: RwLock<Option<PathExtensions>> = RwLock::new(None);
+
+impl PathExtensions {
+    /// The extensions listed by the value of `%PATHEXT%`, `None` meaning it's unset.
+    #[cfg(windows)]
+    pub fn parse(pathext: Option<&OsStr>) -> PathExtensions {
+        let list = pathext
+            .and_then(OsStr::to_str)
+            .map(crate::win::parse_pathext)
+            .unwrap_or_default();
+        PathExtensions::windows(list)
     }
 
     #[cfg(not(windows))]
-    fn append_extension<P>(paths: P) -> impl IntoIterator<Item = PathBuf>
-    where
-        P: IntoIterator<Item = PathBuf>,
-    {
-        paths
+    pub fn parse(_pathext: Option<&OsStr>) -> PathExtensions {
+        PathExtensions::new(Vec::new())
     }
 
+    /// Exactly the extensions in `list`, dots included, on every platform. Names are tried as
+    /// they are as well.
+    pub fn new(list: Vec<String>) -> PathExtensions {
+        PathExtensions {
+            list: list.into(),
+            windows: false,
+        }
+    }
+
+    /// The extensions in `list` with Windows semantics: names without an extension are only
+    /// tried with one of them.
+    pub fn windows(list: Vec<String>) -> PathExtensions {
+        PathExtensions {
+            list: list.into(),
+            windows: true,
+        }
+    }
+
+    /// The extensions listed by the process's `%PATHEXT%`, read once until [`Self::refresh`].
     #[cfg(windows)]
-    fn append_extension<P>(paths: P) -> impl IntoIterator<Item = PathBuf>
-    where
-        P: IntoIterator<Item = PathBuf>,
-    {
-        use once_cell::sync::Lazy;
-
-        // Sample %PATHEXT%: .COM;.EXE;.BAT;.CMD;.VBS;.VBE;.JS;.JSE;.WSF;.WSH;.MSC
-        // PATH_EXTENSIONS is then [".COM", ".EXE", ".BAT", …].
-        // (In one use of PATH_EXTENSIONS we skip the dot, but in the other we need it;
-        // hence its retention.)
-        static PATH_EXTENSIONS: Lazy<Vec<String>> = Lazy::new(|| {
-            env::var("PATHEXT")
-                .map(|pathext| {
-                    pathext
-                        .split(';')
-                        .filter_map(|s| {
-                            if s.as_bytes().first() == Some(&b'.') {
-                                Some(s.to_owned())
-                            } else {
-                                // Invalid segment; just ignore it.
-                                None
-                            }
-                        })
-                        .collect()
-                })
+    pub fn from_env() -> PathExtensions {
+        if let Some(snapshot) = crate::environment::substituted() {
+            return snapshot.path_extensions().clone();
+        }
+        if let Some(extensions) = &*PATH_EXTENSIONS.read().unwrap() {
+            return extensions.clone();
+        }
+        PATH_EXTENSIONS
+            .write()
+            .unwrap()
+            .get_or_insert_with(|| {
                 // PATHEXT not being set or not being a proper Unicode string is exceedingly
                 // improbable and would probably break Windows badly. Still, don't crash:
-                .unwrap_or_default()
-        });
+                PathExtensions::parse(std::env::var_os("PATHEXT").as_deref())
+            })
+            .clone()
+    }
 
-        paths
-            .into_iter()
-            .flat_map(move |p| -> Box<dyn Iterator<Item = _>> {
-                // Check if path already have executable extension
-                if has_executable_extension(&p, &PATH_EXTENSIONS) {
-                    Box::new(iter::once(p))
-                } else {
-                    let bare_file = p.extension().map(|_| p.clone());
-                    // Appended paths with windows executable extensions.
-                    // e.g. path `c:/windows/bin[.ext]` will expand to:
-                    // [c:/windows/bin.ext]
-                    // c:/windows/bin[.ext].COM
-                    // c:/windows/bin[.ext].EXE
-                    // c:/windows/bin[.ext].CMD
-                    // ...
-                    Box::new(
-                        bare_file
-                            .into_iter()
-                            .chain(PATH_EXTENSIONS.iter().map(move |e| {
-                                // Append the extension.
-                                let mut p = p.clone().into_os_string();
-                                p.push(e);
-
-                                PathBuf::from(p)
-                            })),
-                    )
+    #[cfg(not(windows))]
+    pub fn from_env() -> PathExtensions {
+        PathExtensions::new(Vec::new())
+    }
+
+    /// Makes [`Self::from_env`] read `%PATHEXT%` again next time.
+    pub fn refresh() {
+        #[cfg(windows)]
+        {
+            *PATH_EXTENSIONS.write().unwrap() = None;
+        }
+    }
+
+    /// Whether `path` ends in one of these extensions.
+    #[cfg(any(unix, windows))]
+    pub fn matches(&self, path: &Path) -> bool {
+        has_executable_extension(path, &self.list)
+    }
+}
+
+/// The candidates for one path: the path itself or, on Windows, the path with each executable
+/// extension appended.
+///
+/// e.g. on Windows, path `c:/windows/bin[.ext]` will expand to:
+/// [c:/windows/bin.ext]
+/// c:/windows/bin[.ext].COM
+/// c:/windows/bin[.ext].EXE
+/// c:/windows/bin[.ext].CMD
+/// ...
+struct CandidatePaths {
+    path: PathBuf,
+    /// Whether `path` itself is still to be yielded.
+    bare: bool,
+    /// The extensions to append, if any, and the index of the next one.
+    extensions: Option<(PathExtensions, usize)>,
+}
+
+impl CandidatePaths {
+    /// The most candidates a single path expands to with `extensions`.
+    fn per_path(extensions: Option<&PathExtensions>) -> usize {
+        1 + extensions.map_or(0, |extensions| extensions.list.len())
+    }
+
+    /// The candidates for `path`, expanded with `extensions` if there are any.
+    fn with_extensions(path: PathBuf, extensions: Option<&PathExtensions>) -> CandidatePaths {
+        match extensions {
+            // Check if path already have executable extension
+            // Without Windows semantics, names are taken as they are as well, extension or not.
+            Some(extensions)
+                if (extensions.windows || !extensions.list.is_empty())
+                    && !has_executable_extension(&path, &extensions.list) =>
+            {
+                CandidatePaths {
+                    bare: !extensions.windows || path.extension().is_some(),
+                    path,
+                    extensions: Some((extensions.clone(), 0)),
                 }
-            })
+            }
+            _ => CandidatePaths::exact(path),
+        }
+    }
+
+    /// Just `path` itself.
+    fn exact(path: PathBuf) -> CandidatePaths {
+        CandidatePaths {
+            path,
+            bare: true,
+            extensions: None,
+        }
+    }
+}
+
+impl Iterator for CandidatePaths {
+    type Item = PathBuf;
+
+    fn next(&mut self) -> Option<PathBuf> {
+        if self.bare {
+            self.bare = false;
+            return Some(self.path.clone());
+        }
+        let (extensions, next) = self.extensions.as_mut()?;
+        let e = extensions.list.get(*next)?;
+        *next += 1;
+
+        // Append the extension.
+        let mut p = self.path.clone().into_os_string();
+        p.push(e);
+
+        Some(PathBuf::from(p))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self
+            .extensions
+            .as_ref()
+            .map_or(0, |(extensions, next)| extensions.list.len() - next);
+        let len = self.bare as usize + left;
+        (len, Some(len))
+    }
+}
+
+/// The spellings of `path`'s candidates with `extensions` that are present in `listing`, as
+/// paths inside it.
+fn listed_candidates<'a>(
+    listing: &'a DirListing,
+    path: &Path,
+    extensions: &PathExtensions,
+) -> impl Iterator<Item = PathBuf> + 'a {
+    CandidatePaths::with_extensions(path.to_path_buf(), Some(extensions))
+        .filter_map(move |candidate| listing.get(candidate.as_os_str()))
+        .map(move |file_name| listing.dir().join(file_name))
+}
+
+/// The fewest paths worth fetching the metadata of in bulk.
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+const MIN_FETCH: usize = 8;
+
+/// Metadata fetched in bulk ahead of checking candidates, where the platform supports that.
+#[derive(Default)]
+struct Prefetched {
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    infos: HashMap<PathBuf, Option<FileInfo>>,
+    /// The entries of the directory being searched whose names start with the binary's, keyed
+    /// by their name with its case folded. `None` if the directory couldn't be queried.
+    #[cfg(windows)]
+    prefixed: Option<HashMap<OsString, (PathBuf, FileInfo)>>,
+}
+
+impl Prefetched {
+    /// Fetches the metadata of the paths returned by `paths` in one go. Without support for
+    /// that, `paths` isn't even called.
+    ///
+    /// Fewer than `MIN_FETCH` paths are left to be looked up one by one, which is cheaper.
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    fn fetch<F: FnOnce() -> Vec<PathBuf>>(paths: F) -> Prefetched {
+        let paths = paths();
+        if paths.len() < MIN_FETCH {
+            return Prefetched::default();
+        }
+        let infos = match crate::uring::statx(&paths) {
+            Ok(infos) => paths
+                .into_iter()
+                .zip(infos)
+                .filter_map(|(path, info)| Some((path, info?)))
+                .collect(),
+            // Fall back to looking candidates up one by one.
+            Err(_) => HashMap::new(),
+        };
+        Prefetched { infos }
+    }
+
+    #[cfg(not(all(target_os = "linux", feature = "io-uring")))]
+    fn fetch<F: FnOnce() -> Vec<PathBuf>>(_paths: F) -> Prefetched {
+        Prefetched::default()
+    }
+
+    /// Lists every file `path` could expand to with a single query of its directory, rather than
+    /// checking each extension separately.
+    #[cfg(windows)]
+    fn query(path: &Path) -> Prefetched {
+        let prefixed = match (path.parent(), path.file_name()) {
+            (Some(dir), Some(prefix)) => crate::dir_query::query_prefixed(dir, prefix).ok(),
+            _ => None,
+        };
+        Prefetched {
+            prefixed: prefixed.map(|entries| {
+                entries
+                    .into_iter()
+                    .map(|(name, info)| {
+                        (
+                            crate::win::fold_case(&name),
+                            (path.with_file_name(name), info),
+                        )
+                    })
+                    .collect()
+            }),
+        }
+    }
+
+    /// Returns the candidate for `path`, or `None` if it's already known not to exist.
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    fn candidate<'a>(&'a self, path: &'a Path) -> Option<Candidate<'a>> {
+        Some(match self.infos.get(path) {
+            Some(info) => Candidate::with_info(path, info.clone()),
+            None => Candidate::new(path),
+        })
+    }
+
+    /// Returns the candidate for `path`, or `None` if it's already known not to exist.
+    ///
+    /// Candidates found by a query are spelled the way the directory spells them.
+    #[cfg(windows)]
+    fn candidate<'a>(&'a self, path: &'a Path) -> Option<Candidate<'a>> {
+        match &self.prefixed {
+            Some(prefixed) => {
+                let (path, info) = prefixed.get(&crate::win::fold_case(path.file_name()?))?;
+                Some(Candidate::with_info(path, Some(info.clone())))
+            }
+            None => Some(Candidate::new(path)),
+        }
+    }
+
+    /// Returns the candidate for `path`, or `None` if it's already known not to exist.
+    #[cfg(not(any(windows, all(target_os = "linux", feature = "io-uring"))))]
+    fn candidate<'a>(&'a self, path: &'a Path) -> Option<Candidate<'a>> {
+        Some(Candidate::new(path))
+    }
+}
+
+fn read_listing(cache: Option<&WhichCache>, dir: &Path) -> io::Result<Arc<DirListing>> {
+    match cache {
+        Some(cache) => cache.listing(dir),
+        None => DirListing::read(dir).map(Arc::new),
     }
 }
 
@@ -0,0 +1,42 @@
+//! Looking executables up by glob, with the `glob` feature.
+
+use crate::build_binary_checker;
+use crate::error::*;
+use crate::finder::{Finder, ListingMatches, NameMatcher};
+use crate::pattern::{sealed, Pattern};
+use std::borrow::Borrow;
+use std::ffi::{OsStr, OsString};
+use std::path::PathBuf;
+
+pub struct GlobMatcher<P>(pub P);
+
+impl<P: Borrow<glob::Pattern>> NameMatcher for GlobMatcher<P> {
+    fn matches(&self, file_name: &OsStr) -> bool {
+        // Like a shell, wildcards don't match the dot of hidden files, and file names are
+        // case-insensitive on Windows.
+        let options = glob::MatchOptions {
+            case_sensitive: !cfg!(windows),
+            require_literal_separator: true,
+            require_literal_leading_dot: true,
+        };
+        file_name
+            .to_str()
+            .is_some_and(|file_name| self.0.borrow().matches_with(file_name, options))
+    }
+}
+
+macro_rules! glob_pattern {
+    ($($ty:ty),*) => {$(
+        impl Pattern for $ty {}
+
+        impl sealed::Sealed for $ty {
+            type Matches = ListingMatches<GlobMatcher<Self>>;
+
+            fn find(self, paths: Option<OsString>, _: Option<PathBuf>) -> Result<Self::Matches> {
+                Finder::new().find_listed(GlobMatcher(self), paths, build_binary_checker())
+            }
+        }
+    )*};
+}
+
+glob_pattern!(glob::Pattern, &glob::Pattern);
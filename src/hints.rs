@@ -0,0 +1,182 @@
+//! Suggestions on how to install tools that couldn't be found, so that command-line programs can
+//! fail with an error that says what to do about it.
+//!
+//! Hints come from an [`InstallHint`] provider: a closure, a type of the program's own, or, with
+//! the `install-hints` feature, the table of common tools in [`DefaultHints`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use which::hints::which_with_hint;
+//!
+//! let hints = |name: &std::ffi::OsStr| {
+//!     (name == "rg").then(|| "install it with `cargo install ripgrep`".to_owned())
+//! };
+//! match which_with_hint("rg", &hints) {
+//!     Ok(rg) => println!("{}", rg.display()),
+//!     // "cannot find `rg`: install it with `cargo install ripgrep`"
+//!     Err(error) => eprintln!("{}", error),
+//! }
+//! ```
+
+use crate::Error;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::path::PathBuf;
+
+/// A source of suggestions on how to install a tool.
+pub trait InstallHint {
+    /// A suggestion on how to install the tool `name`, such as "install it with `apt install
+    /// ripgrep`", or `None` if there's nothing to suggest.
+    fn hint(&self, name: &OsStr) -> Option<String>;
+}
+
+impl<F> InstallHint for F
+where
+    F: Fn(&OsStr) -> Option<String>,
+{
+    fn hint(&self, name: &OsStr) -> Option<String> {
+        self(name)
+    }
+}
+
+/// Looks `binary_name` up like [`which`](crate::which), asking `hints` how to install it if it
+/// can't be found.
+pub fn which_with_hint<T: AsRef<OsStr>>(
+    binary_name: T,
+    hints: &dyn InstallHint,
+) -> Result<PathBuf, MissingTool> {
+    let binary_name = binary_name.as_ref();
+    crate::which(binary_name).map_err(|error| MissingTool::new(binary_name, error, hints))
+}
+
+/// A failed lookup, with a suggestion on how to install the tool if there was one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MissingTool {
+    /// The name that was looked up.
+    pub name: OsString,
+    /// Why the lookup failed.
+    pub error: Error,
+    /// How to install the tool, if known. Only asked for if it couldn't be found at all,
+    /// rather than because of, say, an invalid name.
+    pub hint: Option<String>,
+}
+
+impl MissingTool {
+    /// The failure to find `name` with `error`, asking `hints` for a suggestion if `error` means
+    /// nothing matched.
+    pub fn new<T: AsRef<OsStr>>(name: T, error: Error, hints: &dyn InstallHint) -> MissingTool {
+        let name = name.as_ref();
+        let hint = match error.kind() {
+            crate::ErrorKind::NotFound => hints.hint(name),
+            _ => None,
+        };
+        MissingTool {
+            name: name.to_owned(),
+            error,
+            hint,
+        }
+    }
+}
+
+impl fmt::Display for MissingTool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.error, &self.hint) {
+            (Error::CannotFindBinaryPath, Some(hint)) => {
+                write!(f, "cannot find `{}`: {}", self.name.to_string_lossy(), hint)
+            }
+            (Error::CannotFindBinaryPath, None) => {
+                write!(f, "cannot find `{}`", self.name.to_string_lossy())
+            }
+            (error, _) => write!(
+                f,
+                "cannot look up `{}`: {}",
+                self.name.to_string_lossy(),
+                error
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MissingTool {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Hints for common developer tools, naming the packages that provide them.
+///
+/// The package managers suggested are those of the platform the crate was compiled for: `apt`
+/// on Linux, Homebrew on macOS and `winget` on Windows, followed by `cargo` for tools written in
+/// Rust. Names may have an `.exe` extension.
+#[cfg(feature = "install-hints")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DefaultHints;
+
+/// The tools of [`DefaultHints`], with their packages for `cargo`, `apt`, Homebrew and `winget`,
+/// empty where there is none.
+#[cfg(feature = "install-hints")]
+const PACKAGES: &[(&str, [&str; 4])] = &[
+    ("bat", ["bat", "bat", "bat", "sharkdp.bat"]),
+    ("cargo", ["", "cargo", "rustup", "Rustlang.Rustup"]),
+    ("clang", ["", "clang", "llvm", "LLVM.LLVM"]),
+    ("cmake", ["", "cmake", "cmake", "Kitware.CMake"]),
+    ("curl", ["", "curl", "curl", "cURL.cURL"]),
+    (
+        "delta",
+        ["git-delta", "git-delta", "git-delta", "dandavison.delta"],
+    ),
+    ("fd", ["fd-find", "fd-find", "fd", "sharkdp.fd"]),
+    ("fzf", ["", "fzf", "fzf", "junegunn.fzf"]),
+    ("gcc", ["", "gcc", "gcc", ""]),
+    ("gh", ["", "gh", "gh", "GitHub.cli"]),
+    ("git", ["", "git", "git", "Git.Git"]),
+    ("go", ["", "golang-go", "go", "GoLang.Go"]),
+    ("jq", ["", "jq", "jq", "jqlang.jq"]),
+    ("just", ["just", "just", "just", "Casey.Just"]),
+    ("make", ["", "make", "make", "GnuWin32.Make"]),
+    ("ninja", ["", "ninja-build", "ninja", "Ninja-build.Ninja"]),
+    ("node", ["", "nodejs", "node", "OpenJS.NodeJS"]),
+    ("npm", ["", "npm", "node", "OpenJS.NodeJS"]),
+    ("python3", ["", "python3", "python", "Python.Python.3.12"]),
+    (
+        "rg",
+        ["ripgrep", "ripgrep", "ripgrep", "BurntSushi.ripgrep.MSVC"],
+    ),
+    ("rustc", ["", "rustc", "rustup", "Rustlang.Rustup"]),
+    ("tokei", ["tokei", "", "tokei", ""]),
+    ("wget", ["", "wget", "wget", "JernejSimoncic.Wget"]),
+];
+
+#[cfg(feature = "install-hints")]
+impl InstallHint for DefaultHints {
+    fn hint(&self, name: &OsStr) -> Option<String> {
+        let name = name.to_str()?;
+        let name = name
+            .strip_suffix(".exe")
+            .or_else(|| name.strip_suffix(".EXE"))
+            .unwrap_or(name);
+        let [cargo, apt, brew, winget] = PACKAGES
+            .iter()
+            .find(|(tool, _)| *tool == name)
+            .map(|(_, packages)| *packages)?;
+
+        let native = if cfg!(target_os = "macos") {
+            (!brew.is_empty()).then(|| format!("brew install {}", brew))
+        } else if cfg!(windows) {
+            (!winget.is_empty()).then(|| format!("winget install --id {}", winget))
+        } else if cfg!(target_os = "linux") {
+            (!apt.is_empty()).then(|| format!("apt install {}", apt))
+        } else {
+            None
+        };
+        let cargo = (!cargo.is_empty()).then(|| format!("cargo install {}", cargo));
+        let commands: Vec<String> = native
+            .into_iter()
+            .chain(cargo)
+            .map(|command| format!("`{}`", command))
+            .collect();
+        (!commands.is_empty()).then(|| format!("install it with {}", commands.join(" or ")))
+    }
+}
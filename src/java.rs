@@ -0,0 +1,234 @@
+//! Finding Java installations and their tools, such as `java` and `javac`.
+//!
+//! The `java` on `PATH` is often not the one to use: on macOS, `/usr/bin/java` is a stub that
+//! asks to install Java if there's none, and build tools expect `JAVA_HOME` to win anyway. So
+//! installations are looked up the way Java tooling does, with `PATH` only as a last resort.
+
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A Java installation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Installation {
+    /// The installation's home directory, which `JAVA_HOME` would be set to.
+    pub home: PathBuf,
+    /// The version, such as `17.0.2` or `1.8.0_292`, if known.
+    pub version: Option<String>,
+    /// Where the installation was found.
+    pub source: Source,
+}
+
+/// Where a Java installation was found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Source {
+    /// The `JAVA_HOME` environment variable.
+    JavaHome,
+    /// The Windows registry, where the Oracle installers record installations.
+    Registry,
+    /// `/usr/libexec/java_home` on macOS, or the directory it looks in.
+    MacOsJavaHome,
+    /// The `java` on `PATH`.
+    Path,
+}
+
+impl Installation {
+    fn new(home: PathBuf, version: Option<String>, source: Source) -> Installation {
+        let version = release_version(&home).or(version);
+        Installation {
+            home,
+            version,
+            source,
+        }
+    }
+
+    /// The tool `name` of this installation, such as `java` or `javac`, if it has it.
+    pub fn tool(&self, name: &str) -> Option<PathBuf> {
+        let mut tool = self.home.join("bin").join(name);
+        if tool.extension().is_none() && !env::consts::EXE_SUFFIX.is_empty() {
+            tool.set_extension(&env::consts::EXE_SUFFIX[1..]);
+        }
+        tool.is_file().then_some(tool)
+    }
+}
+
+/// Every Java installation found, in order of precedence: `JAVA_HOME`, then the registry on
+/// Windows or `/usr/libexec/java_home` on macOS, then the installation of the `java` on `PATH`.
+/// Installations found more than once are only listed the first time.
+pub fn installations() -> Vec<Installation> {
+    let mut found = Vec::new();
+    if let Some(home) = crate::environment::var_os("JAVA_HOME").filter(|home| !home.is_empty()) {
+        found.push(Installation::new(home.into(), None, Source::JavaHome));
+    }
+    #[cfg(windows)]
+    found.extend(registry_installations());
+    #[cfg(target_os = "macos")]
+    found.extend(macos_installations());
+    if let Some(home) = path_installation() {
+        found.push(Installation::new(home, None, Source::Path));
+    }
+
+    unique(found)
+}
+
+/// The first of the `installations` at each home, telling them apart by their canonical path so
+/// that a `JAVA_HOME` through a symbolic link matches the `PATH` one it leads to.
+fn unique(installations: Vec<Installation>) -> Vec<Installation> {
+    let mut seen = HashSet::new();
+    installations
+        .into_iter()
+        .filter(|installation| {
+            seen.insert(
+                fs::canonicalize(&installation.home).unwrap_or_else(|_| installation.home.clone()),
+            )
+        })
+        .collect()
+}
+
+/// Finds the Java tool `name`, such as `java` or `javac`, in the first of the [`installations`]
+/// that has it, along with that installation.
+///
+/// # Example
+///
+/// ```no_run
+/// let (javac, installation) = which::java::find_tool("javac").unwrap();
+/// println!("{} is Java {:?}", javac.display(), installation.version);
+/// ```
+pub fn find_tool(name: &str) -> Option<(PathBuf, Installation)> {
+    installations()
+        .into_iter()
+        .find_map(|installation| Some((installation.tool(name)?, installation)))
+}
+
+/// The version in the `release` file of the installation at `home`, which JDKs since 8 have.
+fn release_version(home: &Path) -> Option<String> {
+    let release = fs::read_to_string(home.join("release")).ok()?;
+    parse_release(&release)
+}
+
+fn parse_release(release: &str) -> Option<String> {
+    release.lines().find_map(|line| {
+        let value = line.strip_prefix("JAVA_VERSION=")?.trim();
+        Some(value.trim_matches('"').to_owned())
+    })
+}
+
+/// The home of the installation the `java` on `PATH` belongs to, following symlinks such as
+/// the ones `update-alternatives` makes.
+fn path_installation() -> Option<PathBuf> {
+    let java = crate::which("java").ok()?.canonicalize().ok()?;
+    // The stub's "installation" would be `/usr`.
+    if cfg!(target_os = "macos") && java == Path::new("/usr/bin/java") {
+        return None;
+    }
+    let bin = java.parent()?;
+    if bin.file_name()? != "bin" {
+        return None;
+    }
+    bin.parent().map(Path::to_path_buf)
+}
+
+#[cfg(windows)]
+fn registry_installations() -> Vec<Installation> {
+    use crate::registry::{self, HKEY_LOCAL_MACHINE};
+
+    const KEYS: &[&str] = &[
+        r"SOFTWARE\JavaSoft\JDK",
+        r"SOFTWARE\JavaSoft\Java Development Kit",
+        r"SOFTWARE\JavaSoft\JRE",
+        r"SOFTWARE\JavaSoft\Java Runtime Environment",
+    ];
+    let mut found = Vec::new();
+    for key in KEYS {
+        let mut versions = registry::subkeys(HKEY_LOCAL_MACHINE, key);
+        // Newest first, roughly: `17.0.2` after `1.8`, but the release file has the real one.
+        versions.sort();
+        versions.reverse();
+        for version in versions {
+            let version = version.to_string_lossy().into_owned();
+            let path = format!(r"{}\{}", key, version);
+            if let Some(home) = registry::string_value(HKEY_LOCAL_MACHINE, &path, "JavaHome") {
+                found.push(Installation::new(
+                    home.into(),
+                    Some(version),
+                    Source::Registry,
+                ));
+            }
+        }
+    }
+    found
+}
+
+#[cfg(target_os = "macos")]
+fn macos_installations() -> Vec<Installation> {
+    use std::process::Command;
+
+    let mut found = Vec::new();
+    // The default installation, taking the user's preferences into account.
+    if let Ok(output) = Command::new("/usr/libexec/java_home").output() {
+        let home = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+        if output.status.success() && !home.is_empty() {
+            found.push(Installation::new(home.into(), None, Source::MacOsJavaHome));
+        }
+    }
+    // Where `java_home` looks for the others.
+    let mut roots = vec![PathBuf::from("/Library/Java/JavaVirtualMachines")];
+    if let Some(home) = crate::environment::var_os("HOME") {
+        roots.push(Path::new(&home).join("Library/Java/JavaVirtualMachines"));
+    }
+    for root in roots {
+        let mut homes: Vec<PathBuf> = match fs::read_dir(&root) {
+            Ok(entries) => entries
+                .flatten()
+                .map(|entry| entry.path().join("Contents").join("Home"))
+                .filter(|home| home.is_dir())
+                .collect(),
+            Err(_) => continue,
+        };
+        homes.sort();
+        found.extend(
+            homes
+                .into_iter()
+                .map(|home| Installation::new(home, None, Source::MacOsJavaHome)),
+        );
+    }
+    found
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_release_version() {
+        let release =
+            "IMPLEMENTOR=\"Eclipse Adoptium\"\nJAVA_VERSION=\"17.0.2\"\nOS_NAME=\"Linux\"\n";
+        assert_eq!(parse_release(release), Some("17.0.2".to_owned()));
+        assert_eq!(parse_release("OS_NAME=\"Linux\"\n"), None);
+
+        let home = tempfile::tempdir().unwrap();
+        fs::write(home.path().join("release"), release).unwrap();
+        let installation = Installation::new(home.path().to_path_buf(), None, Source::JavaHome);
+        assert_eq!(installation.version.as_deref(), Some("17.0.2"));
+        assert_eq!(installation.tool("java"), None);
+    }
+
+    #[test]
+    fn test_unique() {
+        let home = tempfile::tempdir().unwrap();
+        fs::create_dir(home.path().join("bin")).unwrap();
+        let found = unique(vec![
+            Installation::new(home.path().to_path_buf(), None, Source::JavaHome),
+            Installation::new(home.path().join("bin").join(".."), None, Source::Path),
+            Installation::new(home.path().join("missing"), None, Source::Path),
+        ]);
+        let sources: Vec<_> = found
+            .iter()
+            .map(|installation| installation.source)
+            .collect();
+        assert_eq!(sources, vec![Source::JavaHome, Source::Path]);
+    }
+}
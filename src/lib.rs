@@ -14,23 +14,106 @@
 //!
 //! ```
 
+pub mod android;
+pub mod cache;
+pub mod cargo;
 mod checker;
+mod collision;
+mod command;
+mod complete;
+mod cross;
+mod diff;
+#[cfg(windows)]
+mod dir_query;
+pub mod doctor;
+mod environment;
 mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 mod finder;
+#[cfg(feature = "glob")]
+mod glob_pattern;
+pub mod hints;
+pub mod java;
+#[path = "library.rs"]
+pub mod lib;
+mod listing;
+pub mod macos;
+#[cfg(feature = "serde")]
+pub mod manifest;
+#[cfg(all(windows, feature = "msvc"))]
+pub mod msvc;
+pub mod node;
+pub mod path_audit;
+mod pattern;
+mod profile;
+pub mod python;
+#[cfg(feature = "regex")]
+mod regex_pattern;
 #[cfg(windows)]
-mod helper;
+mod registry;
+mod same;
+mod snapshot;
+mod split;
+mod stats;
+#[cfg(feature = "futures-core")]
+pub mod stream;
+mod subcommand;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+mod uring;
+#[cfg(feature = "camino")]
+mod utf8;
+mod variants;
+mod versions;
+mod vfs;
+mod well_known;
+pub mod win;
 
 #[cfg(feature = "regex")]
 use std::borrow::Borrow;
 use std::env;
 use std::fmt;
+use std::iter::FusedIterator;
 use std::path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
 
 use std::ffi::{OsStr, OsString};
 
-use crate::checker::{CompositeChecker, ExecutableChecker, ExistedChecker};
+pub use crate::cache::WhichCache;
+pub use crate::checker::{Candidate, FileInfo, FileKind};
+use crate::checker::{
+    CompositeChecker, DirChecker, ExecutableChecker, ExistedChecker, NonEmptyChecker,
+    PredicateChecker, SignatureChecker, VisibleChecker,
+};
+pub use crate::collision::CaseCollision;
+pub use crate::complete::{Completer, Completion};
+pub use crate::diff::{path_diff, PathDiff, Placement};
+use crate::environment::Environment;
 pub use crate::error::*;
-use crate::finder::Finder;
+pub use crate::finder::{
+    Checker, Cost, ExtensionGroup, ExtensionPolicy, Origin, PathForm, UnreadablePolicy,
+};
+use crate::finder::{Finder, Matches, Observer, PathExtensions, PredicateMatcher, Search};
+pub use crate::pattern::{Pattern, Predicate};
+pub use crate::profile::PlatformProfile;
+pub use crate::same::{same, same_file, LinkGroup};
+pub use crate::snapshot::EnvSnapshot;
+pub use crate::split::normalize;
+use crate::stats::SlowDirCallback;
+pub use crate::stats::{DirStats, Stats};
+pub use crate::subcommand::{
+    subcommand, subcommand_in, subcommand_with, subcommands, subcommands_in, subcommands_with,
+    Subcommand, SubcommandScheme,
+};
+#[cfg(feature = "camino")]
+pub use crate::utf8::{which_all_utf8, which_in_utf8, which_utf8};
+pub use crate::variants::NameVariants;
+use crate::vfs::RootedFs;
+pub use crate::vfs::{Filesystem, VirtualFs};
+pub use crate::well_known::well_known_dirs;
 
 /// Find an executable binary's path by name.
 ///
@@ -54,7 +137,93 @@ use crate::finder::Finder;
 ///
 /// ```
 pub fn which<T: AsRef<OsStr>>(binary_name: T) -> Result<path::PathBuf> {
-    which_all(binary_name).and_then(|mut i| i.next().ok_or(Error::CannotFindBinaryPath))
+    if let Some(config) = default_config(binary_name.as_ref()) {
+        return config.first_result();
+    }
+
+    let cwd = environment::current_dir();
+
+    let binary_checker = build_binary_checker();
+
+    let finder = Finder::new();
+
+    finder
+        .find(
+            binary_name,
+            environment::var_os("PATH"),
+            cwd,
+            binary_checker,
+        )
+        .and_then(|mut i| i.next().ok_or(Error::CannotFindBinaryPath))
+}
+
+/// Find the lowest-precedence match for `binary_name` in `$PATH`, i.e. the executable that would be
+/// used if every earlier match was removed.
+///
+/// This is useful for looking past local overrides (shims, wrappers, virtualenvs) to the "system"
+/// version of a tool.
+///
+/// # Example
+///
+/// ```no_run
+/// let first = which::which("python3").unwrap();
+/// let system = which::which_last("python3").unwrap();
+/// if first != system {
+///     println!("{} is shadowed by {}", system.display(), first.display());
+/// }
+/// ```
+pub fn which_last<T: AsRef<OsStr>>(binary_name: T) -> Result<path::PathBuf> {
+    which_all(binary_name).and_then(|i| i.last().ok_or(Error::CannotFindBinaryPath))
+}
+
+/// Find an executable binary's path by name, remembering the result for the rest of the process.
+///
+/// This behaves like [`which`], but successful lookups are cached process-wide, keyed by
+/// `binary_name` together with the current `$PATH`, `%PATHEXT%` on Windows and, for names
+/// containing a separator, the working directory. Cached results aren't revalidated; call
+/// [`cache::clear`] to forget them, e.g. after installing a tool.
+///
+/// # Example
+///
+/// ```no_run
+/// for _ in 0..10 {
+///     // Only the first iteration searches `$PATH`.
+///     let git = which::which_cached("git").unwrap();
+/// }
+/// ```
+pub fn which_cached<T: AsRef<OsStr>>(binary_name: T) -> Result<path::PathBuf> {
+    cache::cached_lookup(binary_name.as_ref(), || which(binary_name.as_ref()))
+}
+
+/// Reports whether [`which`] would find `binary_name`, e.g. to probe for an optional tool.
+///
+/// The search stops at the first executable it comes across and skips the work of returning its
+/// path, such as correcting its case on Windows.
+///
+/// # Example
+///
+/// ```no_run
+/// let grep = if which::exists("rg") { "rg" } else { "grep" };
+/// ```
+pub fn exists<T: AsRef<OsStr>>(binary_name: T) -> bool {
+    if let Some(config) = default_config(binary_name.as_ref()) {
+        return config.first_result().is_ok();
+    }
+
+    let cwd = environment::current_dir();
+
+    let binary_checker = build_binary_checker();
+
+    let finder = Finder::new();
+
+    finder
+        .find(
+            binary_name,
+            environment::var_os("PATH"),
+            cwd,
+            binary_checker,
+        )
+        .is_ok_and(Matches::has_match)
 }
 
 /// Find an executable binary's path by name, ignoring `cwd`.
@@ -78,31 +247,195 @@ pub fn which<T: AsRef<OsStr>>(binary_name: T) -> Result<path::PathBuf> {
 ///
 /// ```
 pub fn which_global<T: AsRef<OsStr>>(binary_name: T) -> Result<path::PathBuf> {
-    which_all_global(binary_name).and_then(|mut i| i.next().ok_or(Error::CannotFindBinaryPath))
+    let binary_checker = build_binary_checker();
+
+    let finder = Finder::new();
+
+    finder
+        .find(
+            binary_name,
+            environment::var_os("PATH"),
+            Option::<&Path>::None,
+            binary_checker,
+        )
+        .and_then(|mut i| i.next().ok_or(Error::CannotFindBinaryPath))
+}
+
+/// Find an executable binary's path by name in `$PATH`, and nowhere else.
+///
+/// Unlike [`which_global`], names with a separator, such as `./foo` or `/usr/bin/foo`, are
+/// rejected with [`Error::BadRelativePath`] or [`Error::BadAbsolutePath`], and relative entries
+/// of `$PATH`, including empty ones, are ignored, as they would be resolved against the working
+/// directory. This makes it suitable for security-sensitive callers that must never run a file
+/// from the working directory.
+///
+/// # Example
+///
+/// ```no_run
+/// assert!(which::which_global_strict("./configure").is_err());
+/// let git = which::which_global_strict("git").unwrap();
+/// ```
+pub fn which_global_strict<T: AsRef<OsStr>>(binary_name: T) -> Result<path::PathBuf> {
+    which_in_global_strict(binary_name, environment::var_os("PATH"))
+}
+
+/// Like [`which_global_strict`], but searches the path list `paths` instead of `$PATH`.
+pub fn which_in_global_strict<T, U>(binary_name: T, paths: Option<U>) -> Result<path::PathBuf>
+where
+    T: AsRef<OsStr>,
+    U: AsRef<OsStr>,
+{
+    let name = path::Path::new(binary_name.as_ref());
+    let mut components = name.components();
+    match (components.next(), components.next()) {
+        (Some(path::Component::Normal(_)), None) => {}
+        _ if name.has_root() => return Err(Error::BadAbsolutePath),
+        _ => return Err(Error::BadRelativePath),
+    }
+
+    // Rejoined on nul bytes, which no path can contain, so that entries quoted because they
+    // hold the separator stay whole.
+    let mut absolute = OsString::new();
+    for dir in paths.into_iter().flat_map(split::SplitPaths::new) {
+        if dir.is_absolute() {
+            if !absolute.is_empty() {
+                absolute.push("\0");
+            }
+            absolute.push(dir);
+        }
+    }
+    if absolute.is_empty() {
+        return Err(Error::CannotFindBinaryPath);
+    }
+
+    let binary_checker = build_binary_checker();
+
+    let finder = Finder::new().separator(Some(b'\0'));
+
+    finder
+        .find(name, Some(absolute), Option::<&Path>::None, binary_checker)
+        .and_then(|mut i| i.next().ok_or(Error::CannotFindBinaryPath))
 }
 
 /// Find all binaries with `binary_name` using `cwd` to resolve relative paths.
-pub fn which_all<T: AsRef<OsStr>>(binary_name: T) -> Result<impl Iterator<Item = path::PathBuf>> {
-    let cwd = env::current_dir().ok();
+pub fn which_all<T: AsRef<OsStr>>(
+    binary_name: T,
+) -> Result<impl FusedIterator<Item = path::PathBuf>> {
+    if let Some(config) = default_config(binary_name.as_ref()) {
+        return config.search(true, false);
+    }
+
+    let cwd = environment::current_dir();
 
     let binary_checker = build_binary_checker();
 
-    let finder = Finder::new();
+    let finder = Finder::new().batch(true);
+
+    finder
+        .find(
+            binary_name,
+            environment::var_os("PATH"),
+            cwd,
+            binary_checker,
+        )
+        .map(Search::Name)
+}
+
+/// The configuration set with [`set_default_config`].
+static DEFAULT_CONFIG: RwLock<Option<WhichConfig>> = RwLock::new(None);
+
+/// Sets the configuration [`which`], [`which_all`] and the functions built on them, like
+/// [`exists`], [`which_last`] and [`which_cached`], search with, so that an application can
+/// enable options such as [`WhichConfig::path_form`] or extra checkers once rather than at every
+/// call site.
+///
+/// The name or regex `config` looks up, if any, is ignored for the one each call is given. Other
+/// functions, like [`which_in`], and searches made through a [`WhichConfig`] aren't affected.
+/// [`clear_default_config`] goes back to the default behavior. Results [`which_cached`]
+/// remembered with an earlier configuration aren't returned once it's replaced.
+///
+/// With an [`UnreadablePolicy::Fail`] configuration, [`which_all`] results end at the first path
+/// that couldn't be looked at without saying why, see [`WhichConfig::all_results_with_errors`].
+///
+/// # Example
+///
+/// ```no_run
+/// use which::{PathForm, WhichConfig};
+///
+/// which::set_default_config(
+///     WhichConfig::new()
+///         .path_form(PathForm::Canonical)
+///         .reject_newlines(true)
+///         .reject_empty(true),
+/// );
+/// let git = which::which("git").unwrap();
+/// ```
+pub fn set_default_config(config: WhichConfig) {
+    *DEFAULT_CONFIG.write().unwrap() = Some(config);
+    CONFIG_GENERATION.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Forgets the configuration set with [`set_default_config`], so that [`which`] and the
+/// functions built on it search the default way again.
+pub fn clear_default_config() {
+    *DEFAULT_CONFIG.write().unwrap() = None;
+    CONFIG_GENERATION.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Bumped whenever the default configuration changes, for [`which_cached`] to tell results
+/// looked up with different ones apart.
+static CONFIG_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+pub(crate) fn config_generation() -> u64 {
+    CONFIG_GENERATION.load(Ordering::Relaxed)
+}
+
+/// The configuration set with [`set_default_config`], looking `binary_name` up, if one was set.
+fn default_config(binary_name: &OsStr) -> Option<WhichConfig> {
+    #[cfg_attr(not(feature = "regex"), allow(unused_mut))]
+    let mut config = DEFAULT_CONFIG.read().unwrap().clone()?;
+    #[cfg(feature = "regex")]
+    {
+        config.regex = None;
+    }
+    config.binary_name = None;
+    Some(config.binary_name(binary_name.to_owned()))
+}
+
+/// Find all binaries with `binary_name` using `cwd` to resolve relative paths, as a boxed iterator.
+///
+/// This suits callers that keep searches behind trait objects, e.g. in plugin registries, and
+/// can't name the type returned by [`which_all`].
+pub fn which_all_boxed<T: AsRef<OsStr>>(
+    binary_name: T,
+) -> Result<Box<dyn Iterator<Item = path::PathBuf> + Send>> {
+    let cwd = environment::current_dir();
+
+    let binary_checker = build_binary_checker();
+
+    let finder = Finder::new().batch(true);
 
-    finder.find(binary_name, env::var_os("PATH"), cwd, binary_checker)
+    finder
+        .find(
+            binary_name,
+            environment::var_os("PATH"),
+            cwd,
+            binary_checker,
+        )
+        .map(|i| Box::new(i) as Box<dyn Iterator<Item = path::PathBuf> + Send>)
 }
 
 /// Find all binaries with `binary_name` ignoring `cwd`.
 pub fn which_all_global<T: AsRef<OsStr>>(
     binary_name: T,
-) -> Result<impl Iterator<Item = path::PathBuf>> {
+) -> Result<impl FusedIterator<Item = path::PathBuf>> {
     let binary_checker = build_binary_checker();
 
-    let finder = Finder::new();
+    let finder = Finder::new().batch(true);
 
     finder.find(
         binary_name,
-        env::var_os("PATH"),
+        environment::var_os("PATH"),
         Option::<&Path>::None,
         binary_checker,
     )
@@ -141,8 +474,8 @@ pub fn which_all_global<T: AsRef<OsStr>>(
 ///     .for_each(|pth| println!("{}", pth.to_string_lossy()));
 /// ```
 #[cfg(feature = "regex")]
-pub fn which_re(regex: impl Borrow<Regex>) -> Result<impl Iterator<Item = path::PathBuf>> {
-    which_re_in(regex, env::var_os("PATH"))
+pub fn which_re(regex: impl Borrow<Regex>) -> Result<impl FusedIterator<Item = path::PathBuf>> {
+    which_re_in(regex, environment::var_os("PATH"))
 }
 
 /// Find `binary_name` in the path list `paths`, using `cwd` to resolve relative paths.
@@ -152,10 +485,32 @@ where
     U: AsRef<OsStr>,
     V: AsRef<path::Path>,
 {
-    which_in_all(binary_name, paths, cwd)
+    let binary_checker = build_binary_checker();
+
+    let finder = Finder::new();
+
+    finder
+        .find(binary_name, paths, Some(cwd), binary_checker)
         .and_then(|mut i| i.next().ok_or(Error::CannotFindBinaryPath))
 }
 
+/// Reports whether [`which_in`] would find `binary_name` in the path list `paths`, using `cwd` to
+/// resolve relative paths. See [`exists`].
+pub fn exists_in<T, U, V>(binary_name: T, paths: Option<U>, cwd: V) -> bool
+where
+    T: AsRef<OsStr>,
+    U: AsRef<OsStr>,
+    V: AsRef<path::Path>,
+{
+    let binary_checker = build_binary_checker();
+
+    let finder = Finder::new();
+
+    finder
+        .find(binary_name, paths, Some(cwd), binary_checker)
+        .is_ok_and(Matches::has_match)
+}
+
 /// Find all binaries matching a regular expression in a list of paths.
 ///
 /// Only available when feature `regex` is enabled.
@@ -164,7 +519,7 @@ where
 ///
 /// * `regex` - A regular expression to match binaries with
 /// * `paths` - A string containing the paths to search
-///             (separated in the same way as the PATH environment variable)
+///   (separated in the same way as the PATH environment variable)
 ///
 /// # Examples
 ///
@@ -183,213 +538,1640 @@ where
 pub fn which_re_in<T>(
     regex: impl Borrow<Regex>,
     paths: Option<T>,
-) -> Result<impl Iterator<Item = path::PathBuf>>
+) -> Result<impl FusedIterator<Item = path::PathBuf>>
+where
+    T: AsRef<OsStr>,
+{
+    let binary_checker = build_binary_checker();
+
+    let finder = Finder::new();
+
+    finder.find_re(regex, paths, binary_checker)
+}
+
+/// Find all binaries matching a byte-oriented regular expression on the path.
+///
+/// Unlike [`which_re`], which skips file names that aren't valid UTF-8, this matches the raw
+/// bytes of file names on Unix, so even those can be found. Elsewhere, names are matched as
+/// UTF-8.
+///
+/// Only available when feature `regex` is enabled.
+///
+/// # Examples
+///
+/// ```no_run
+/// use regex::bytes::Regex;
+///
+/// // Matches `r\xe9sum\xe9`, `résumé` in Latin-1, which isn't valid UTF-8.
+/// let re = Regex::new(r"(?-u)^r\xe9sum\xe9$").unwrap();
+/// let binaries: Vec<_> = which::which_re_bytes(re).unwrap().collect();
+/// ```
+#[cfg(feature = "regex")]
+pub fn which_re_bytes(
+    regex: impl Borrow<regex::bytes::Regex>,
+) -> Result<impl FusedIterator<Item = path::PathBuf>> {
+    which_re_bytes_in(regex, environment::var_os("PATH"))
+}
+
+/// Find all binaries matching a byte-oriented regular expression in a list of paths.
+///
+/// See [`which_re_bytes`] for how names are matched.
+///
+/// Only available when feature `regex` is enabled.
+#[cfg(feature = "regex")]
+pub fn which_re_bytes_in<T>(
+    regex: impl Borrow<regex::bytes::Regex>,
+    paths: Option<T>,
+) -> Result<impl FusedIterator<Item = path::PathBuf>>
+where
+    T: AsRef<OsStr>,
+{
+    let binary_checker = build_binary_checker();
+
+    let finder = Finder::new();
+
+    finder.find_re_bytes(regex, paths, binary_checker)
+}
+
+/// Find all binaries on the path whose file name is accepted by `predicate`, for name matching
+/// that doesn't fit a regular expression.
+///
+/// Each directory is listed once and `predicate` is called with the name of every entry in it.
+/// On Windows, names aren't expanded with `%PATHEXT%` extensions, so `predicate` sees them with
+/// their extensions.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::ffi::OsStr;
+///
+/// let is_tool = |name: &OsStr| {
+///     let name = name.to_string_lossy();
+///     name.starts_with("llvm-") && name.ends_with("-15")
+/// };
+/// for tool in which::which_matching(is_tool).unwrap() {
+///     println!("{}", tool.display());
+/// }
+/// ```
+pub fn which_matching<F>(predicate: F) -> Result<impl FusedIterator<Item = path::PathBuf>>
+where
+    F: Fn(&OsStr) -> bool,
+{
+    which_matching_in(predicate, environment::var_os("PATH"))
+}
+
+/// Find all binaries in the path list `paths` whose file name is accepted by `predicate`.
+///
+/// See [`which_matching`] for details.
+pub fn which_matching_in<F, T>(
+    predicate: F,
+    paths: Option<T>,
+) -> Result<impl FusedIterator<Item = path::PathBuf>>
 where
+    F: Fn(&OsStr) -> bool,
     T: AsRef<OsStr>,
 {
     let binary_checker = build_binary_checker();
 
     let finder = Finder::new();
 
-    finder.find_re(regex, paths, binary_checker)
-}
+    finder.find_listed(PredicateMatcher(predicate), paths, binary_checker)
+}
+
+/// Find all binaries matching `pattern` on the path, whichever kind of [`Pattern`] it is.
+///
+/// # Example
+///
+/// ```no_run
+/// let cargos: Vec<_> = which::which_pattern("cargo").unwrap().collect();
+/// # #[cfg(feature = "regex")]
+/// let subcommands: Vec<_> = which::which_pattern(regex::Regex::new("^cargo-").unwrap())
+///     .unwrap()
+///     .collect();
+/// # #[cfg(feature = "glob")]
+/// let pythons: Vec<_> = which::which_pattern(glob::Pattern::new("python3.*").unwrap())
+///     .unwrap()
+///     .collect();
+/// ```
+pub fn which_pattern<P: Pattern>(pattern: P) -> Result<impl FusedIterator<Item = path::PathBuf>> {
+    which_pattern_in(
+        pattern,
+        environment::var_os("PATH"),
+        environment::current_dir(),
+    )
+}
+
+/// Find all binaries matching `pattern` in the path list `paths`, using `cwd` to resolve
+/// relative paths.
+///
+/// `cwd` is only used by names with a path separator, as with [`which_in`].
+pub fn which_pattern_in<P, U, V>(
+    pattern: P,
+    paths: Option<U>,
+    cwd: Option<V>,
+) -> Result<impl FusedIterator<Item = path::PathBuf>>
+where
+    P: Pattern,
+    U: AsRef<OsStr>,
+    V: AsRef<path::Path>,
+{
+    pattern.find(
+        paths.map(|paths| paths.as_ref().to_os_string()),
+        cwd.map(|cwd| cwd.as_ref().to_path_buf()),
+    )
+}
+
+/// Find all binaries with `binary_name` in the path list `paths`, using `cwd` to resolve relative paths.
+///
+/// Results are found lazily, as the iterator is advanced.
+///
+/// # Example
+///
+/// ```no_run
+/// let paths = Some("/usr/local/bin:/usr/bin");
+/// for python in which::which_in_all("python3", paths, "/").unwrap() {
+///     println!("{}", python.display());
+/// }
+/// ```
+pub fn which_in_all<T, U, V>(
+    binary_name: T,
+    paths: Option<U>,
+    cwd: V,
+) -> Result<impl FusedIterator<Item = path::PathBuf>>
+where
+    T: AsRef<OsStr>,
+    U: AsRef<OsStr>,
+    V: AsRef<path::Path>,
+{
+    let binary_checker = build_binary_checker();
+
+    let finder = Finder::new().batch(true);
+
+    finder.find(binary_name, paths, Some(cwd), binary_checker)
+}
+
+/// Find the only executable with `binary_name` in `$PATH`, failing with [`Error::Ambiguous`] if
+/// there's more than one, for callers that need to be sure which one runs, such as deployment
+/// checks.
+///
+/// Matches are compared by their canonical paths, which is what's returned, so the same file
+/// found through different `PATH` entries, like `/bin` and `/usr/bin` where one links to the
+/// other, counts once. On Windows, `tool.exe` and `tool.cmd` are different executables, even in
+/// the same directory.
+///
+/// # Example
+///
+/// ```no_run
+/// match which::which_unique("python3") {
+///     Ok(python) => println!("{}", python.display()),
+///     Err(which::Error::Ambiguous(pythons)) => eprintln!("{} pythons on PATH", pythons.len()),
+///     Err(e) => eprintln!("{}", e),
+/// }
+/// ```
+pub fn which_unique<T: AsRef<OsStr>>(binary_name: T) -> Result<path::PathBuf> {
+    unique(which_all(binary_name)?)
+}
+
+/// Find the only executable with `binary_name` in the path list `paths`, using `cwd` to resolve
+/// relative paths.
+///
+/// See [`which_unique`] for details.
+pub fn which_unique_in<T, U, V>(binary_name: T, paths: Option<U>, cwd: V) -> Result<path::PathBuf>
+where
+    T: AsRef<OsStr>,
+    U: AsRef<OsStr>,
+    V: AsRef<path::Path>,
+{
+    unique(which_in_all(binary_name, paths, cwd)?)
+}
+
+/// The one distinct file among `matches`, canonicalized.
+fn unique<I: Iterator<Item = path::PathBuf>>(matches: I) -> Result<path::PathBuf> {
+    let mut distinct: Vec<path::PathBuf> = Vec::new();
+    for found in matches {
+        let found = found
+            .canonicalize()
+            .map_err(|e| Error::CannotCanonicalize(e.into()))?;
+        if !distinct.contains(&found) {
+            distinct.push(found);
+        }
+    }
+    match distinct.len() {
+        0 => Err(Error::CannotFindBinaryPath),
+        1 => Ok(distinct.remove(0)),
+        _ => Err(Error::Ambiguous(distinct)),
+    }
+}
+
+/// Find all binaries with `binary_name` in the path list `paths`, ignoring `cwd`.
+pub fn which_in_global<T, U>(
+    binary_name: T,
+    paths: Option<U>,
+) -> Result<impl FusedIterator<Item = path::PathBuf>>
+where
+    T: AsRef<OsStr>,
+    U: AsRef<OsStr>,
+{
+    let binary_checker = build_binary_checker();
+
+    let finder = Finder::new().batch(true);
+
+    finder.find(binary_name, paths, Option::<&Path>::None, binary_checker)
+}
+
+/// The `PATH` container runtimes set for images that don't record one.
+const CONTAINER_PATH: &str = "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin";
+
+/// Find an executable binary's path as it would resolve inside a Linux container, given the
+/// container's extracted root filesystem `rootfs` and the `PATH` recorded in its image config.
+///
+/// Relative names are resolved against the root directory and symlinks are followed within
+/// `rootfs`, as if it were the container's root. Without `paths`, the default `PATH` of
+/// container runtimes is used. The result is a path inside `rootfs`; strip `rootfs` off to get
+/// the path inside the container. See [`WhichConfig::sysroot`] for more control.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::path::Path;
+///
+/// let rootfs = Path::new("/var/lib/images/alpine");
+/// let sh = which::which_in_rootfs("sh", rootfs, Some("/usr/local/bin:/usr/bin:/bin")).unwrap();
+/// assert_eq!(sh.strip_prefix(rootfs).unwrap(), Path::new("bin/sh"));
+/// ```
+pub fn which_in_rootfs<T, U, V>(
+    binary_name: T,
+    rootfs: V,
+    paths: Option<U>,
+) -> Result<path::PathBuf>
+where
+    T: AsRef<OsStr>,
+    U: AsRef<OsStr>,
+    V: AsRef<path::Path>,
+{
+    let rootfs = rootfs.as_ref().to_path_buf();
+    let paths = paths.map_or_else(|| CONTAINER_PATH.into(), |p| p.as_ref().to_os_string());
+
+    let binary_checker = build_binary_checker();
+
+    let finder = Finder::new()
+        .extensions(false)
+        .separator(Some(b':'))
+        .filesystem(Some(Arc::new(RootedFs::new(rootfs.clone()))))
+        .root(Some(rootfs));
+
+    finder
+        .find(binary_name, Some(paths), Some("/"), binary_checker)
+        .and_then(|mut i| i.next().ok_or(Error::CannotFindBinaryPath))
+}
+
+/// Find the cross-compilation tool `tool`, such as `gcc` or `ld`, of a toolchain for the Rust
+/// target triple `target`, for build scripts.
+///
+/// The conventional prefixed names are looked up in `$PATH` first, in order: the triple itself,
+/// like `aarch64-unknown-linux-gnu-gcc`, then the GNU spellings distributions use, like
+/// `aarch64-linux-gnu-gcc`. After that, the unprefixed `tool` is looked up in the bin
+/// directories of the toolchains' sysroots, like `/usr/aarch64-linux-gnu/bin`.
+///
+/// # Example
+///
+/// ```no_run
+/// let gcc = which::which_tool_for_target("gcc", "aarch64-unknown-linux-gnu").unwrap();
+/// ```
+pub fn which_tool_for_target<T: AsRef<OsStr>>(tool: T, target: &str) -> Result<path::PathBuf> {
+    let tool = tool.as_ref();
+    let prefixes = cross::prefixes(target);
+    let variants = prefixes
+        .iter()
+        .fold(NameVariants::new(), |variants, prefix| {
+            variants.prefix(format!("{}-", prefix))
+        });
+    if let Ok(found) = which_variants(tool, &variants) {
+        return Ok(found);
+    }
+
+    let dirs = cross::sysroot_bin_dirs(&prefixes);
+    let paths = env::join_paths(dirs).map_err(|_| Error::CannotFindBinaryPath)?;
+    Finder::new()
+        .find(
+            tool,
+            Some(paths),
+            Option::<&Path>::None,
+            build_binary_checker(),
+        )
+        .and_then(|mut i| i.next().ok_or(Error::CannotFindBinaryPath))
+}
+
+/// Find the first match for each of `binary_names`, using `cwd` to resolve relative paths.
+///
+/// Rather than checking every name against every directory in `$PATH`, each directory is read
+/// once and its listing is matched against the names that haven't been found yet. Directories
+/// that can't be listed, like execute-only ones, are checked name by name instead.
+///
+/// The results are in the same order as `binary_names`, with `None` for each name that couldn't
+/// be found, including every bare name when `$PATH` isn't set.
+///
+/// # Example
+///
+/// ```no_run
+/// let found = which::which_many(["cc", "gcc", "clang"]).unwrap();
+/// let compiler = found.into_iter().flatten().next();
+/// ```
+pub fn which_many<I, T>(binary_names: I) -> Result<Vec<Option<path::PathBuf>>>
+where
+    I: IntoIterator<Item = T>,
+    T: AsRef<OsStr>,
+{
+    let binary_names: Vec<_> = binary_names.into_iter().collect();
+    let cwd = environment::current_dir();
+
+    let binary_checker = build_binary_checker();
+
+    let finder = Finder::new();
+
+    finder.find_many(
+        &binary_names,
+        environment::var_os("PATH"),
+        cwd,
+        binary_checker,
+    )
+}
+
+/// Find the first match for each of `binary_names` in the path list `paths`, using `cwd` to
+/// resolve relative paths.
+///
+/// See [`which_many`] for details.
+pub fn which_many_in<I, T, U, V>(
+    binary_names: I,
+    paths: Option<U>,
+    cwd: V,
+) -> Result<Vec<Option<path::PathBuf>>>
+where
+    I: IntoIterator<Item = T>,
+    T: AsRef<OsStr>,
+    U: AsRef<OsStr>,
+    V: AsRef<path::Path>,
+{
+    let binary_names: Vec<_> = binary_names.into_iter().collect();
+
+    let binary_checker = build_binary_checker();
+
+    let finder = Finder::new();
+
+    finder.find_many(&binary_names, paths, Some(cwd), binary_checker)
+}
+
+/// Find the first of the `variants` of `binary_name` in `$PATH`, in their order of precedence,
+/// like `clang-18` before `clang`.
+///
+/// Like [`which_many`], each directory is read once for all variants, so the first variant
+/// wins even if another one comes earlier in `$PATH`.
+///
+/// # Example
+///
+/// ```no_run
+/// use which::NameVariants;
+///
+/// let variants = NameVariants::new().suffix("-18").suffix("-17").suffix("");
+/// let clang = which::which_variants("clang", &variants).unwrap();
+/// ```
+pub fn which_variants<T: AsRef<OsStr>>(
+    binary_name: T,
+    variants: &NameVariants,
+) -> Result<path::PathBuf> {
+    which_many(variants.names(binary_name))?
+        .into_iter()
+        .flatten()
+        .next()
+        .ok_or(Error::CannotFindBinaryPath)
+}
+
+/// Find the first of the `variants` of `binary_name` in the path list `paths`, using `cwd` to
+/// resolve relative paths.
+///
+/// See [`which_variants`] for details.
+pub fn which_variants_in<T, U, V>(
+    binary_name: T,
+    variants: &NameVariants,
+    paths: Option<U>,
+    cwd: V,
+) -> Result<path::PathBuf>
+where
+    T: AsRef<OsStr>,
+    U: AsRef<OsStr>,
+    V: AsRef<path::Path>,
+{
+    which_many_in(variants.names(binary_name), paths, cwd)?
+        .into_iter()
+        .flatten()
+        .next()
+        .ok_or(Error::CannotFindBinaryPath)
+}
+
+/// Find all files named `file_name` in the directories listed by the environment variable `var`,
+/// such as `MANPATH` or `CDPATH`, keeping those accepted by `predicate`.
+///
+/// This is the same search [`which_all`] runs over `PATH`, minus the executable-specific
+/// parts: the existence and permission checks are replaced by `predicate`, names aren't expanded
+/// with `%PATHEXT%` extensions, and names containing a separator are only resolved against the
+/// listed directories. The variable is split like `PATH` on the current platform.
+///
+/// # Example
+///
+/// ```no_run
+/// use which::FileKind;
+///
+/// let pages: Vec<_> = which::find_file_in_var("man1/ls.1", "MANPATH", |candidate| {
+///     candidate.info().map(|info| info.kind()) == Some(FileKind::File)
+/// })
+/// .unwrap()
+/// .collect();
+/// ```
+pub fn find_file_in_var<T, V, P>(
+    file_name: T,
+    var: V,
+    predicate: P,
+) -> Result<impl FusedIterator<Item = path::PathBuf>>
+where
+    T: AsRef<OsStr>,
+    V: AsRef<OsStr>,
+    P: Fn(&Candidate<'_>) -> bool + Send + Sync + 'static,
+{
+    find_file_in(file_name, environment::var_os(var), predicate)
+}
+
+/// Find all files named `file_name` in the path list `paths`, keeping those accepted by
+/// `predicate`.
+///
+/// See [`find_file_in_var`] for details.
+pub fn find_file_in<T, U, P>(
+    file_name: T,
+    paths: Option<U>,
+    predicate: P,
+) -> Result<impl FusedIterator<Item = path::PathBuf>>
+where
+    T: AsRef<OsStr>,
+    U: AsRef<OsStr>,
+    P: Fn(&Candidate<'_>) -> bool + Send + Sync + 'static,
+{
+    let checker = CompositeChecker::new().add_checker(Box::new(PredicateChecker(predicate)));
+
+    let finder = Finder::new().extensions(false);
+
+    finder.find(file_name, paths, Option::<&Path>::None, checker)
+}
+
+/// Makes later lookups see changes to the environment made since it was last read.
+///
+/// Most of the environment is read on every lookup, but `%PATHEXT%` is read once and kept, since
+/// it rarely changes and every lookup on Windows needs it. Long-running processes that change it,
+/// or run for long enough that it's changed for them, like terminals, can call this to pick the
+/// new value up. This also forgets the results remembered by [`which_cached`], which may have
+/// been found with the old one, like [`cache::clear`] does.
+pub fn refresh_env() {
+    PathExtensions::refresh();
+    cache::clear();
+}
+
+/// The directories [`which`] searches, in order: the entries of `$PATH`, split and expanded the
+/// same way. See [`WhichConfig::search_dirs`] for those of a configured search.
+///
+/// # Example
+///
+/// ```no_run
+/// for dir in which::search_dirs() {
+///     println!("{}", dir.display());
+/// }
+/// ```
+pub fn search_dirs() -> Vec<path::PathBuf> {
+    Finder::new().search_dirs(environment::var_os("PATH"))
+}
+
+/// Reports whether `path` is an executable file by the same rules the searches in this crate
+/// apply to candidates.
+///
+/// On Unix, that's a file with an execute bit set that applies to the current user. On Windows,
+/// it's a file whose extension is listed by `%PATHEXT%`, such as `.exe`. On WASI, it's a file
+/// with any execute bit set, or any file if the runtime reports no permissions. Elsewhere, any
+/// file counts. Symlinks are followed, except on Windows, where they count as files as they do when
+/// searching.
+///
+/// # Example
+///
+/// ```no_run
+/// assert!(which::is_executable("/bin/sh"));
+/// ```
+pub fn is_executable<P: AsRef<path::Path>>(path: P) -> bool {
+    let path = path.as_ref();
+    #[cfg(windows)]
+    if !finder::PathExtensions::from_env().matches(path) {
+        return false;
+    }
+    build_binary_checker().is_valid(&Candidate::new(path))
+}
+
+fn build_binary_checker() -> CompositeChecker {
+    CompositeChecker::new()
+        .add_checker(Box::new(ExistedChecker::new()))
+        .add_checker(Box::new(ExecutableChecker::new()))
+}
+
+/// Where the platform keeps its system binaries, for [`WhichConfig::system_fallback`].
+#[cfg(target_os = "android")]
+const SYSTEM_PATH_LIST: Option<&str> = Some("/system/bin:/system/xbin:/vendor/bin");
+#[cfg(not(target_os = "android"))]
+const SYSTEM_PATH_LIST: Option<&str> = None;
+
+/// The extensions of Windows executables, for [`WhichConfig::windows_extensions`], as the default
+/// `%PATHEXT%` starts. They're lowercase, as the names of files are on most filesystems Unix can
+/// see, which unlike Windows' ones tell case apart.
+const WINDOWS_EXTENSIONS: &str = ".com;.exe;.bat;.cmd";
+
+/// A wrapper containing all functionality in this crate.
+#[derive(Clone)]
+pub struct WhichConfig {
+    cwd: Option<either::Either<bool, path::PathBuf>>,
+    custom_path_list: Option<OsString>,
+    binary_name: Option<OsString>,
+    #[cfg(feature = "regex")]
+    regex: Option<Regex>,
+    checkers: Vec<Arc<dyn Checker>>,
+    cache: Option<WhichCache>,
+    limit: Option<usize>,
+    directory: bool,
+    depth: usize,
+    path_separator: Option<u8>,
+    filesystem: Option<Arc<dyn Filesystem>>,
+    system_fallback: bool,
+    observer: Option<Observer>,
+    sysroot: Option<path::PathBuf>,
+    reject_newlines: bool,
+    special_files: bool,
+    reject_empty: bool,
+    verify_signature: bool,
+    hidden_files: bool,
+    resolve_path_entries: bool,
+    path_form: PathForm,
+    relative_to: Option<path::PathBuf>,
+    well_known_dirs: bool,
+    xcode_toolchain: bool,
+    homebrew: bool,
+    windows_extensions: bool,
+    platform: Option<PlatformProfile>,
+    env_overrides: Vec<OsString>,
+    project_dirs: Vec<path::PathBuf>,
+    extension_policy: ExtensionPolicy,
+    unreadable_policy: UnreadablePolicy,
+    slow_dirs: Option<(std::time::Duration, SlowDirCallback)>,
+    env: Environment,
+}
+
+impl Default for WhichConfig {
+    fn default() -> Self {
+        Self {
+            cwd: Some(either::Either::Left(true)),
+            custom_path_list: None,
+            binary_name: None,
+            #[cfg(feature = "regex")]
+            regex: None,
+            checkers: Vec::new(),
+            cache: None,
+            limit: None,
+            directory: false,
+            depth: 0,
+            path_separator: None,
+            filesystem: None,
+            system_fallback: false,
+            observer: None,
+            sysroot: None,
+            reject_newlines: false,
+            special_files: false,
+            reject_empty: false,
+            verify_signature: false,
+            hidden_files: true,
+            resolve_path_entries: false,
+            path_form: PathForm::AsConstructed,
+            relative_to: None,
+            well_known_dirs: false,
+            xcode_toolchain: false,
+            homebrew: false,
+            windows_extensions: false,
+            platform: None,
+            env_overrides: Vec::new(),
+            project_dirs: Vec::new(),
+            extension_policy: ExtensionPolicy::All,
+            unreadable_policy: UnreadablePolicy::Skip,
+            slow_dirs: None,
+            env: Environment::Process,
+        }
+    }
+}
+
+#[cfg(feature = "regex")]
+type Regex = regex::Regex;
+
+#[cfg(not(feature = "regex"))]
+type Regex = ();
+
+impl WhichConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A configuration that reads nothing from the environment: `PATH`, `PATHEXT`, the working
+    /// directory and the variables set with [`EnvSnapshot::var`] are those of `env`, and every
+    /// other variable is taken to be unset,
+    /// e.g. for reproducible builds or sandboxes where access to the environment is audited.
+    ///
+    /// Every environment read a search makes goes through `env`, including those of
+    /// [`Self::env_override`], [`Self::well_known_dirs`], [`Self::homebrew`] and
+    /// [`Self::xcode_toolchain`], so searches give the same results whatever the process's
+    /// environment. [`Self::custom_path_list`] and [`Self::custom_cwd`] still take precedence.
+    ///
+    /// Only the searches made with this configuration are covered, and only their reads of the
+    /// environment: the filesystem is looked at as usual, and so is the setting
+    /// `xcode-select -s` makes for [`Self::xcode_toolchain`]. Nothing checks where `env` came
+    /// from, so one made with [`EnvSnapshot::capture`] holds the process's values, and one made
+    /// from nothing but `None`s is an empty environment rather than an error.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use which::{EnvSnapshot, WhichConfig};
+    ///
+    /// let env = EnvSnapshot::new(
+    ///     Some("/usr/bin:/bin".into()),
+    ///     None,
+    ///     Some("/src/project".into()),
+    /// );
+    /// let cc = WhichConfig::hermetic(env)
+    ///     .binary_name("cc".into())
+    ///     .first_result()
+    ///     .unwrap();
+    /// ```
+    pub fn hermetic(env: EnvSnapshot) -> Self {
+        Self {
+            env: Environment::Explicit(env),
+            ..Self::default()
+        }
+    }
+
+    /// Whether or not to use the current working directory. `true` by default.
+    ///
+    /// # Panics
+    ///
+    /// If regex was set previously, and you've just passed in `use_cwd: true`, this will panic.
+    pub fn system_cwd(mut self, use_cwd: bool) -> Self {
+        #[cfg(feature = "regex")]
+        if self.regex.is_some() && use_cwd {
+            panic!("which can't use regex and cwd at the same time!")
+        }
+        self.cwd = Some(either::Either::Left(use_cwd));
+        self
+    }
+
+    /// Sets a custom path for resolving relative paths.
+    ///
+    /// # Panics
+    ///
+    /// If regex was set previously, this will panic.
+    pub fn custom_cwd(mut self, cwd: path::PathBuf) -> Self {
+        #[cfg(feature = "regex")]
+        if self.regex.is_some() {
+            panic!("which can't use regex and cwd at the same time!")
+        }
+        self.cwd = Some(either::Either::Right(cwd));
+        self
+    }
+
+    /// Sets the path name regex to search for. You ***MUST*** call this, or [`Self::binary_name`] prior to searching.
+    ///
+    /// When `Regex` is disabled this function takes the unit type as a stand in. The parameter will change when
+    /// `Regex` is enabled.
+    ///
+    /// # Panics
+    ///
+    /// If the `regex` feature wasn't turned on for this crate this will always panic. Additionally if a
+    /// `cwd` (aka current working directory) or `binary_name` was set previously, this will panic, as those options
+    /// are incompatible with `regex`.
+    #[allow(unused_variables)]
+    #[allow(unused_mut)]
+    pub fn regex(mut self, regex: Regex) -> Self {
+        #[cfg(not(feature = "regex"))]
+        {
+            panic!("which's regex feature was not enabled in your Cargo.toml!")
+        }
+        #[cfg(feature = "regex")]
+        {
+            if self.cwd != Some(either::Either::Left(false)) && self.cwd.is_some() {
+                panic!("which can't use regex and cwd at the same time!")
+            }
+            if self.binary_name.is_some() {
+                panic!("which can't use `binary_name` and `regex` at the same time!");
+            }
+            self.regex = Some(regex);
+            self
+        }
+    }
+
+    /// Sets the path name to search for. You ***MUST*** call this, or [`Self::regex`] prior to searching.
+    ///
+    /// # Panics
+    ///
+    /// If a `regex` was set previously this will panic as this is not compatible with `regex`.
+    pub fn binary_name(mut self, name: OsString) -> Self {
+        #[cfg(feature = "regex")]
+        if self.regex.is_some() {
+            panic!("which can't use `binary_name` and `regex` at the same time!");
+        }
+        self.binary_name = Some(name);
+        self
+    }
+
+    /// Uses the given string instead of the `PATH` env variable.
+    pub fn custom_path_list(mut self, custom_path_list: OsString) -> Self {
+        self.custom_path_list = Some(custom_path_list);
+        self
+    }
+
+    /// Splits the path list on `separator` instead of the current platform's separator, e.g. to
+    /// resolve against a `;`-separated `PATH` captured on Windows while running elsewhere. With
+    /// `;`, double quotes group entries the way they do on Windows.
+    ///
+    /// # Panics
+    ///
+    /// If `separator` isn't an ASCII character this will panic.
+    pub fn path_separator(mut self, separator: char) -> Self {
+        if !separator.is_ascii() {
+            panic!("which's path separator must be an ASCII character!");
+        }
+        self.path_separator = Some(separator as u8);
+        self
+    }
+
+    /// Uses the `PATH` env variable. Enabled by default.
+    pub fn system_path_list(mut self) -> Self {
+        self.custom_path_list = None;
+        self
+    }
+
+    /// Searches inside `sysroot` as if it was the root directory, e.g. to find out what a name
+    /// would resolve to inside a cross-compilation sysroot or an unpacked container image.
+    ///
+    /// Every path list entry, the working directory and absolute names are resolved under
+    /// `sysroot`, with relative entries taken relative to it, and results are returned as paths
+    /// inside it. Symlinks are followed within `sysroot` too, as in a chroot, so ones with
+    /// absolute targets don't lead outside of it. A [`Self::cache`] isn't used. See
+    /// [`which_in_rootfs`] for container images.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use which::WhichConfig;
+    ///
+    /// let sh = WhichConfig::new()
+    ///     .sysroot("/var/lib/images/alpine".into())
+    ///     .custom_path_list("/usr/local/bin:/usr/bin:/bin".into())
+    ///     .binary_name("sh".into())
+    ///     .first_result()
+    ///     .unwrap();
+    /// assert_eq!(sh, std::path::Path::new("/var/lib/images/alpine/bin/sh"));
+    /// ```
+    pub fn sysroot(mut self, sysroot: path::PathBuf) -> Self {
+        self.sysroot = Some(sysroot);
+        self
+    }
+
+    /// Searches the platform's system directories when the path list is unset or empty, rather
+    /// than finding nothing. Disabled by default.
+    ///
+    /// Only Android has such a convention, falling back to `/system/bin`, `/system/xbin` and
+    /// `/vendor/bin`, so this has no effect elsewhere.
+    pub fn system_fallback(mut self, system_fallback: bool) -> Self {
+        self.system_fallback = system_fallback;
+        self
+    }
+
+    /// Rejects names containing a line break with [`Error::InvalidName`], like empty names and
+    /// names with a nul byte always are. Disabled by default, since they are valid in file
+    /// names on Unix, but rarely intended.
+    pub fn reject_newlines(mut self, reject_newlines: bool) -> Self {
+        self.reject_newlines = reject_newlines;
+        self
+    }
+
+    /// Accepts FIFOs, sockets and device nodes with an execute bit as well as regular files.
+    /// Disabled by default, since opening one of them can block indefinitely.
+    pub fn special_files(mut self, special_files: bool) -> Self {
+        self.special_files = special_files;
+        self
+    }
+
+    /// Rejects empty files, which usually are leftovers of broken installs that fail when
+    /// spawned. Disabled by default.
+    pub fn reject_empty(mut self, reject_empty: bool) -> Self {
+        self.reject_empty = reject_empty;
+        self
+    }
+
+    /// Only accepts files starting with the signature of an executable format the crate knows
+    /// of: ELF, Mach-O, PE, or a `#!` line. Disabled by default.
+    ///
+    /// This catches data files that happen to have their execute bit set, at the cost of
+    /// opening every candidate. Scripts without a `#!` line, such as batch files on Windows, are
+    /// rejected too, and so is anything that isn't a regular file.
+    ///
+    /// Candidates are read from the [`Self::filesystem`] or [`Self::sysroot`] searched, if any.
+    pub fn verify_signature(mut self, verify_signature: bool) -> Self {
+        self.verify_signature = verify_signature;
+        self
+    }
+
+    /// Whether hidden files are eligible matches: those whose name starts with a dot on Unix,
+    /// and those with the hidden or system attribute on Windows. `true` by default.
+    ///
+    /// Turning this off mostly matters for [`Self::regex`] searches, which would otherwise
+    /// match leftovers such as editor backups.
+    pub fn hidden_files(mut self, hidden_files: bool) -> Self {
+        self.hidden_files = hidden_files;
+        self
+    }
+
+    /// Canonicalizes every path list entry before searching it, resolving symlinks. Disabled by
+    /// default.
+    ///
+    /// Results are then found under the resolved directory, e.g. `/usr/local/Cellar/git/bin/git`
+    /// rather than `/usr/local/bin/git` if `/usr/local/bin` links there, so entries reaching the
+    /// same directory produce the same paths, and so do [`Self::checker`]s comparing against
+    /// known directories. Only the entries are resolved: a symlink found inside one is returned
+    /// as it is. On Windows, resolved entries are verbatim paths, as in `\\?\C:\tools`. Has
+    /// no effect with a custom [`Self::filesystem`] or [`Self::sysroot`].
+    pub fn resolve_path_entries(mut self, resolve_path_entries: bool) -> Self {
+        self.resolve_path_entries = resolve_path_entries;
+        self
+    }
+
+    /// The form results are returned in. [`PathForm::AsConstructed`] by default.
+    ///
+    /// Checkers and [`Self::observer`]s see candidates normalized with [`PathForm::Lexical`],
+    /// and as constructed with the other forms.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use which::{PathForm, WhichConfig};
+    ///
+    /// let rustc = WhichConfig::new()
+    ///     .binary_name("rustc".into())
+    ///     .path_form(PathForm::Canonical)
+    ///     .first_result()
+    ///     .unwrap();
+    /// ```
+    pub fn path_form(mut self, path_form: PathForm) -> Self {
+        self.path_form = path_form;
+        self
+    }
+
+    /// Returns results inside `base` relative to it, e.g. `./tools/bin/protoc` rather than
+    /// `/home/me/project/tools/bin/protoc` for a `base` of `/home/me/project`, for embedding in
+    /// command lines that shouldn't depend on where they were generated. Results elsewhere stay
+    /// as they are.
+    ///
+    /// Relative results start with `.`, so they are never taken for a name to look up. They are
+    /// computed after applying [`Self::path_form`], so `base` should be in the same form.
+    pub fn relative_to(mut self, base: path::PathBuf) -> Self {
+        self.relative_to = Some(base);
+        self
+    }
+
+    /// Appends the [`well_known_dirs`] that the path list is missing, for "best effort"
+    /// lookups from processes that may have been started with a minimal `PATH`. Disabled by
+    /// default.
+    pub fn well_known_dirs(mut self, well_known_dirs: bool) -> Self {
+        self.well_known_dirs = well_known_dirs;
+        self
+    }
+
+    /// Also searches the active Xcode developer directory after the path list, like `xcrun
+    /// --find` does, so tools such as `clang` and `ld` are found even when they aren't on
+    /// `PATH`. Disabled by default.
+    ///
+    /// The directories are those of [`macos::toolchain_dirs`] for [`macos::developer_dir`], and
+    /// matches from them have [`Origin::Toolchain`] in [`Self::all_results_with_origin`].
+    pub fn xcode_toolchain(mut self, xcode_toolchain: bool) -> Self {
+        self.xcode_toolchain = xcode_toolchain;
+        self
+    }
+
+    /// Also searches the `bin` and `sbin` directories of the [`macos::homebrew_prefix`] after
+    /// the path list, for processes started without Homebrew's environment, like apps launched
+    /// from the Finder. Disabled by default.
+    ///
+    /// Matches from them have [`Origin::Homebrew`] in [`Self::all_results_with_origin`]. If
+    /// [`Self::xcode_toolchain`] is enabled too, Homebrew's directories are searched first.
+    pub fn homebrew(mut self, homebrew: bool) -> Self {
+        self.homebrew = homebrew;
+        self
+    }
+
+    /// Also tries names with the extensions of Windows executables, `.com`, `.exe`, `.bat` and
+    /// `.cmd`, on platforms other than Windows, e.g. to find `cl.exe` in a Wine prefix or a
+    /// mounted Windows toolchain by looking up `cl`. Disabled by default, and always in effect
+    /// on Windows.
+    ///
+    /// As on Windows, files with those extensions count as executables whatever their
+    /// permissions. Names are still tried as they are first.
+    pub fn windows_extensions(mut self, windows_extensions: bool) -> Self {
+        self.windows_extensions = windows_extensions;
+        self
+    }
+
+    /// Applies the rules of `platform` rather than those of the platform the crate was compiled
+    /// for, e.g. to predict what Windows would resolve from a captured `PATH` and `%PATHEXT%`.
+    ///
+    /// The profile's separator is used for the path list unless [`Self::path_separator`] is
+    /// set. See [`PlatformProfile`] for what can and can't be emulated.
+    pub fn platform(mut self, platform: PlatformProfile) -> Self {
+        self.platform = Some(platform);
+        self
+    }
+
+    /// Tries the file named by the environment variable `var` before the path list, if it's set
+    /// and the file passes the checks, like build tools do with `$CARGO` or `$PYTHON`. Variables
+    /// are tried in the order they were added.
+    ///
+    /// The value is taken as the path of the executable, resolved against the working
+    /// directory if relative, and without executable extensions. Empty values are ignored, and
+    /// so are the variables when a name is resolved against the working directory or a pattern
+    /// is searched for. Matches from them have [`Origin::EnvVar`] in
+    /// [`Self::all_results_with_origin`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use which::WhichConfig;
+    ///
+    /// let cargo = WhichConfig::new()
+    ///     .env_override("CARGO".into())
+    ///     .binary_name("cargo".into())
+    ///     .first_result()
+    ///     .unwrap();
+    /// ```
+    pub fn env_override(mut self, var: OsString) -> Self {
+        self.env_overrides.push(var);
+        self
+    }
+
+    /// Searches `dir` before the path list, like `node_modules/.bin` or `.venv/bin` in a
+    /// project, so that tools installed there shadow global ones as with `npx` or an activated
+    /// virtualenv. Directories are searched in the order they were added.
+    ///
+    /// Relative directories are resolved against the working directory. Path list entries that
+    /// are project directories too are skipped rather than searched twice. Matches from them
+    /// have [`Origin::Project`] in [`Self::all_results_with_origin`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use which::{Origin, WhichConfig};
+    ///
+    /// let (eslint, origin) = WhichConfig::new()
+    ///     .project_dir("node_modules/.bin".into())
+    ///     .binary_name("eslint".into())
+    ///     .all_results_with_origin()
+    ///     .unwrap()
+    ///     .next()
+    ///     .unwrap();
+    /// let local = matches!(origin, Origin::Project(_));
+    /// ```
+    pub fn project_dir(mut self, dir: path::PathBuf) -> Self {
+        self.project_dirs.push(dir);
+        self
+    }
+
+    /// Decides which of the files a name expands to in one directory are matches when several
+    /// executable extensions are present, such as `tool.exe` and `tool.cmd`. By default, every
+    /// one is, in `%PATHEXT%` order, so [`Self::first_result`] returns what `cmd.exe` would run.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use which::{ExtensionPolicy, WhichConfig};
+    ///
+    /// // Prefer a wrapper script over the binary next to it.
+    /// let node = WhichConfig::new()
+    ///     .binary_name("node".into())
+    ///     .extension_policy(ExtensionPolicy::Priority(vec![".cmd".into()]))
+    ///     .first_result()
+    ///     .unwrap();
+    /// ```
+    pub fn extension_policy(mut self, extension_policy: ExtensionPolicy) -> Self {
+        self.extension_policy = extension_policy;
+        self
+    }
+
+    /// Decides what happens when the search comes across a path it can't look at for another
+    /// reason than not existing, such as the candidates in a `PATH` entry the process may not
+    /// search. By default, they're skipped like those that don't exist, which suits interactive
+    /// tools, but security-sensitive callers may rather fail than risk finding a different
+    /// executable than intended, and others warn.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::sync::{Arc, Mutex};
+    /// use which::{UnreadablePolicy, WhichConfig};
+    ///
+    /// let warnings = Arc::new(Mutex::new(Vec::new()));
+    /// let collected = warnings.clone();
+    /// let git = WhichConfig::new()
+    ///     .binary_name("git".into())
+    ///     .unreadable_policy(UnreadablePolicy::Warn(Arc::new(move |error| {
+    ///         collected.lock().unwrap().push(error.clone());
+    ///     })))
+    ///     .first_result();
+    /// for warning in warnings.lock().unwrap().iter() {
+    ///     eprintln!("warning: {}", warning);
+    /// }
+    /// ```
+    pub fn unreadable_policy(mut self, unreadable_policy: UnreadablePolicy) -> Self {
+        self.unreadable_policy = unreadable_policy;
+        self
+    }
+
+    /// Adds a checker every candidate must pass, on top of the built-in existence and
+    /// executability checks.
+    ///
+    /// All checkers, including the built-in ones, run in order of their [`Checker::cost`], so a
+    /// [`Cost::Free`] checker rejecting a candidate means the filesystem is never consulted for it.
+    pub fn checker<C: Checker + 'static>(mut self, checker: C) -> Self {
+        self.checkers.push(Arc::new(checker));
+        self
+    }
 
-/// Find all binaries with `binary_name` in the path list `paths`, using `cwd` to resolve relative paths.
-pub fn which_in_all<T, U, V>(
-    binary_name: T,
-    paths: Option<U>,
-    cwd: V,
-) -> Result<impl Iterator<Item = path::PathBuf>>
-where
-    T: AsRef<OsStr>,
-    U: AsRef<OsStr>,
-    V: AsRef<path::Path>,
-{
-    let binary_checker = build_binary_checker();
+    /// Shares `cache` with this search, so directories already listed by an earlier search
+    /// using the same cache aren't read again unless they changed.
+    pub fn cache(mut self, cache: WhichCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
 
-    let finder = Finder::new();
+    /// Searches for directories instead of executable files, e.g. to locate a toolchain root that
+    /// was put on `PATH`. Disabled by default.
+    ///
+    /// Names aren't expanded with `%PATHEXT%` extensions on Windows in this mode.
+    pub fn directory(mut self, directory: bool) -> Self {
+        self.directory = directory;
+        self
+    }
 
-    finder.find(binary_name, paths, Some(cwd), binary_checker)
-}
+    /// Also searches the subdirectories of every `PATH` entry, up to `depth` levels below it, as
+    /// some SDK and toolchain layouts require. `0`, the default, only searches the entries
+    /// themselves.
+    ///
+    /// Each entry is searched breadth first, with subdirectories in order of their names, before
+    /// moving on to the next entry. Symlinked subdirectories aren't descended into. Regex
+    /// searches ignore this setting.
+    pub fn depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self
+    }
 
-/// Find all binaries with `binary_name` in the path list `paths`, ignoring `cwd`.
-pub fn which_in_global<T, U>(
-    binary_name: T,
-    paths: Option<U>,
-) -> Result<impl Iterator<Item = path::PathBuf>>
-where
-    T: AsRef<OsStr>,
-    U: AsRef<OsStr>,
-{
-    let binary_checker = build_binary_checker();
+    /// Runs the search against `filesystem` instead of the real filesystem, e.g. a [`VirtualFs`]
+    /// in a browser, where there is no filesystem to search. Names resolve by the same rules
+    /// either way.
+    ///
+    /// The working directory and path list fall back to the process's, as usual, which are
+    /// unlikely to make sense for `filesystem`, so set them with [`Self::custom_cwd`] (or
+    /// disable it) and [`Self::custom_path_list`]. A [`Self::cache`] isn't used.
+    pub fn filesystem<F: Filesystem + 'static>(mut self, filesystem: F) -> Self {
+        self.filesystem = Some(Arc::new(filesystem));
+        self
+    }
 
-    let finder = Finder::new();
+    /// Calls `observer` with every candidate path the search checks and whether it was accepted,
+    /// e.g. to show progress or log why a binary wasn't found.
+    ///
+    /// Candidates are reported as they're checked, so a search that stops at its first result
+    /// reports nothing past it.
+    pub fn observer<F>(mut self, observer: F) -> Self
+    where
+        F: Fn(&path::Path, bool) + Send + Sync + 'static,
+    {
+        self.observer = Some(Arc::new(observer));
+        self
+    }
 
-    finder.find(binary_name, paths, Option::<&Path>::None, binary_checker)
-}
+    /// Calls `callback` with each directory the search spends at least `threshold` in and the
+    /// time spent, e.g. to warn that `PATH` contains a slow network mount. The search carries
+    /// on regardless.
+    ///
+    /// Each directory is reported once, as soon as it took long enough: after the candidate or
+    /// listing that pushed it over, which a hanging mount can delay indefinitely. Time spent in
+    /// subdirectories searched because of [`Self::depth`] counts towards each subdirectory.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use which::WhichConfig;
+    ///
+    /// let cargo = WhichConfig::new()
+    ///     .binary_name("cargo".into())
+    ///     .slow_dir_warning(Duration::from_millis(200), |dir, _| {
+    ///         eprintln!("warning: your PATH contains a slow directory: {}", dir.display());
+    ///     })
+    ///     .first_result();
+    /// ```
+    pub fn slow_dir_warning<F>(mut self, threshold: std::time::Duration, callback: F) -> Self
+    where
+        F: Fn(&path::Path, std::time::Duration) + Send + Sync + 'static,
+    {
+        self.slow_dirs = Some((threshold, Arc::new(callback)));
+        self
+    }
 
-fn build_binary_checker() -> CompositeChecker {
-    CompositeChecker::new()
-        .add_checker(Box::new(ExistedChecker::new()))
-        .add_checker(Box::new(ExecutableChecker::new()))
-}
+    /// Returns at most `limit` results from [`Self::all_results`] and friends.
+    ///
+    /// Unlike [`Iterator::take`], the limit is enforced by the search itself, which then never
+    /// checks any candidate past the last result and never touches later directories. This
+    /// matters with expensive custom checkers.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
 
-/// A wrapper containing all functionality in this crate.
-pub struct WhichConfig {
-    cwd: Option<either::Either<bool, path::PathBuf>>,
-    custom_path_list: Option<OsString>,
-    binary_name: Option<OsString>,
-    #[cfg(feature = "regex")]
-    regex: Option<Regex>,
-}
+    /// Finishes configuring, runs the query and returns the first result.
+    pub fn first_result(self) -> Result<path::PathBuf> {
+        self.search(false, false)
+            .and_then(|mut i| i.next().ok_or_else(|| i.failure()))
+    }
 
-impl Default for WhichConfig {
-    fn default() -> Self {
-        Self {
-            cwd: Some(either::Either::Left(true)),
-            custom_path_list: None,
-            binary_name: None,
-            #[cfg(feature = "regex")]
-            regex: None,
+    /// Finishes configuring, runs the query and returns the last result, i.e. the one with the
+    /// lowest precedence.
+    pub fn last_result(self) -> Result<path::PathBuf> {
+        let mut search = self.search(true, false)?;
+        let last = search.by_ref().last();
+        match search.take_error() {
+            Some(error) => Err(Error::Unreadable(error)),
+            None => last.ok_or(Error::CannotFindBinaryPath),
         }
     }
-}
 
-#[cfg(feature = "regex")]
-type Regex = regex::Regex;
+    /// Finishes configuring, runs the query and returns its only result, like [`which_unique`],
+    /// failing with [`Error::Ambiguous`] if more than one distinct file matches.
+    pub fn unique_result(self) -> Result<path::PathBuf> {
+        let mut search = self.search(true, false)?;
+        let result = unique(search.by_ref());
+        match search.take_error() {
+            Some(error) => Err(Error::Unreadable(error)),
+            None => result,
+        }
+    }
 
-#[cfg(not(feature = "regex"))]
-type Regex = ();
+    /// Like [`Self::first_result`], but also reports what the search cost.
+    pub fn first_result_with_stats(self) -> (Result<path::PathBuf>, Stats) {
+        match self.search(false, true) {
+            Ok(mut search) => {
+                let result = search.next().ok_or_else(|| search.failure());
+                (result, search.into_stats().unwrap_or_default())
+            }
+            Err(e) => (Err(e), Stats::default()),
+        }
+    }
 
-impl WhichConfig {
-    pub fn new() -> Self {
-        Self::default()
+    /// Finishes configuring, runs the query and returns all results.
+    ///
+    /// With [`UnreadablePolicy::Fail`], the results end at the first path that couldn't be looked
+    /// at; use [`Self::all_results_with_errors`] to tell that apart from having found them all.
+    pub fn all_results(self) -> Result<impl FusedIterator<Item = path::PathBuf>> {
+        self.search(true, false)
     }
 
-    /// Whether or not to use the current working directory. `true` by default.
+    /// Like [`Self::all_results`], but also reports where each result was found, so that callers
+    /// can tell which `PATH` entry takes precedence without re-deriving it.
     ///
-    /// # Panics
+    /// # Example
     ///
-    /// If regex was set previously, and you've just passed in `use_cwd: true`, this will panic.
-    pub fn system_cwd(mut self, use_cwd: bool) -> Self {
-        #[cfg(feature = "regex")]
-        if self.regex.is_some() && use_cwd {
-            panic!("which can't use regex and cwd at the same time!")
-        }
-        self.cwd = Some(either::Either::Left(use_cwd));
-        self
+    /// ```no_run
+    /// use which::{Origin, WhichConfig};
+    ///
+    /// for (path, origin) in WhichConfig::new()
+    ///     .binary_name("python3".into())
+    ///     .all_results_with_origin()
+    ///     .unwrap()
+    /// {
+    ///     if let Origin::PathEntry(index) = origin {
+    ///         println!("{} (PATH entry {})", path.display(), index);
+    ///     }
+    /// }
+    /// ```
+    pub fn all_results_with_origin(
+        self,
+    ) -> Result<impl FusedIterator<Item = (path::PathBuf, Origin)>> {
+        let mut search = self.search(true, false)?;
+        Ok(std::iter::from_fn(move || search.next_with_origin()).fuse())
     }
 
-    /// Sets a custom path for resolving relative paths.
+    /// Like [`Self::all_results`], but also returns the paths that couldn't be looked at, such as
+    /// candidates in a directory the process may not search, instead of skipping them.
     ///
-    /// # Panics
+    /// A search that skips them silently can't tell "not installed" apart from "not
+    /// accessible". The search carries on after an error, so the results are the same, unless
+    /// the [`Self::unreadable_policy`] is [`UnreadablePolicy::Fail`], which makes the first error
+    /// the last item. Paths that don't exist aren't errors.
     ///
-    /// If regex was set previously, this will panic.
-    pub fn custom_cwd(mut self, cwd: path::PathBuf) -> Self {
-        #[cfg(feature = "regex")]
-        if self.regex.is_some() {
-            panic!("which can't use regex and cwd at the same time!")
-        }
-        self.cwd = Some(either::Either::Right(cwd));
-        self
+    /// # Example
+    ///
+    /// ```no_run
+    /// use which::WhichConfig;
+    ///
+    /// for result in WhichConfig::new()
+    ///     .binary_name("python3".into())
+    ///     .all_results_with_errors()
+    ///     .unwrap()
+    /// {
+    ///     match result {
+    ///         Ok(path) => println!("{}", path.display()),
+    ///         Err(error) => eprintln!("warning: {}", error),
+    ///     }
+    /// }
+    /// ```
+    pub fn all_results_with_errors(
+        self,
+    ) -> Result<impl FusedIterator<Item = std::result::Result<path::PathBuf, CandidateError>>> {
+        let mut prepared = self.prepare(true, false);
+        prepared.finder = prepared.finder.report_errors(true);
+        let mut search = Self::run(prepared)?;
+        Ok(std::iter::from_fn(move || match search.next() {
+            Some(path) => Some(Ok(path)),
+            None => search.take_error().map(Err),
+        })
+        .fuse())
     }
 
-    /// Sets the path name regex to search for. You ***MUST*** call this, or [`Self::binary_name`] prior to searching.
+    /// Like [`Self::all_results`], but returns a boxed iterator, for storing searches behind trait
+    /// objects without naming their type.
     ///
-    /// When `Regex` is disabled this function takes the unit type as a stand in. The parameter will change when
-    /// `Regex` is enabled.
+    /// # Example
     ///
-    /// # Panics
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use which::WhichConfig;
     ///
-    /// If the `regex` feature wasn't turned on for this crate this will always panic. Additionally if a
-    /// `cwd` (aka current working directory) or `binary_name` was set previously, this will panic, as those options
-    /// are incompatible with `regex`.
-    #[allow(unused_variables)]
-    #[allow(unused_mut)]
-    pub fn regex(mut self, regex: Regex) -> Self {
-        #[cfg(not(feature = "regex"))]
-        {
-            panic!("which's regex feature was not enabled in your Cargo.toml!")
-        }
-        #[cfg(feature = "regex")]
-        {
-            if self.cwd != Some(either::Either::Left(false)) && self.cwd.is_some() {
-                panic!("which can't use regex and cwd at the same time!")
-            }
-            if self.binary_name.is_some() {
-                panic!("which can't use `binary_name` and `regex` at the same time!");
-            }
-            self.regex = Some(regex);
-            self
-        }
+    /// let searches: Vec<Box<dyn Iterator<Item = PathBuf> + Send>> = ["cc", "ld"]
+    ///     .into_iter()
+    ///     .map(|name| WhichConfig::new().binary_name(name.into()).all_results_boxed())
+    ///     .collect::<which::Result<_>>()
+    ///     .unwrap();
+    /// ```
+    pub fn all_results_boxed(self) -> Result<Box<dyn Iterator<Item = path::PathBuf> + Send>> {
+        self.search(true, false)
+            .map(|i| Box::new(i) as Box<dyn Iterator<Item = path::PathBuf> + Send>)
     }
 
-    /// Sets the path name to search for. You ***MUST*** call this, or [`Self::regex`] prior to searching.
+    /// Like [`Self::all_results`], but as a [`stream::WhichStream`] that searches in the
+    /// background, see [`crate::stream`].
     ///
-    /// # Panics
+    /// Only available with the `futures-core` feature.
+    #[cfg(feature = "futures-core")]
+    pub fn all_results_stream(self) -> Result<stream::WhichStream> {
+        self.all_results_stream_with(&stream::ThreadSpawner)
+    }
+
+    /// Like [`Self::all_results_stream`], but running the search with `spawner`, e.g. on the
+    /// blocking pool of the async runtime in use.
     ///
-    /// If a `regex` was set previously this will panic as this is not compatible with `regex`.
-    pub fn binary_name(mut self, name: OsString) -> Self {
-        #[cfg(feature = "regex")]
-        if self.regex.is_some() {
-            panic!("which can't use `binary_name` and `regex` at the same time!");
-        }
-        self.binary_name = Some(name);
-        self
+    /// Only available with the `futures-core` feature.
+    #[cfg(feature = "futures-core")]
+    pub fn all_results_stream_with(
+        self,
+        spawner: &dyn stream::Spawner,
+    ) -> Result<stream::WhichStream> {
+        self.all_results_boxed()
+            .map(|search| stream::WhichStream::spawn(search, spawner))
     }
 
-    /// Uses the given string instead of the `PATH` env variable.
-    pub fn custom_path_list(mut self, custom_path_list: OsString) -> Self {
-        self.custom_path_list = Some(custom_path_list);
-        self
+    /// Like [`Self::all_results`], but collapses results that are the same file, e.g. hard links
+    /// to each other or symlinks to the same target, into one group per file. Groups are in the
+    /// order their first path was found.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use which::WhichConfig;
+    ///
+    /// for group in WhichConfig::new()
+    ///     .binary_name("python3".into())
+    ///     .all_results_by_file()
+    ///     .unwrap()
+    /// {
+    ///     println!("{} (also {:?})", group.path.display(), group.links);
+    /// }
+    /// ```
+    pub fn all_results_by_file(self) -> Result<Vec<LinkGroup>> {
+        self.search(true, false).map(same::group_links)
     }
 
-    /// Uses the `PATH` env variable. Enabled by default.
-    pub fn system_path_list(mut self) -> Self {
-        self.custom_path_list = None;
-        self
+    /// Finishes configuring and returns every file the name expands to with an executable
+    /// extension in each directory, whatever [`Self::extension_policy`] says, grouped by
+    /// directory: the file the policy picks, and the others it shadows. Meant for diagnostics,
+    /// e.g. to show that both `tool.bat` and `tool.exe` exist and which one runs.
+    ///
+    /// Only lookups by name are grouped; a [`Self::regex`] is ignored.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use which::WhichConfig;
+    ///
+    /// for group in WhichConfig::new()
+    ///     .binary_name("tool".into())
+    ///     .all_extension_variants()
+    ///     .unwrap()
+    /// {
+    ///     for shadowed in &group.shadowed {
+    ///         println!("{} is shadowed by {}", shadowed.display(), group.path.display());
+    ///     }
+    /// }
+    /// ```
+    pub fn all_extension_variants(self) -> Result<Vec<ExtensionGroup>> {
+        let prepared = self.prepare(true, false);
+        let mut matches = prepared.finder.every_variant(true).find(
+            prepared.binary_name.expect(
+                "binary_name not set! You must set binary_name before searching for variants!",
+            ),
+            prepared.paths,
+            prepared.cwd,
+            prepared.checker,
+        )?;
+        Ok(finder::group_extensions(std::iter::from_fn(move || {
+            matches.next_with_origin()
+        })))
     }
 
-    /// Finishes configuring, runs the query and returns the first result.
-    pub fn first_result(self) -> Result<path::PathBuf> {
-        self.all_results()
-            .and_then(|mut i| i.next().ok_or(Error::CannotFindBinaryPath))
+    /// Finishes configuring and returns the directories with more than one executable named
+    /// like the binary but for case, such as `Foo` and `foo`, which lookups on a case-sensitive
+    /// filesystem tell apart but case-insensitive ones, like macOS' by default, can't. Names are
+    /// compared without executable extensions being added.
+    ///
+    /// Each directory is listed once. Only lookups by name in the path list are checked for
+    /// collisions; a [`Self::regex`] is ignored.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use which::WhichConfig;
+    ///
+    /// for collision in WhichConfig::new()
+    ///     .binary_name("make".into())
+    ///     .case_collisions()
+    ///     .unwrap()
+    /// {
+    ///     println!("{}: {:?}", collision.dir.display(), collision.paths);
+    /// }
+    /// ```
+    pub fn case_collisions(self) -> Result<Vec<CaseCollision>> {
+        let prepared = self.prepare(false, false);
+        let name = collision::fold(&prepared.binary_name.expect(
+            "binary_name not set! You must set binary_name before looking for collisions!",
+        ));
+        let mut matches = prepared.finder.find_listed(
+            PredicateMatcher(|file_name: &OsStr| collision::fold(file_name) == name),
+            prepared.paths,
+            prepared.checker,
+        )?;
+        Ok(collision::collisions(std::iter::from_fn(move || {
+            matches.next_with_origin()
+        })))
     }
 
-    /// Finishes configuring, runs the query and returns all results.
-    pub fn all_results(self) -> Result<impl Iterator<Item = path::PathBuf>> {
-        let binary_checker = build_binary_checker();
+    /// Like [`Self::all_results`], but collects every result and also reports what the search
+    /// cost.
+    pub fn all_results_with_stats(self) -> Result<(Vec<path::PathBuf>, Stats)> {
+        let mut search = self.search(true, true)?;
+        let results = search.by_ref().collect();
+        Ok((results, search.into_stats().unwrap_or_default()))
+    }
+
+    /// Finishes configuring and returns the directories the query would search, in order,
+    /// without searching them: any [`Self::project_dir`]s, the entries of the path list, split,
+    /// expanded and rerooted as configured, then any extra directories, such as
+    /// [`Self::homebrew`]'s.
+    ///
+    /// Subdirectories searched because of [`Self::depth`] aren't included, and neither is the
+    /// working directory, which only names containing a separator are resolved against.
+    pub fn search_dirs(self) -> Vec<path::PathBuf> {
+        let prepared = self.prepare(false, false);
+        prepared.finder.search_dirs(prepared.paths)
+    }
 
-        let finder = Finder::new();
+    fn search(self, batch: bool, stats: bool) -> Result<Search> {
+        Self::run(self.prepare(batch, stats))
+    }
 
-        let paths = self.custom_path_list.or_else(|| env::var_os("PATH"));
+    /// Starts the search `prepared` sets up.
+    fn run(prepared: Prepared) -> Result<Search> {
+        let finder = prepared.finder;
 
         #[cfg(feature = "regex")]
-        if let Some(regex) = self.regex {
+        if let Some(regex) = prepared.regex {
             return finder
-                .find_re(regex, paths, binary_checker)
-                .map(|i| Box::new(i) as Box<dyn Iterator<Item = path::PathBuf>>);
+                .find_re(regex, prepared.paths, prepared.checker)
+                .map(Search::Regex);
         }
 
-        let cwd = match self.cwd {
-            Some(either::Either::Left(false)) => None,
-            Some(either::Either::Right(custom)) => Some(custom),
-            None | Some(either::Either::Left(true)) => env::current_dir().ok(),
-        };
-
         finder
             .find(
-                self.binary_name.expect(
+                prepared.binary_name.expect(
                     "binary_name not set! You must set binary_name or regex before searching!",
                 ),
-                paths,
-                cwd,
-                binary_checker,
+                prepared.paths,
+                prepared.cwd,
+                prepared.checker,
             )
-            .map(|i| Box::new(i) as Box<dyn Iterator<Item = path::PathBuf>>)
+            .map(Search::Name)
+    }
+
+    /// Sets up the search this configures, down to the path list it runs over.
+    fn prepare(self, batch: bool, stats: bool) -> Prepared {
+        let windows_rules = self
+            .platform
+            .as_ref()
+            .map_or(cfg!(windows), PlatformProfile::is_windows);
+        let windows_extensions = (self.windows_extensions && !windows_rules)
+            .then(|| PathExtensions::new(win::parse_pathext(WINDOWS_EXTENSIONS)));
+        let path_extensions = match &self.platform {
+            Some(platform) if windows_rules => Some(platform.expansions()),
+            Some(platform) => windows_extensions.clone().or(Some(platform.expansions())),
+            None => windows_extensions
+                .clone()
+                .or_else(|| Some(self.env.path_extensions())),
+        };
+        let fold_case = self
+            .platform
+            .as_ref()
+            .is_some_and(|platform| !platform.is_case_sensitive());
+        let path_separator = self
+            .path_separator
+            .or_else(|| self.platform.as_ref().map(PlatformProfile::separator));
+
+        let base_checker = if self.directory {
+            CompositeChecker::new().add_checker(Box::new(DirChecker::new()))
+        } else {
+            let checker = CompositeChecker::new().add_checker(Box::new(
+                ExistedChecker::new().special_files(self.special_files),
+            ));
+            // Windows has no execute permissions to check.
+            if windows_rules && !cfg!(windows) {
+                checker
+            } else {
+                checker.add_checker(Box::new(
+                    ExecutableChecker::new().windows_extensions(windows_extensions),
+                ))
+            }
+        };
+        let base_checker = if self.reject_empty {
+            base_checker.add_checker(Box::new(NonEmptyChecker::new()))
+        } else {
+            base_checker
+        };
+        let base_checker = if self.verify_signature {
+            base_checker.add_checker(Box::new(SignatureChecker::new()))
+        } else {
+            base_checker
+        };
+        let base_checker = if self.hidden_files {
+            base_checker
+        } else {
+            base_checker.add_checker(Box::new(VisibleChecker::new()))
+        };
+        let binary_checker = self
+            .checkers
+            .into_iter()
+            .fold(base_checker, |composite, checker| {
+                composite.add_checker(Box::new(checker))
+            });
+
+        let env = &self.env;
+        let paths = self.custom_path_list.or_else(|| env.var_os("PATH"));
+        let (paths, separator) = match paths {
+            Some(ref p) if !p.is_empty() => (paths, path_separator),
+            // The fallback list is in the platform's format, whatever the custom one's is.
+            _ if self.system_fallback => match SYSTEM_PATH_LIST {
+                Some(list) => (Some(list.into()), None),
+                None => (paths, path_separator),
+            },
+            _ => (paths, path_separator),
+        };
+        let paths = if self.well_known_dirs {
+            well_known::append_missing(paths, separator, well_known::well_known_dirs_in(env))
+        } else {
+            paths
+        };
+
+        let mut extra_dirs = Vec::new();
+        if let Some(prefix) = self
+            .homebrew
+            .then(|| macos::homebrew_prefix_in(env))
+            .flatten()
+        {
+            for dir in ["bin", "sbin"] {
+                extra_dirs.push((prefix.join(dir), Origin::Homebrew));
+            }
+        }
+        if let Some(dir) = self
+            .xcode_toolchain
+            .then(|| macos::developer_dir_in(env))
+            .flatten()
+        {
+            for dir in macos::toolchain_dirs(&dir) {
+                extra_dirs.push((dir, Origin::Toolchain));
+            }
+        }
+
+        let cwd = match self.cwd {
+            Some(either::Either::Left(false)) => None,
+            Some(either::Either::Right(custom)) => Some(custom),
+            None | Some(either::Either::Left(true)) => env.current_dir(),
+        };
+        let resolve = |path: path::PathBuf| match &cwd {
+            Some(cwd) => cwd.join(path),
+            None => path,
+        };
+        let overrides = self
+            .env_overrides
+            .iter()
+            .enumerate()
+            .filter_map(|(index, var)| {
+                let value = env.var_os(var).filter(|value| !value.is_empty())?;
+                Some((resolve(value.into()), Origin::EnvVar(index)))
+            })
+            .collect();
+        let project_dirs = self
+            .project_dirs
+            .into_iter()
+            .enumerate()
+            .map(|(index, dir)| (resolve(dir), Origin::Project(index)))
+            .collect();
+
+        let filesystem = match (self.filesystem, &self.sysroot) {
+            (None, Some(sysroot)) => Some(Arc::new(RootedFs::new(sysroot.clone())) as Arc<_>),
+            (filesystem, _) => filesystem,
+        };
+
+        let finder = Finder::new()
+            .cache(self.cache)
+            .extensions(!self.directory)
+            .depth(self.depth)
+            .separator(separator)
+            .batch(batch)
+            .limit(self.limit)
+            .filesystem(filesystem)
+            .observer(self.observer)
+            .slow_dirs(self.slow_dirs)
+            .root(self.sysroot)
+            .reject_newlines(self.reject_newlines)
+            .resolve_dirs(self.resolve_path_entries)
+            .form(self.path_form)
+            .relative_to(self.relative_to)
+            .leading_dirs(project_dirs)
+            .extra_dirs(extra_dirs)
+            .overrides(overrides)
+            .path_extensions(path_extensions)
+            .fold_case(fold_case)
+            .extension_policy(self.extension_policy)
+            .unreadable_policy(self.unreadable_policy)
+            .stats(stats);
+
+        Prepared {
+            finder,
+            paths,
+            cwd,
+            checker: binary_checker,
+            binary_name: self.binary_name,
+            #[cfg(feature = "regex")]
+            regex: self.regex,
+        }
     }
 }
 
+/// A search set up by [`WhichConfig`], ready to run.
+struct Prepared {
+    finder: Finder,
+    paths: Option<OsString>,
+    cwd: Option<path::PathBuf>,
+    checker: CompositeChecker,
+    binary_name: Option<OsString>,
+    #[cfg(feature = "regex")]
+    regex: Option<Regex>,
+}
+
+/// The path list `paths` that a [`Path`] or [`CanonicalPath`] is found in, to run it with.
+fn found_in<U: AsRef<OsStr>>(paths: &Option<U>) -> command::FoundIn {
+    command::FoundIn::Paths(paths.as_ref().map(|paths| paths.as_ref().to_owned()))
+}
+
 /// An owned, immutable wrapper around a `PathBuf` containing the path of an executable.
 ///
 /// The constructed `PathBuf` is the output of `which` or `which_in`, but `which::Path` has the
@@ -400,9 +2182,10 @@ impl WhichConfig {
 ///
 /// Since `which::Path` implements `Deref` for `std::path::Path`, all methods on `&std::path::Path`
 /// are also available to `&which::Path` values.
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone)]
 pub struct Path {
     inner: path::PathBuf,
+    found_in: command::FoundIn,
 }
 
 impl Path {
@@ -410,14 +2193,22 @@ impl Path {
     ///
     /// This calls `which` and maps the result into a `Path`.
     pub fn new<T: AsRef<OsStr>>(binary_name: T) -> Result<Path> {
-        which(binary_name).map(|inner| Path { inner })
+        which(binary_name).map(|inner| Path {
+            inner,
+            found_in: command::FoundIn::Env,
+        })
     }
 
     /// Returns the paths of all executable binaries by a name.
     ///
     /// this calls `which_all` and maps the results into `Path`s.
-    pub fn all<T: AsRef<OsStr>>(binary_name: T) -> Result<impl Iterator<Item = Path>> {
-        which_all(binary_name).map(|inner| inner.map(|inner| Path { inner }))
+    pub fn all<T: AsRef<OsStr>>(binary_name: T) -> Result<impl FusedIterator<Item = Path>> {
+        which_all(binary_name).map(|inner| {
+            inner.map(|inner| Path {
+                inner,
+                found_in: command::FoundIn::Env,
+            })
+        })
     }
 
     /// Returns the path of an executable binary by name in the path list `paths` and using the
@@ -430,7 +2221,8 @@ impl Path {
         U: AsRef<OsStr>,
         V: AsRef<path::Path>,
     {
-        which_in(binary_name, paths, cwd).map(|inner| Path { inner })
+        let found_in = found_in(&paths);
+        which_in(binary_name, paths, cwd).map(|inner| Path { inner, found_in })
     }
 
     /// Returns all paths of an executable binary by name in the path list `paths` and using the
@@ -441,13 +2233,19 @@ impl Path {
         binary_name: T,
         paths: Option<U>,
         cwd: V,
-    ) -> Result<impl Iterator<Item = Path>>
+    ) -> Result<impl FusedIterator<Item = Path>>
     where
         T: AsRef<OsStr>,
         U: AsRef<OsStr>,
         V: AsRef<path::Path>,
     {
-        which_in_all(binary_name, paths, cwd).map(|inner| inner.map(|inner| Path { inner }))
+        let found_in = found_in(&paths);
+        which_in_all(binary_name, paths, cwd).map(|inner| {
+            inner.map(move |inner| Path {
+                inner,
+                found_in: found_in.clone(),
+            })
+        })
     }
 
     /// Returns a reference to a `std::path::Path`.
@@ -459,6 +2257,16 @@ impl Path {
     pub fn into_path_buf(self) -> path::PathBuf {
         self.inner
     }
+
+    /// Returns a `std::process::Command` that runs this executable.
+    ///
+    /// On Windows, files `CreateProcess` can't start, like scripts with a `#!` line or `.vbs`
+    /// and `.ps1` files found through `%PATHEXT%`, are run through their interpreter, with the
+    /// path as its first argument after the interpreter's own. Arguments added to the command
+    /// come after that.
+    pub fn to_command(&self) -> std::process::Command {
+        command::command(&self.inner, &self.found_in)
+    }
 }
 
 impl fmt::Debug for Path {
@@ -487,6 +2295,14 @@ impl AsRef<OsStr> for Path {
     }
 }
 
+impl PartialEq for Path {
+    fn eq(&self, other: &Path) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl Eq for Path {}
+
 impl PartialEq<path::PathBuf> for Path {
     fn eq(&self, other: &path::PathBuf) -> bool {
         self.inner == *other
@@ -512,9 +2328,10 @@ impl PartialEq<Path> for path::PathBuf {
 ///
 /// Since `CanonicalPath` implements `Deref` for `std::path::Path`, all methods on
 /// `&std::path::Path` are also available to `&CanonicalPath` values.
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone)]
 pub struct CanonicalPath {
     inner: path::PathBuf,
+    found_in: command::FoundIn,
 }
 
 impl CanonicalPath {
@@ -523,8 +2340,14 @@ impl CanonicalPath {
     /// This calls `which` and `Path::canonicalize` and maps the result into a `CanonicalPath`.
     pub fn new<T: AsRef<OsStr>>(binary_name: T) -> Result<CanonicalPath> {
         which(binary_name)
-            .and_then(|p| p.canonicalize().map_err(|_| Error::CannotCanonicalize))
-            .map(|inner| CanonicalPath { inner })
+            .and_then(|p| {
+                p.canonicalize()
+                    .map_err(|e| Error::CannotCanonicalize(e.into()))
+            })
+            .map(|inner| CanonicalPath {
+                inner,
+                found_in: command::FoundIn::Env,
+            })
     }
 
     /// Returns the canonical paths of an executable binary by name.
@@ -532,13 +2355,16 @@ impl CanonicalPath {
     /// This calls `which_all` and `Path::canonicalize` and maps the results into `CanonicalPath`s.
     pub fn all<T: AsRef<OsStr>>(
         binary_name: T,
-    ) -> Result<impl Iterator<Item = Result<CanonicalPath>>> {
+    ) -> Result<impl FusedIterator<Item = Result<CanonicalPath>>> {
         which_all(binary_name).map(|inner| {
             inner.map(|inner| {
                 inner
                     .canonicalize()
-                    .map_err(|_| Error::CannotCanonicalize)
-                    .map(|inner| CanonicalPath { inner })
+                    .map_err(|e| Error::CannotCanonicalize(e.into()))
+                    .map(|inner| CanonicalPath {
+                        inner,
+                        found_in: command::FoundIn::Env,
+                    })
             })
         })
     }
@@ -553,9 +2379,13 @@ impl CanonicalPath {
         U: AsRef<OsStr>,
         V: AsRef<path::Path>,
     {
+        let found_in = found_in(&paths);
         which_in(binary_name, paths, cwd)
-            .and_then(|p| p.canonicalize().map_err(|_| Error::CannotCanonicalize))
-            .map(|inner| CanonicalPath { inner })
+            .and_then(|p| {
+                p.canonicalize()
+                    .map_err(|e| Error::CannotCanonicalize(e.into()))
+            })
+            .map(|inner| CanonicalPath { inner, found_in })
     }
 
     /// Returns all of the canonical paths of an executable binary by name in the path list `paths` and
@@ -566,18 +2396,20 @@ impl CanonicalPath {
         binary_name: T,
         paths: Option<U>,
         cwd: V,
-    ) -> Result<impl Iterator<Item = Result<CanonicalPath>>>
+    ) -> Result<impl FusedIterator<Item = Result<CanonicalPath>>>
     where
         T: AsRef<OsStr>,
         U: AsRef<OsStr>,
         V: AsRef<path::Path>,
     {
+        let found_in = found_in(&paths);
         which_in_all(binary_name, paths, cwd).map(|inner| {
-            inner.map(|inner| {
+            inner.map(move |inner| {
+                let found_in = found_in.clone();
                 inner
                     .canonicalize()
-                    .map_err(|_| Error::CannotCanonicalize)
-                    .map(|inner| CanonicalPath { inner })
+                    .map_err(|e| Error::CannotCanonicalize(e.into()))
+                    .map(|inner| CanonicalPath { inner, found_in })
             })
         })
     }
@@ -591,6 +2423,16 @@ impl CanonicalPath {
     pub fn into_path_buf(self) -> path::PathBuf {
         self.inner
     }
+
+    /// Returns a `std::process::Command` that runs this executable.
+    ///
+    /// On Windows, files `CreateProcess` can't start, like scripts with a `#!` line or `.vbs`
+    /// and `.ps1` files found through `%PATHEXT%`, are run through their interpreter, with the
+    /// path as its first argument after the interpreter's own. Arguments added to the command
+    /// come after that.
+    pub fn to_command(&self) -> std::process::Command {
+        command::command(&self.inner, &self.found_in)
+    }
 }
 
 impl fmt::Debug for CanonicalPath {
@@ -619,6 +2461,14 @@ impl AsRef<OsStr> for CanonicalPath {
     }
 }
 
+impl PartialEq for CanonicalPath {
+    fn eq(&self, other: &CanonicalPath) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl Eq for CanonicalPath {}
+
 impl PartialEq<path::PathBuf> for CanonicalPath {
     fn eq(&self, other: &path::PathBuf) -> bool {
         self.inner == *other
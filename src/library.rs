@@ -0,0 +1,112 @@
+//! Locating shared libraries the way the platform's dynamic loader searches its path variable.
+//!
+//! # Example
+//!
+//! ```no_run
+//! // libz.so on Linux, libz.dylib on macOS, z.dll on Windows.
+//! let zlib = which::lib::find("z").unwrap();
+//! ```
+
+use crate::checker::{CompositeChecker, ExistedChecker};
+use crate::error::*;
+use crate::finder::Finder;
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::iter::FusedIterator;
+use std::path::{Path, PathBuf};
+
+/// The variable listing the directories libraries are loaded from.
+#[cfg(windows)]
+pub const PATH_VAR: &str = "PATH";
+/// The variable listing the directories libraries are loaded from.
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub const PATH_VAR: &str = "DYLD_LIBRARY_PATH";
+/// The variable listing the directories libraries are loaded from.
+#[cfg(not(any(windows, target_os = "macos", target_os = "ios")))]
+pub const PATH_VAR: &str = "LD_LIBRARY_PATH";
+
+/// Find the shared library `name` in the directories listed by [`PATH_VAR`].
+///
+/// `name` is either the bare library name, as passed to `-l`, or a full file name ending in the
+/// platform's suffix, so `"z"` and `"libz.so"` find the same file on Linux.
+pub fn find<T: AsRef<OsStr>>(name: T) -> Result<PathBuf> {
+    find_all(name).and_then(|mut i| i.next().ok_or(Error::CannotFindBinaryPath))
+}
+
+/// Find all shared libraries named `name` in the directories listed by [`PATH_VAR`].
+pub fn find_all<T: AsRef<OsStr>>(name: T) -> Result<impl FusedIterator<Item = PathBuf>> {
+    find_all_in(name, crate::environment::var_os(PATH_VAR))
+}
+
+/// Find the shared library `name` in the path list `paths`.
+pub fn find_in<T, U>(name: T, paths: Option<U>) -> Result<PathBuf>
+where
+    T: AsRef<OsStr>,
+    U: AsRef<OsStr>,
+{
+    find_all_in(name, paths).and_then(|mut i| i.next().ok_or(Error::CannotFindBinaryPath))
+}
+
+/// Find all shared libraries named `name` in the path list `paths`.
+pub fn find_all_in<T, U>(name: T, paths: Option<U>) -> Result<impl FusedIterator<Item = PathBuf>>
+where
+    T: AsRef<OsStr>,
+    U: AsRef<OsStr>,
+{
+    let checker = CompositeChecker::new().add_checker(Box::new(ExistedChecker::new()));
+
+    let finder = Finder::new().extensions(false);
+
+    finder.find(
+        file_name(name.as_ref()),
+        paths,
+        Option::<&Path>::None,
+        checker,
+    )
+}
+
+/// Returns the file name of library `name`, e.g. `libfoo.so` for `foo` on Linux. Names that
+/// already end in the platform's suffix are returned as they are.
+pub fn file_name(name: &OsStr) -> OsString {
+    if has_suffix(&name.to_string_lossy(), env::consts::DLL_SUFFIX) {
+        return name.to_os_string();
+    }
+    let mut file_name = OsString::from(env::consts::DLL_PREFIX);
+    file_name.push(name);
+    file_name.push(env::consts::DLL_SUFFIX);
+    file_name
+}
+
+// File names are case-insensitive on Windows, so `FOO.DLL` counts too.
+#[cfg(windows)]
+fn has_suffix(name: &str, suffix: &str) -> bool {
+    name.len()
+        .checked_sub(suffix.len())
+        .and_then(|start| name.get(start..))
+        .is_some_and(|end| end.eq_ignore_ascii_case(suffix))
+}
+
+#[cfg(not(windows))]
+fn has_suffix(name: &str, suffix: &str) -> bool {
+    name.ends_with(suffix)
+}
+
+#[cfg(all(test, any(target_os = "linux", windows)))]
+mod test {
+    use super::*;
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_file_name() {
+        assert_eq!(file_name("z".as_ref()), "libz.so");
+        assert_eq!(file_name("libz.so".as_ref()), "libz.so");
+        assert_eq!(file_name("libz".as_ref()), "liblibz.so");
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn test_file_name() {
+        assert_eq!(file_name("z".as_ref()), "z.dll");
+        assert_eq!(file_name("z.DLL".as_ref()), "z.DLL");
+    }
+}
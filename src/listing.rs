@@ -0,0 +1,258 @@
+#[cfg(windows)]
+use crate::checker::FileInfo;
+use crate::checker::FileKind;
+use std::collections::{HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// What reading a directory told about one of its entries, beyond its name.
+#[derive(Clone, Debug)]
+pub enum Listed {
+    /// Nothing, so checking the entry takes a lookup of its own.
+    Name,
+    /// The kind of file the entry is, from `d_type` on Linux.
+    #[cfg_attr(
+        not(any(target_os = "linux", target_os = "android", windows)),
+        allow(dead_code)
+    )]
+    Kind(FileKind),
+    /// Everything the checkers look at, from `FindFirstFileExW` on Windows.
+    #[cfg(windows)]
+    Info(FileInfo),
+}
+
+impl Listed {
+    /// What's still true once the entry may have been modified since the listing was read: a
+    /// file can't change its kind without being replaced, which shows in the directory's
+    /// modification time, but its size and attributes can.
+    pub fn kind_only(&self) -> Listed {
+        match self {
+            #[cfg(windows)]
+            Listed::Info(info) => Listed::Kind(info.kind()),
+            listed => listed.clone(),
+        }
+    }
+}
+
+/// The file names in one directory, read once so that any number of names or patterns can be
+/// matched against it without touching the filesystem again.
+pub struct DirListing {
+    dir: PathBuf,
+    names: Vec<OsString>,
+    listed: Vec<Listed>,
+    index: HashMap<OsString, usize>,
+    /// The names with their case folded, built the first time a name isn't found as spelled.
+    folded: once_cell::sync::OnceCell<HashSet<OsString>>,
+}
+
+impl DirListing {
+    /// Reads `dir` with as few system calls as the platform allows, keeping whatever the
+    /// directory read says about each entry.
+    pub fn read(dir: &Path) -> io::Result<DirListing> {
+        Ok(DirListing::with_listed(dir, read_entries(dir)?))
+    }
+
+    /// A listing of `dir` made up of `names`, which were read elsewhere.
+    pub fn new(dir: &Path, names: Vec<OsString>) -> DirListing {
+        let listed = vec![Listed::Name; names.len()];
+        DirListing::from_parts(dir, names, listed)
+    }
+
+    fn with_listed(dir: &Path, entries: Vec<(OsString, Listed)>) -> DirListing {
+        let (names, listed) = entries.into_iter().unzip();
+        DirListing::from_parts(dir, names, listed)
+    }
+
+    fn from_parts(dir: &Path, names: Vec<OsString>, listed: Vec<Listed>) -> DirListing {
+        let index = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (lookup_key(name), i))
+            .collect();
+
+        DirListing {
+            dir: dir.to_path_buf(),
+            names,
+            listed,
+            index,
+            folded: once_cell::sync::OnceCell::new(),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &OsStr> {
+        self.names.iter().map(OsString::as_os_str)
+    }
+
+    /// The names in the listing, with what the directory read told about each.
+    pub fn entries(&self) -> impl Iterator<Item = (&OsStr, &Listed)> {
+        self.names().zip(&self.listed)
+    }
+
+    /// Looks up `file_name` the way the filesystem would (case-insensitively on Windows),
+    /// returning the name as it is actually spelled in the directory.
+    pub fn get(&self, file_name: &OsStr) -> Option<&OsStr> {
+        self.index
+            .get(&lookup_key(file_name))
+            .map(|&i| self.names[i].as_os_str())
+    }
+
+    /// Whether the listing has `file_name` spelled in a different case. Filesystems other than
+    /// Windows' may be case-insensitive too, like those of macOS by default, in which case the
+    /// name can be looked up as it is spelled.
+    pub fn has_other_case(&self, file_name: &OsStr) -> bool {
+        if cfg!(windows) || self.get(file_name).is_some() {
+            return false;
+        }
+        self.folded
+            .get_or_init(|| self.names().map(crate::win::fold_case).collect())
+            .contains(&crate::win::fold_case(file_name))
+    }
+}
+
+#[cfg(windows)]
+fn lookup_key(name: &OsStr) -> OsString {
+    crate::win::fold_case(name)
+}
+
+#[cfg(not(windows))]
+fn lookup_key(name: &OsStr) -> OsString {
+    name.to_os_string()
+}
+
+/// Reads `dir` with `getdents64` and a large buffer, which takes one system call per few
+/// hundred entries and gives the type of each.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn read_entries(dir: &Path) -> io::Result<Vec<(OsString, Listed)>> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+
+    let path = CString::new(dir.as_os_str().as_bytes())?;
+    // SAFETY: `path` is nul terminated.
+    let fd = unsafe {
+        libc::open(
+            path.as_ptr(),
+            libc::O_RDONLY | libc::O_DIRECTORY | libc::O_CLOEXEC,
+        )
+    };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: the descriptor was just opened and is owned by nothing else.
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    let mut buf = vec![0u8; 32 * 1024];
+    let mut entries = Vec::new();
+    loop {
+        // SAFETY: the kernel writes at most `buf.len()` bytes into `buf`.
+        let read = unsafe {
+            libc::syscall(
+                libc::SYS_getdents64,
+                fd.as_raw_fd(),
+                buf.as_mut_ptr(),
+                buf.len(),
+            )
+        };
+        if read < 0 {
+            let error = io::Error::last_os_error();
+            match error.raw_os_error() {
+                Some(libc::EINTR) => continue,
+                // Seccomp filters sometimes leave only `readdir`'s own way in.
+                Some(libc::ENOSYS) => return read_names(dir),
+                _ => return Err(error),
+            }
+        }
+        if read == 0 {
+            return Ok(entries);
+        }
+
+        // Each record is a `linux_dirent64`: an 8-byte inode and offset, a 2-byte record length,
+        // a 1-byte type and the nul-terminated name.
+        let mut records = &buf[..read as usize];
+        while records.len() > 19 {
+            let len = usize::from(u16::from_ne_bytes([records[16], records[17]]));
+            if len < 20 || len > records.len() {
+                break;
+            }
+            let kind = records[18];
+            let name = &records[19..len];
+            let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+            records = &records[len..];
+            if name != b"." && name != b".." {
+                entries.push((OsStr::from_bytes(name).to_owned(), listed(kind)));
+            }
+        }
+    }
+}
+
+/// Symlinks are followed when candidates are looked up, so their own type says nothing.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn listed(d_type: u8) -> Listed {
+    match d_type {
+        libc::DT_REG => Listed::Kind(FileKind::File),
+        libc::DT_DIR => Listed::Kind(FileKind::Dir),
+        libc::DT_FIFO | libc::DT_SOCK | libc::DT_CHR | libc::DT_BLK => {
+            Listed::Kind(FileKind::Other)
+        }
+        _ => Listed::Name,
+    }
+}
+
+/// Reads `dir` with `FindFirstFileExW`, which gives everything a lookup of each entry would.
+#[cfg(windows)]
+fn read_entries(dir: &Path) -> io::Result<Vec<(OsString, Listed)>> {
+    Ok(crate::dir_query::query_prefixed(dir, OsStr::new(""))?
+        .into_iter()
+        .filter(|(name, _)| name != "." && name != "..")
+        .map(|(name, info)| (name, Listed::Info(info)))
+        .collect())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", windows)))]
+fn read_entries(dir: &Path) -> io::Result<Vec<(OsString, Listed)>> {
+    read_names(dir)
+}
+
+#[cfg_attr(windows, allow(dead_code))]
+fn read_names(dir: &Path) -> io::Result<Vec<(OsString, Listed)>> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir)?.flatten() {
+        entries.push((entry.file_name(), Listed::Name));
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_read() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("file"), "").unwrap();
+        fs::create_dir(dir.path().join("dir")).unwrap();
+
+        let listing = DirListing::read(dir.path()).unwrap();
+        let mut names: Vec<_> = listing.names().collect();
+        names.sort();
+        assert_eq!(names, ["dir", "file"]);
+
+        let kind = |name: &str| match listing.entries().find(|(n, _)| *n == name).unwrap().1 {
+            Listed::Name => None,
+            Listed::Kind(kind) => Some(*kind),
+            #[cfg(windows)]
+            Listed::Info(info) => Some(info.kind()),
+        };
+        if cfg!(any(target_os = "linux", target_os = "android", windows)) {
+            assert_eq!(kind("file"), Some(FileKind::File));
+            assert_eq!(kind("dir"), Some(FileKind::Dir));
+        }
+        assert!(DirListing::read(&dir.path().join("file")).is_err());
+    }
+}
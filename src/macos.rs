@@ -0,0 +1,185 @@
+//! The `PATH` a login shell starts with on macOS, for apps that inherit a minimal one.
+//!
+//! Apps launched from the Finder or the Dock get `PATH` from `launchd`, which is just
+//! `/usr/bin:/bin:/usr/sbin:/sbin`, so Homebrew's tools and the like can't be found. Login shells
+//! run `/usr/libexec/path_helper`, which builds `PATH` from `/etc/paths` and `/etc/paths.d`.
+//! This does the same without spawning a shell.
+//!
+//! It also finds the Homebrew prefix and the active Xcode developer directory, whose tools, such
+//! as `clang` and `ld`, `xcrun` finds even though they aren't on `PATH`.
+//!
+//! These are available on every platform, e.g. for inspecting a macOS system image from
+//! elsewhere.
+
+use crate::environment::Environment;
+use crate::split::SplitPaths;
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Builds the `PATH` a login shell would have, the way `path_helper` does: the directories
+/// listed in `/etc/paths`, then those in the files in `/etc/paths.d` in name order, then the
+/// entries of the current `PATH` not listed already.
+///
+/// # Example
+///
+/// ```no_run
+/// let path = which::macos::login_path();
+/// let make = which::which_in("make", Some(path), "/").unwrap();
+/// ```
+pub fn login_path() -> OsString {
+    login_path_in(
+        Path::new("/"),
+        crate::environment::var_os("PATH").as_deref(),
+    )
+}
+
+/// Like [`login_path`], but reads the configuration below `root` rather than `/`, and adds the
+/// entries of `current` rather than of the current `PATH`.
+pub fn login_path_in(root: &Path, current: Option<&OsStr>) -> OsString {
+    let etc = root.join("etc");
+    let mut files = vec![etc.join("paths")];
+    if let Ok(entries) = fs::read_dir(etc.join("paths.d")) {
+        let mut names: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+        names.sort();
+        files.extend(names);
+    }
+
+    let mut dirs: Vec<OsString> = Vec::new();
+    let mut seen = HashSet::new();
+    let mut add = |dir: OsString| {
+        if !dir.is_empty() && seen.insert(dir.clone()) {
+            dirs.push(dir);
+        }
+    };
+    for file in files {
+        if let Ok(contents) = fs::read_to_string(&file) {
+            contents
+                .lines()
+                .map(|line| OsString::from(line.trim()))
+                .for_each(&mut add);
+        }
+    }
+    if let Some(current) = current {
+        SplitPaths::with_separator(current, b':', false)
+            .map(PathBuf::into_os_string)
+            .for_each(&mut add);
+    }
+
+    let mut path = OsString::new();
+    for (i, dir) in dirs.iter().enumerate() {
+        if i > 0 {
+            path.push(":");
+        }
+        path.push(dir);
+    }
+    path
+}
+
+/// The Homebrew prefix: `HOMEBREW_PREFIX` if set, then where Homebrew installs itself by
+/// default, if it's there: `/opt/homebrew` on Apple silicon, `/usr/local` on Intel Macs and
+/// `/home/linuxbrew/.linuxbrew` on Linux.
+pub fn homebrew_prefix() -> Option<PathBuf> {
+    homebrew_prefix_in(&Environment::Process)
+}
+
+/// Like [`homebrew_prefix`], with `HOMEBREW_PREFIX` read from `env`.
+pub(crate) fn homebrew_prefix_in(env: &Environment) -> Option<PathBuf> {
+    if let Some(prefix) = env
+        .var_os("HOMEBREW_PREFIX")
+        .filter(|prefix| !prefix.is_empty())
+    {
+        return Some(PathBuf::from(prefix));
+    }
+    let default = if cfg!(all(target_os = "macos", target_arch = "aarch64")) {
+        "/opt/homebrew"
+    } else if cfg!(target_os = "macos") {
+        "/usr/local"
+    } else if cfg!(target_os = "linux") {
+        "/home/linuxbrew/.linuxbrew"
+    } else {
+        return None;
+    };
+    // `/usr/local` exists without Homebrew too, but `bin/brew` doesn't.
+    let prefix = PathBuf::from(default);
+    prefix.join("bin").join("brew").is_file().then_some(prefix)
+}
+
+/// The active developer directory, the way `xcode-select -p` finds it: `DEVELOPER_DIR` if set,
+/// then the one chosen with `xcode-select -s`, then Xcode or the Command Line Tools in their
+/// default locations. `None` if there's none.
+pub fn developer_dir() -> Option<PathBuf> {
+    developer_dir_in(&Environment::Process)
+}
+
+/// Like [`developer_dir`], with `DEVELOPER_DIR` read from `env`.
+pub(crate) fn developer_dir_in(env: &Environment) -> Option<PathBuf> {
+    if let Some(dir) = env.var_os("DEVELOPER_DIR").filter(|dir| !dir.is_empty()) {
+        return Some(PathBuf::from(dir));
+    }
+    if let Ok(dir) = fs::read_link("/var/db/xcode_select_link") {
+        return Some(dir);
+    }
+    [
+        "/Applications/Xcode.app/Contents/Developer",
+        "/Library/Developer/CommandLineTools",
+    ]
+    .iter()
+    .map(PathBuf::from)
+    .find(|dir| dir.is_dir())
+}
+
+/// The directories of `developer_dir` that `xcrun --find` searches, in order: the default
+/// toolchain's, then the developer directory's own. Only those that exist are returned.
+pub fn toolchain_dirs(developer_dir: &Path) -> Vec<PathBuf> {
+    vec![
+        developer_dir
+            .join("Toolchains")
+            .join("XcodeDefault.xctoolchain")
+            .join("usr")
+            .join("bin"),
+        developer_dir.join("usr").join("bin"),
+    ]
+    .into_iter()
+    .filter(|dir| dir.is_dir())
+    .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_login_path_in() {
+        let root = tempfile::tempdir().unwrap();
+        let etc = root.path().join("etc");
+        fs::create_dir_all(etc.join("paths.d")).unwrap();
+        fs::write(etc.join("paths"), "/usr/local/bin\n/usr/bin\n/bin\n").unwrap();
+        fs::write(etc.join("paths.d").join("20-tex"), "/Library/TeX/texbin\n").unwrap();
+        fs::write(etc.join("paths.d").join("10-go"), " /usr/local/go/bin \n\n").unwrap();
+
+        let path = login_path_in(root.path(), Some(OsStr::new("/bin:/opt/homebrew/bin")));
+        assert_eq!(
+            path,
+            "/usr/local/bin:/usr/bin:/bin:/usr/local/go/bin:/Library/TeX/texbin:/opt/homebrew/bin"
+        );
+        assert_eq!(login_path_in(&root.path().join("missing"), None), "");
+    }
+
+    #[test]
+    fn test_toolchain_dirs() {
+        let dev = tempfile::tempdir().unwrap();
+        let toolchain = dev
+            .path()
+            .join("Toolchains/XcodeDefault.xctoolchain/usr/bin");
+        fs::create_dir_all(&toolchain).unwrap();
+        assert_eq!(toolchain_dirs(dev.path()), vec![toolchain.clone()]);
+
+        fs::create_dir_all(dev.path().join("usr/bin")).unwrap();
+        assert_eq!(
+            toolchain_dirs(dev.path()),
+            vec![toolchain, dev.path().join("usr/bin")]
+        );
+    }
+}
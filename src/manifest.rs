@@ -0,0 +1,192 @@
+//! Search settings and tool requirements declared in a file, such as a `tools.toml` checked
+//! into a repository, so every checkout resolves and checks tools the same way.
+//!
+//! Enabled by the `serde` feature. A [`Manifest`] can be deserialized from any format serde
+//! supports; in TOML, it looks like this:
+//!
+//! ```toml
+//! [search]
+//! path = ["tools/bin", "/usr/local/bin", "/usr/bin", "/bin"]
+//! homebrew = true
+//!
+//! [[tool]]
+//! name = "git"
+//! version = ">= 2.30"
+//! probe_version = true
+//!
+//! [[tool]]
+//! name = "cc"
+//! or = ["gcc", "clang"]
+//!
+//! [[tool]]
+//! name = "docker"
+//! optional = true
+//! ```
+//!
+//! Unknown keys are rejected, so that typos don't go unnoticed. Relative directories in `path`
+//! are resolved against the current directory, like relative `$PATH` entries.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let manifest = std::fs::read_to_string("tools.toml")?;
+//! let manifest: which::manifest::Manifest = toml::from_str(&manifest)?;
+//! let report = manifest.check();
+//! for check in report.problems() {
+//!     eprintln!("{:?}: {:?}", check.name, check.status);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::doctor::{Report, Requirement, Requirements, VersionReq};
+use crate::WhichConfig;
+use serde::Deserialize;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+/// Search settings and the tools that are needed.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct Manifest {
+    /// How tools are looked up.
+    pub search: Search,
+    /// The tools that are needed, reported in this order.
+    #[serde(rename = "tool")]
+    pub tools: Vec<Tool>,
+}
+
+impl Manifest {
+    /// A search with the [`Self::search`] settings, to set a name or pattern on.
+    pub fn config(&self) -> WhichConfig {
+        self.search.config()
+    }
+
+    /// The [`Self::tools`], as requirements.
+    pub fn requirements(&self) -> Requirements {
+        self.tools
+            .iter()
+            .fold(Requirements::new(), |requirements, tool| {
+                requirements.require(tool.requirement())
+            })
+    }
+
+    /// Looks every tool up with the [`Self::search`] settings and checks it.
+    pub fn check(&self) -> Report {
+        self.requirements().check_config(|| self.config())
+    }
+}
+
+/// How tools are looked up. Settings that are left out keep the defaults of [`WhichConfig`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct Search {
+    /// The directories to search instead of `$PATH`, in order.
+    pub path: Option<Vec<PathBuf>>,
+    /// See [`WhichConfig::system_fallback`].
+    pub system_fallback: Option<bool>,
+    /// See [`WhichConfig::hidden_files`].
+    pub hidden_files: Option<bool>,
+    /// See [`WhichConfig::reject_empty`].
+    pub reject_empty: Option<bool>,
+    /// See [`WhichConfig::verify_signature`].
+    pub verify_signature: Option<bool>,
+    /// See [`WhichConfig::well_known_dirs`].
+    pub well_known_dirs: Option<bool>,
+    /// See [`WhichConfig::xcode_toolchain`].
+    pub xcode_toolchain: Option<bool>,
+    /// See [`WhichConfig::homebrew`].
+    pub homebrew: Option<bool>,
+    /// See [`WhichConfig::windows_extensions`].
+    pub windows_extensions: Option<bool>,
+}
+
+impl Search {
+    /// A search with these settings.
+    pub fn config(&self) -> WhichConfig {
+        let mut config = WhichConfig::new();
+        if let Some(path) = &self.path {
+            config = config.custom_path_list(join(path)).path_separator('\0');
+        }
+        let settings: [(Option<bool>, Setter); 8] = [
+            (self.system_fallback, WhichConfig::system_fallback),
+            (self.hidden_files, WhichConfig::hidden_files),
+            (self.reject_empty, WhichConfig::reject_empty),
+            (self.verify_signature, WhichConfig::verify_signature),
+            (self.well_known_dirs, WhichConfig::well_known_dirs),
+            (self.xcode_toolchain, WhichConfig::xcode_toolchain),
+            (self.homebrew, WhichConfig::homebrew),
+            (self.windows_extensions, WhichConfig::windows_extensions),
+        ];
+        for (setting, apply) in settings {
+            if let Some(value) = setting {
+                config = apply(config, value);
+            }
+        }
+        config
+    }
+}
+
+/// A boolean setting of [`WhichConfig`].
+type Setter = fn(WhichConfig, bool) -> WhichConfig;
+
+/// A tool that's needed, see [`Requirement`].
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[non_exhaustive]
+pub struct Tool {
+    /// The name the tool is looked up by first.
+    pub name: String,
+    /// The names accepted instead, in order. See [`Requirement::or`].
+    #[serde(default)]
+    pub or: Vec<String>,
+    /// The versions accepted, like `>= 18, < 21`. See [`Requirement::version`].
+    #[serde(default)]
+    pub version: Option<VersionReq>,
+    /// See [`Requirement::probe_version`].
+    #[serde(default)]
+    pub probe_version: bool,
+    /// See [`Requirement::version_args`].
+    #[serde(default)]
+    pub version_args: Option<Vec<String>>,
+    /// See [`Requirement::optional`].
+    #[serde(default)]
+    pub optional: bool,
+}
+
+impl Tool {
+    /// This tool, as a requirement.
+    pub fn requirement(&self) -> Requirement {
+        let mut requirement = self
+            .or
+            .iter()
+            .fold(Requirement::new(&self.name), |requirement, name| {
+                requirement.or(name)
+            })
+            .probe_version(self.probe_version)
+            .optional(self.optional);
+        if let Some(version) = &self.version {
+            requirement = requirement.version(version.clone());
+        }
+        if let Some(args) = &self.version_args {
+            requirement = requirement.version_args(args);
+        }
+        requirement
+    }
+}
+
+/// `dirs` as a path list separated by nul bytes, which no path can contain, so that every
+/// directory stays whole whatever separators and quotes it has.
+fn join(dirs: &[PathBuf]) -> OsString {
+    let mut paths = OsString::new();
+    for (i, dir) in dirs.iter().enumerate() {
+        if i > 0 {
+            paths.push("\0");
+        }
+        paths.push(dir);
+    }
+    paths
+}
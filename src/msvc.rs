@@ -0,0 +1,152 @@
+//! Finding the MSVC tools, such as `cl.exe`, `link.exe` and `rc.exe`, which live inside Visual
+//! Studio and Windows SDK installations rather than on `PATH`.
+//!
+//! Enabled by the `msvc` feature, on Windows only. Visual Studio installations are located with
+//! `vswhere.exe`, which the Visual Studio installer always puts in the same place.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The component every installation with the C++ build tools has.
+const VC_TOOLS_COMPONENT: &str = "Microsoft.VisualStudio.Component.VC.Tools.x86.x64";
+
+/// The architecture the tools run on, as Visual Studio names it.
+#[cfg(target_arch = "x86")]
+const HOST: &str = "x86";
+#[cfg(target_arch = "aarch64")]
+const HOST: &str = "arm64";
+#[cfg(not(any(target_arch = "x86", target_arch = "aarch64")))]
+const HOST: &str = "x64";
+
+/// The installation paths of every Visual Studio instance with the C++ build tools, newest
+/// first. Empty if `vswhere.exe` is missing or fails.
+pub fn installations() -> Vec<PathBuf> {
+    let vswhere = match vswhere() {
+        Some(vswhere) => vswhere,
+        None => return Vec::new(),
+    };
+    let output = Command::new(vswhere)
+        .args([
+            "-all",
+            "-products",
+            "*",
+            "-sort",
+            "-requires",
+            VC_TOOLS_COMPONENT,
+        ])
+        .args(["-property", "installationPath", "-utf8"])
+        .output();
+    match output {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(PathBuf::from)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Finds the MSVC tool `name`, such as `cl.exe` or `link.exe`, building for the architecture the
+/// current process runs on. See [`find_tool_for`].
+///
+/// # Example
+///
+/// ```no_run
+/// let cl = which::msvc::find_tool("cl.exe").unwrap();
+/// ```
+pub fn find_tool(name: &str) -> Option<PathBuf> {
+    find_tool_for(name, HOST)
+}
+
+/// Finds the MSVC tool `name` building for `target`, one of `x86`, `x64`, `arm` or `arm64`.
+///
+/// Compiler and linker tools are looked up in the default toolset of each Visual Studio
+/// installation, newest first. Tools that come with the Windows SDK instead, such as `rc.exe`
+/// and `mt.exe`, are looked up in its newest version.
+pub fn find_tool_for(name: &str, target: &str) -> Option<PathBuf> {
+    installations()
+        .iter()
+        .filter_map(|installation| toolset_bin(installation, target))
+        .chain(sdk_bin(target))
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Where 32-bit programs are installed, which is where both Visual Studio's installer and the
+/// Windows SDK go.
+fn program_files_x86() -> Option<std::ffi::OsString> {
+    crate::environment::var_os("ProgramFiles(x86)")
+        .or_else(|| crate::environment::var_os("ProgramFiles"))
+}
+
+fn vswhere() -> Option<PathBuf> {
+    let program_files = program_files_x86()?;
+    let vswhere = Path::new(&program_files)
+        .join("Microsoft Visual Studio")
+        .join("Installer")
+        .join("vswhere.exe");
+    vswhere.is_file().then_some(vswhere)
+}
+
+/// The directory with the tools of the default toolset of `installation` for `target`.
+fn toolset_bin(installation: &Path, target: &str) -> Option<PathBuf> {
+    let vc = installation.join("VC");
+    let version = fs::read_to_string(
+        vc.join("Auxiliary")
+            .join("Build")
+            .join("Microsoft.VCToolsVersion.default.txt"),
+    )
+    .ok()?;
+    Some(
+        vc.join("Tools")
+            .join("MSVC")
+            .join(version.trim())
+            .join("bin")
+            .join(format!("Host{}", HOST))
+            .join(target),
+    )
+}
+
+/// The directory with the tools of the newest Windows 10 or 11 SDK for `target`.
+fn sdk_bin(target: &str) -> Option<PathBuf> {
+    let program_files = program_files_x86()?;
+    let bin = Path::new(&program_files)
+        .join("Windows Kits")
+        .join("10")
+        .join("bin");
+    Some(crate::versions::newest(&bin)?.join(target))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_toolset_bin() {
+        let installation = tempfile::tempdir().unwrap();
+        let build = installation.path().join("VC/Auxiliary/Build");
+        fs::create_dir_all(&build).unwrap();
+        fs::write(
+            build.join("Microsoft.VCToolsVersion.default.txt"),
+            "14.38.33130\r\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            toolset_bin(installation.path(), "arm64"),
+            Some(
+                installation
+                    .path()
+                    .join("VC/Tools/MSVC/14.38.33130/bin")
+                    .join(format!("Host{}", HOST))
+                    .join("arm64")
+            )
+        );
+        assert_eq!(
+            toolset_bin(&installation.path().join("missing"), "x64"),
+            None
+        );
+    }
+}
@@ -0,0 +1,61 @@
+//! Finding the executables of Node.js packages the way `npx` does, in the `node_modules/.bin`
+//! directories of a project and the projects it's nested in, before `PATH`.
+
+use crate::{Origin, WhichConfig};
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+/// The `node_modules/.bin` directories of `start` and each of its ancestors that has one,
+/// nearest first. A relative `start` is resolved against the working directory, and `..` in it
+/// is taken lexically.
+pub fn bin_dirs<P: AsRef<Path>>(start: P) -> Vec<PathBuf> {
+    absolute(start.as_ref())
+        .ancestors()
+        .map(|dir| dir.join("node_modules").join(".bin"))
+        .filter(|dir| dir.is_dir())
+        .collect()
+}
+
+/// Finds `binary_name` in the [`bin_dirs`] of `start`, nearest first, or else on `PATH`.
+/// Relative names containing a separator are resolved against `start`.
+///
+/// # Example
+///
+/// ```no_run
+/// let eslint = which::node::find("eslint", std::env::current_dir().unwrap()).unwrap();
+/// ```
+pub fn find<T, P>(binary_name: T, start: P) -> crate::Result<PathBuf>
+where
+    T: AsRef<OsStr>,
+    P: AsRef<Path>,
+{
+    find_with_origin(binary_name, start).map(|(path, _)| path)
+}
+
+/// Like [`find`], but also reports where the executable was found: [`Origin::Project`] with
+/// the index of its directory among the [`bin_dirs`], or where on `PATH` it was.
+pub fn find_with_origin<T, P>(binary_name: T, start: P) -> crate::Result<(PathBuf, Origin)>
+where
+    T: AsRef<OsStr>,
+    P: AsRef<Path>,
+{
+    let start = absolute(start.as_ref());
+    bin_dirs(&start)
+        .into_iter()
+        .fold(WhichConfig::new(), WhichConfig::project_dir)
+        .custom_cwd(start)
+        .binary_name(binary_name.as_ref().to_owned())
+        .all_results_with_origin()?
+        .next()
+        .ok_or(crate::Error::CannotFindBinaryPath)
+}
+
+/// `start` joined onto the working directory and normalized the way `npx` does, so that its
+/// ancestors are the real ones and the directories derived from it don't depend on what they're
+/// later resolved against.
+fn absolute(start: &Path) -> PathBuf {
+    match crate::environment::current_dir() {
+        Some(cwd) if start.is_relative() => crate::normalize(&cwd.join(start)),
+        _ => crate::normalize(start),
+    }
+}
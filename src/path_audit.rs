@@ -0,0 +1,31 @@
+//! Quick checks of the path list itself, for "doctor" commands.
+
+use crate::finder::Finder;
+use std::ffi::OsStr;
+use std::path::PathBuf;
+
+/// The entries of `$PATH` that don't exist or aren't directories, in order.
+///
+/// These can't contain anything to find, so they usually point at an uninstalled tool or a
+/// typo in a shell profile. Entries are split and expanded the way searches do, see
+/// [`search_dirs`](crate::search_dirs).
+///
+/// # Example
+///
+/// ```no_run
+/// for dir in which::path_audit::missing_dirs() {
+///     eprintln!("warning: {} is on PATH but isn't a directory", dir.display());
+/// }
+/// ```
+pub fn missing_dirs() -> Vec<PathBuf> {
+    crate::environment::var_os("PATH").map_or_else(Vec::new, missing_dirs_in)
+}
+
+/// Like [`missing_dirs`], but for the path list `paths`.
+pub fn missing_dirs_in<T: AsRef<OsStr>>(paths: T) -> Vec<PathBuf> {
+    Finder::new()
+        .search_dirs(Some(paths))
+        .into_iter()
+        .filter(|dir| !dir.is_dir())
+        .collect()
+}
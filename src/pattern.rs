@@ -0,0 +1,71 @@
+use crate::build_binary_checker;
+use crate::error::*;
+use crate::finder::{Finder, ListingMatches, Matches, PredicateMatcher};
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+
+/// Something [`crate::which_pattern`] can look executables up by.
+///
+/// Implemented for names, which are looked up like [`crate::which_all`]; for closures wrapped in
+/// a [`Predicate`], which are called like [`crate::which_matching`]; with the `regex` feature,
+/// for regular expressions, which are matched like [`crate::which_re`] or
+/// [`crate::which_re_bytes`]; and with the `glob` feature, for `glob::Pattern`s, which are
+/// matched against file names like a shell would, so that `*` doesn't match a leading dot and
+/// case is ignored on Windows.
+///
+/// This trait is sealed, so it can't be implemented outside this crate.
+pub trait Pattern: sealed::Sealed {}
+
+/// Matches the file names accepted by a closure, for use as a [`Pattern`].
+///
+/// # Example
+///
+/// ```no_run
+/// use std::ffi::OsStr;
+/// use which::Predicate;
+///
+/// let pythons = which::which_pattern(Predicate(|name: &OsStr| {
+///     name.to_string_lossy().starts_with("python")
+/// }));
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Predicate<F>(pub F);
+
+pub(crate) mod sealed {
+    use super::*;
+    use std::iter::FusedIterator;
+
+    pub trait Sealed {
+        type Matches: FusedIterator<Item = PathBuf>;
+
+        fn find(self, paths: Option<OsString>, cwd: Option<PathBuf>) -> Result<Self::Matches>;
+    }
+}
+
+macro_rules! name_pattern {
+    ($($ty:ty),*) => {$(
+        impl Pattern for $ty {}
+
+        impl sealed::Sealed for $ty {
+            type Matches = Matches;
+
+            fn find(self, paths: Option<OsString>, cwd: Option<PathBuf>) -> Result<Matches> {
+                Finder::new()
+                    .batch(true)
+                    .find(self, paths, cwd, build_binary_checker())
+            }
+        }
+    )*};
+}
+
+name_pattern!(&str, String, &String, &OsStr, OsString, &OsString, &Path, PathBuf, &PathBuf);
+
+impl<F: Fn(&OsStr) -> bool> Pattern for Predicate<F> {}
+
+impl<F: Fn(&OsStr) -> bool> sealed::Sealed for Predicate<F> {
+    type Matches = ListingMatches<PredicateMatcher<F>>;
+
+    fn find(self, paths: Option<OsString>, _: Option<PathBuf>) -> Result<Self::Matches> {
+        Finder::new().find_listed(PredicateMatcher(self.0), paths, build_binary_checker())
+    }
+}
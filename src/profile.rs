@@ -0,0 +1,121 @@
+use crate::finder::PathExtensions;
+use std::ffi::OsStr;
+
+/// The default `%PATHEXT%` on Windows, for [`PlatformProfile::windows`] without one.
+const DEFAULT_PATHEXT: &str = ".COM;.EXE;.BAT;.CMD;.VBS;.VBE;.JS;.JSE;.WSF;.WSH;.MSC";
+
+/// The rules of the platform a search applies, for predicting what another platform would
+/// resolve, e.g. a Windows machine from a captured environment and filesystem on Linux. See
+/// [`WhichConfig::platform`](crate::WhichConfig::platform).
+///
+/// The rules are how path lists are separated, whether names are expanded with executable
+/// extensions and whether only files with those count, and whether file names are matched
+/// case-insensitively. Only files that exist on the host can be found, and execute permissions
+/// can't be checked on Windows hosts, so Unix rules take every file there to be executable.
+///
+/// # Example
+///
+/// ```no_run
+/// use which::{PlatformProfile, WhichConfig};
+///
+/// let profile = PlatformProfile::windows(Some(".COM;.EXE;.BAT".as_ref()));
+/// let cl = WhichConfig::new()
+///     .platform(profile)
+///     .custom_path_list("/mnt/c/tools/bin;/mnt/c/Windows/System32".into())
+///     .system_cwd(false)
+///     .binary_name("cl".into())
+///     .first_result();
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlatformProfile {
+    windows: bool,
+    extensions: Vec<String>,
+    case_sensitive: bool,
+}
+
+impl PlatformProfile {
+    /// The rules of the platform the crate was compiled for, with the process's `%PATHEXT%` on
+    /// Windows.
+    pub fn current() -> PlatformProfile {
+        if cfg!(windows) {
+            PlatformProfile::windows(None).extensions(crate::win::path_extensions())
+        } else {
+            PlatformProfile::unix()
+        }
+    }
+
+    /// Unix rules: path lists are separated by `:`, names are taken as they are, executables are
+    /// files with execute permissions and file names are case-sensitive.
+    pub fn unix() -> PlatformProfile {
+        PlatformProfile {
+            windows: false,
+            extensions: Vec::new(),
+            case_sensitive: true,
+        }
+    }
+
+    /// Windows rules: path lists are separated by `;`, names are expanded with the extensions
+    /// listed by `pathext`, or the default `%PATHEXT%` if `None`, names without an extension are
+    /// only found that way, and file names are case-insensitive.
+    pub fn windows(pathext: Option<&OsStr>) -> PlatformProfile {
+        let pathext = pathext.and_then(OsStr::to_str).unwrap_or(DEFAULT_PATHEXT);
+        PlatformProfile {
+            windows: true,
+            extensions: crate::win::parse_pathext(pathext),
+            case_sensitive: false,
+        }
+    }
+
+    /// Uses the executable extensions in `extensions`, dots included, instead. Only Windows
+    /// rules expand names with them.
+    pub fn extensions(mut self, extensions: Vec<String>) -> PlatformProfile {
+        self.extensions = extensions;
+        self
+    }
+
+    /// Whether file names are matched case-sensitively. Case-insensitive matching only looks at
+    /// the last component of candidates, and can't be turned off on Windows hosts.
+    pub fn case_sensitive(mut self, case_sensitive: bool) -> PlatformProfile {
+        self.case_sensitive = case_sensitive;
+        self
+    }
+
+    /// Whether these are Windows rules.
+    pub fn is_windows(&self) -> bool {
+        self.windows
+    }
+
+    /// The executable extensions, dots included.
+    pub fn path_extensions(&self) -> &[String] {
+        &self.extensions
+    }
+
+    /// Whether file names are matched case-sensitively.
+    pub fn is_case_sensitive(&self) -> bool {
+        self.case_sensitive
+    }
+
+    /// The separator of path lists.
+    pub fn separator(&self) -> u8 {
+        if self.windows {
+            b';'
+        } else {
+            b':'
+        }
+    }
+
+    /// The extensions names are expanded with, if any.
+    pub(crate) fn expansions(&self) -> PathExtensions {
+        if self.windows {
+            PathExtensions::windows(self.extensions.clone())
+        } else {
+            PathExtensions::new(Vec::new())
+        }
+    }
+}
+
+impl Default for PlatformProfile {
+    fn default() -> PlatformProfile {
+        PlatformProfile::current()
+    }
+}
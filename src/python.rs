@@ -0,0 +1,315 @@
+//! Finding Python interpreters the way the Windows `py` launcher does, from the registry.
+//!
+//! On Windows, the `python` on `PATH` is often the Microsoft Store's alias, which opens the
+//! Store rather than running anything unless Python was installed from there. Installers
+//! instead record interpreters in the registry, as described in PEP 514, which is where `py`
+//! finds them. Elsewhere, there's no registry, so [`find`] is just a `PATH` lookup.
+//!
+//! Python tools are also looked up in the active virtualenv or conda environment first, with
+//! [`find_in_environment`].
+
+use crate::{EnvSnapshot, Origin, WhichConfig};
+use std::ffi::OsStr;
+use std::path::PathBuf;
+
+/// A Python interpreter.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Interpreter {
+    /// The interpreter's executable.
+    pub path: PathBuf,
+    /// The version, such as `3.12.1` or `3.12`, if known.
+    pub version: Option<String>,
+    /// The company the registry lists it under, such as `PythonCore` for python.org builds, if
+    /// it was found in the registry.
+    pub company: Option<String>,
+    /// The tag the registry lists it under, such as `3.12` or `3.12-32`, if it was found in the
+    /// registry.
+    pub tag: Option<String>,
+}
+
+/// Every interpreter recorded in the registry, newest first, the current user's before the
+/// machine-wide ones of the same version. Empty on platforms other than Windows.
+pub fn interpreters() -> Vec<Interpreter> {
+    #[cfg(windows)]
+    {
+        registry_interpreters()
+    }
+    #[cfg(not(windows))]
+    {
+        Vec::new()
+    }
+}
+
+/// Finds the interpreter `name` would run, for names like `python`, `python3` and
+/// `python3.12`: the newest registered interpreter of the version in the name, if any, or else
+/// the `name` on `PATH`.
+///
+/// Names not of that form are just looked up on `PATH`.
+///
+/// # Example
+///
+/// ```no_run
+/// let python = which::python::find("python3").unwrap();
+/// println!("{} is Python {:?}", python.path.display(), python.version);
+/// ```
+pub fn find(name: &str) -> crate::Result<Interpreter> {
+    if let Some(requested) = requested_version(name) {
+        let registered = interpreters().into_iter().find(|interpreter| {
+            interpreter
+                .version
+                .as_deref()
+                .is_some_and(|version| version_matches(version, requested))
+        });
+        if let Some(interpreter) = registered {
+            return Ok(interpreter);
+        }
+    }
+    Ok(Interpreter {
+        path: crate::which(name)?,
+        version: None,
+        company: None,
+        tag: None,
+    })
+}
+
+/// A virtualenv or conda environment.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Environment {
+    /// What kind of environment it is.
+    pub kind: EnvironmentKind,
+    /// The directory the environment is installed in.
+    pub prefix: PathBuf,
+    /// The name of the environment: conda's name for it, or the name of a virtualenv's
+    /// directory.
+    pub name: Option<String>,
+}
+
+/// What kind of [`Environment`] one is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EnvironmentKind {
+    /// A virtualenv or `venv`, activated through `VIRTUAL_ENV`.
+    Virtualenv,
+    /// A conda environment, activated through `CONDA_PREFIX`.
+    Conda,
+}
+
+impl Environment {
+    /// The directories the environment keeps its executables in, in search order: `bin` on
+    /// Unix, and on Windows `Scripts` for virtualenvs, or for conda the prefix itself, then
+    /// `Library\bin` and `Scripts`.
+    pub fn bin_dirs(&self) -> Vec<PathBuf> {
+        if !cfg!(windows) {
+            return vec![self.prefix.join("bin")];
+        }
+        match self.kind {
+            EnvironmentKind::Virtualenv => vec![self.prefix.join("Scripts")],
+            EnvironmentKind::Conda => vec![
+                self.prefix.clone(),
+                self.prefix.join("Library").join("bin"),
+                self.prefix.join("Scripts"),
+            ],
+        }
+    }
+}
+
+/// The active environment: the virtualenv in `VIRTUAL_ENV`, which is activated on top of any
+/// conda environment, or else the conda environment in `CONDA_PREFIX`. `None` if neither is
+/// set.
+pub fn active_environment() -> Option<Environment> {
+    active_environment_from(&crate::environment::Environment::Process)
+}
+
+/// Like [`active_environment`], with the variables read from `env`.
+pub fn active_environment_in(env: &EnvSnapshot) -> Option<Environment> {
+    active_environment_from(&crate::environment::Environment::Explicit(env.clone()))
+}
+
+fn active_environment_from(env: &crate::environment::Environment) -> Option<Environment> {
+    let var = |name: &str| env.var_os(name).filter(|value| !value.is_empty());
+    if let Some(prefix) = var("VIRTUAL_ENV").map(PathBuf::from) {
+        let name = prefix
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned());
+        return Some(Environment {
+            kind: EnvironmentKind::Virtualenv,
+            prefix,
+            name,
+        });
+    }
+    let prefix = PathBuf::from(var("CONDA_PREFIX")?);
+    Some(Environment {
+        kind: EnvironmentKind::Conda,
+        prefix,
+        name: var("CONDA_DEFAULT_ENV").map(|name| name.to_string_lossy().into_owned()),
+    })
+}
+
+/// Finds `binary_name`, such as `python`, `pip` or `pytest`, in the [`active_environment`]
+/// first, then on `PATH`, along with the environment it was found in, if it was.
+///
+/// The environment's directories are searched even if activating it didn't put them on `PATH`
+/// or something was put in front of them since.
+///
+/// # Example
+///
+/// ```no_run
+/// let (pytest, environment) = which::python::find_in_environment("pytest").unwrap();
+/// if let Some(environment) = environment {
+///     println!("{} from {}", pytest.display(), environment.prefix.display());
+/// }
+/// ```
+pub fn find_in_environment<T: AsRef<OsStr>>(
+    binary_name: T,
+) -> crate::Result<(PathBuf, Option<Environment>)> {
+    find_in(
+        binary_name.as_ref(),
+        active_environment(),
+        WhichConfig::new(),
+    )
+}
+
+/// Like [`find_in_environment`], with everything read from `env` instead of the process's
+/// environment, as with [`WhichConfig::hermetic`].
+pub fn find_in_environment_in<T: AsRef<OsStr>>(
+    binary_name: T,
+    env: &EnvSnapshot,
+) -> crate::Result<(PathBuf, Option<Environment>)> {
+    find_in(
+        binary_name.as_ref(),
+        active_environment_in(env),
+        WhichConfig::hermetic(env.clone()),
+    )
+}
+
+/// Finds `binary_name` in `environment`, then as `config` would.
+fn find_in(
+    binary_name: &OsStr,
+    environment: Option<Environment>,
+    config: WhichConfig,
+) -> crate::Result<(PathBuf, Option<Environment>)> {
+    let dirs = environment.as_ref().map(Environment::bin_dirs);
+    let (path, origin) = dirs
+        .into_iter()
+        .flatten()
+        .fold(config, WhichConfig::project_dir)
+        .binary_name(binary_name.to_owned())
+        .all_results_with_origin()?
+        .next()
+        .ok_or(crate::Error::CannotFindBinaryPath)?;
+    let environment = environment.filter(|_| matches!(origin, Origin::Project(_)));
+    Ok((path, environment))
+}
+
+/// The version in a name like `python3.12`, which is empty for just `python`. `None` for other
+/// names.
+fn requested_version(name: &str) -> Option<&str> {
+    let name = name
+        .strip_suffix(".exe")
+        .or_else(|| name.strip_suffix(".EXE"))
+        .unwrap_or(name);
+    let version = name.strip_prefix("python")?;
+    version
+        .split('.')
+        .all(|part| part.bytes().all(|b| b.is_ascii_digit()))
+        .then_some(version)
+}
+
+/// Whether `version` is `requested` or a more specific version of it, so `3.12.1` matches `3`
+/// and `3.12` but not `3.1`.
+fn version_matches(version: &str, requested: &str) -> bool {
+    if requested.is_empty() {
+        return true;
+    }
+    let mut version = version.split('.');
+    requested
+        .split('.')
+        .all(|part| version.next() == Some(part))
+}
+
+#[cfg(windows)]
+fn registry_interpreters() -> Vec<Interpreter> {
+    use crate::registry::{self, HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE};
+
+    const ROOTS: &[(registry::HKEY, &str)] = &[
+        (HKEY_CURRENT_USER, r"Software\Python"),
+        (HKEY_LOCAL_MACHINE, r"Software\Python"),
+        (HKEY_LOCAL_MACHINE, r"Software\WOW6432Node\Python"),
+    ];
+    let mut found = Vec::new();
+    for &(root, path) in ROOTS {
+        for company in registry::subkeys(root, path) {
+            let company = company.to_string_lossy().into_owned();
+            // The launcher's own settings, not an interpreter.
+            if company == "PyLauncher" {
+                continue;
+            }
+            let company_path = format!(r"{}\{}", path, company);
+            for tag in registry::subkeys(root, &company_path) {
+                let tag = tag.to_string_lossy().into_owned();
+                let tag_path = format!(r"{}\{}", company_path, tag);
+                let install_path = format!(r"{}\InstallPath", tag_path);
+                let executable = registry::string_value(root, &install_path, "ExecutablePath")
+                    .map(PathBuf::from)
+                    .or_else(|| {
+                        // Older python.org installers only record the directory.
+                        registry::string_value(root, &install_path, "")
+                            .map(|dir| PathBuf::from(dir).join("python.exe"))
+                    });
+                let executable = match executable {
+                    Some(executable) if executable.is_file() => executable,
+                    _ => continue,
+                };
+                let version = ["Version", "SysVersion"]
+                    .iter()
+                    .find_map(|name| registry::string_value(root, &tag_path, name))
+                    .map(|version| version.to_string_lossy().into_owned())
+                    // python.org tags are versions, with `-32` or `-arm64` for other platforms.
+                    .or_else(|| {
+                        let version = tag.split('-').next().unwrap_or_default();
+                        (company == "PythonCore").then(|| version.to_owned())
+                    });
+                found.push(Interpreter {
+                    path: executable,
+                    version,
+                    company: Some(company.clone()),
+                    tag: Some(tag),
+                });
+            }
+        }
+    }
+
+    let key = |version: &Option<String>| -> Vec<u32> {
+        version
+            .as_deref()
+            .unwrap_or_default()
+            .split('.')
+            .map_while(|part| part.parse().ok())
+            .collect()
+    };
+    // Stable, so the order of the roots is kept for the same version.
+    found.sort_by_key(|interpreter| std::cmp::Reverse(key(&interpreter.version)));
+    found
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_requested_version() {
+        assert_eq!(requested_version("python"), Some(""));
+        assert_eq!(requested_version("python3"), Some("3"));
+        assert_eq!(requested_version("python3.12.exe"), Some("3.12"));
+        assert_eq!(requested_version("pythonw"), None);
+        assert_eq!(requested_version("cargo"), None);
+
+        assert!(version_matches("3.12.1", ""));
+        assert!(version_matches("3.12.1", "3"));
+        assert!(version_matches("3.12.1", "3.12"));
+        assert!(!version_matches("3.12.1", "3.1"));
+        assert!(!version_matches("2.7", "3"));
+    }
+}
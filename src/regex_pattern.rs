@@ -0,0 +1,59 @@
+//! Looking executables up by regular expression, with the `regex` feature.
+
+use crate::build_binary_checker;
+use crate::error::*;
+use crate::finder::{Finder, ListingMatches, NameMatcher};
+use crate::pattern::{sealed, Pattern};
+use regex::Regex;
+use std::borrow::Borrow;
+use std::ffi::{OsStr, OsString};
+use std::path::PathBuf;
+
+pub struct RegexMatcher<R>(pub R);
+
+impl<R: Borrow<Regex>> NameMatcher for RegexMatcher<R> {
+    fn matches(&self, file_name: &OsStr) -> bool {
+        file_name
+            .to_str()
+            .is_some_and(|file_name| self.0.borrow().is_match(file_name))
+    }
+}
+
+pub struct BytesRegexMatcher<R>(pub R);
+
+impl<R: Borrow<regex::bytes::Regex>> NameMatcher for BytesRegexMatcher<R> {
+    #[cfg(unix)]
+    fn matches(&self, file_name: &OsStr) -> bool {
+        use std::os::unix::ffi::OsStrExt;
+        self.0.borrow().is_match(file_name.as_bytes())
+    }
+
+    // Names are only bytes on Unix, so elsewhere the UTF-8 ones are matched as such.
+    #[cfg(not(unix))]
+    fn matches(&self, file_name: &OsStr) -> bool {
+        file_name
+            .to_str()
+            .is_some_and(|file_name| self.0.borrow().is_match(file_name.as_bytes()))
+    }
+}
+
+macro_rules! regex_pattern {
+    ($($ty:ty => $find:ident, $matcher:ident;)*) => {$(
+        impl Pattern for $ty {}
+
+        impl sealed::Sealed for $ty {
+            type Matches = ListingMatches<$matcher<Self>>;
+
+            fn find(self, paths: Option<OsString>, _: Option<PathBuf>) -> Result<Self::Matches> {
+                Finder::new().$find(self, paths, build_binary_checker())
+            }
+        }
+    )*};
+}
+
+regex_pattern! {
+    Regex => find_re, RegexMatcher;
+    &Regex => find_re, RegexMatcher;
+    regex::bytes::Regex => find_re_bytes, BytesRegexMatcher;
+    &regex::bytes::Regex => find_re_bytes, BytesRegexMatcher;
+}
@@ -0,0 +1,95 @@
+//! Reading the Windows registry, where installers record where some tools live.
+
+use std::ffi::{OsStr, OsString};
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+use std::ptr;
+use windows_sys::Win32::Foundation::ERROR_SUCCESS;
+use windows_sys::Win32::System::Registry::{
+    RegCloseKey, RegEnumKeyExW, RegGetValueW, RegOpenKeyExW, KEY_READ, RRF_RT_REG_SZ,
+};
+
+pub use windows_sys::Win32::System::Registry::{HKEY, HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE};
+
+/// The longest a key name can be, in UTF-16 units.
+const MAX_KEY_LEN: usize = 255;
+
+fn wide(s: &str) -> Vec<u16> {
+    OsStr::new(s).encode_wide().chain(Some(0)).collect()
+}
+
+/// The names of the subkeys of `root\path`, or nothing if it doesn't exist.
+pub fn subkeys(root: HKEY, path: &str) -> Vec<OsString> {
+    let path = wide(path);
+    let mut key: HKEY = ptr::null_mut();
+    // SAFETY: `path` is nul terminated and `key` is only used once opened.
+    if unsafe { RegOpenKeyExW(root, path.as_ptr(), 0, KEY_READ, &mut key) } != ERROR_SUCCESS {
+        return Vec::new();
+    }
+
+    let mut names = Vec::new();
+    let mut buf = [0u16; MAX_KEY_LEN + 1];
+    for index in 0.. {
+        let mut len = buf.len() as u32;
+        // SAFETY: `len` is the capacity of `buf`, and the optional outputs are null.
+        let result = unsafe {
+            RegEnumKeyExW(
+                key,
+                index,
+                buf.as_mut_ptr(),
+                &mut len,
+                ptr::null(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+        };
+        if result != ERROR_SUCCESS {
+            break;
+        }
+        names.push(OsString::from_wide(&buf[..len as usize]));
+    }
+    // SAFETY: `key` was opened above and isn't used after this.
+    unsafe { RegCloseKey(key) };
+    names
+}
+
+/// The string value `name` of `root\path`, or `None` if it doesn't exist or isn't a string.
+pub fn string_value(root: HKEY, path: &str, name: &str) -> Option<OsString> {
+    let path = wide(path);
+    let name = wide(name);
+    let mut size = 0u32;
+    // SAFETY: both strings are nul terminated, and a null buffer just queries the size.
+    let result = unsafe {
+        RegGetValueW(
+            root,
+            path.as_ptr(),
+            name.as_ptr(),
+            RRF_RT_REG_SZ,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            &mut size,
+        )
+    };
+    if result != ERROR_SUCCESS {
+        return None;
+    }
+
+    let mut buf = vec![0u16; (size as usize).div_ceil(2)];
+    // SAFETY: `size` is the size of `buf` in bytes.
+    let result = unsafe {
+        RegGetValueW(
+            root,
+            path.as_ptr(),
+            name.as_ptr(),
+            RRF_RT_REG_SZ,
+            ptr::null_mut(),
+            buf.as_mut_ptr().cast(),
+            &mut size,
+        )
+    };
+    if result != ERROR_SUCCESS {
+        return None;
+    }
+    let len = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+    Some(OsString::from_wide(&buf[..len]))
+}
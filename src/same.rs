@@ -0,0 +1,114 @@
+use crate::error::*;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+#[cfg(not(target_os = "wasi"))]
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Reports whether `binary_a` and `binary_b` resolve to the same file, the way [`crate::which`]
+/// resolves them, e.g. to check whether `python` is actually `python3`.
+///
+/// Symlinks and hard links to the same file count as the same.
+///
+/// # Example
+///
+/// ```no_run
+/// if which::same("python", "python3").unwrap() {
+///     println!("python is python3");
+/// }
+/// ```
+pub fn same<T: AsRef<OsStr>, U: AsRef<OsStr>>(binary_a: T, binary_b: U) -> Result<bool> {
+    let a = crate::which(binary_a)?;
+    let b = crate::which(binary_b)?;
+    // Either file disappearing since it was found means it can't be found anymore.
+    same_file(a, b).map_err(|_| Error::CannotFindBinaryPath)
+}
+
+/// Reports whether `a` and `b` are the same underlying file, following symlinks: the same
+/// device and inode on Unix and WASI, the same volume and file ID on Windows.
+pub fn same_file<P: AsRef<Path>, Q: AsRef<Path>>(a: P, b: Q) -> io::Result<bool> {
+    Ok(file_id(a.as_ref())? == file_id(b.as_ref())?)
+}
+
+/// A file found under several paths, as returned by
+/// [`WhichConfig::all_results_by_file`](crate::WhichConfig::all_results_by_file).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct LinkGroup {
+    /// The path the file was found under first.
+    pub path: PathBuf,
+    /// The later paths that turned out to be the same file, in search order.
+    pub links: Vec<PathBuf>,
+}
+
+/// Groups `paths` by the file they refer to, keeping the order in which each file was first
+/// seen. Paths whose identity can't be determined are kept on their own.
+pub(crate) fn group_links<I: IntoIterator<Item = PathBuf>>(paths: I) -> Vec<LinkGroup> {
+    let mut groups: Vec<LinkGroup> = Vec::new();
+    let mut seen: HashMap<_, usize> = HashMap::new();
+    for path in paths {
+        match file_id(&path) {
+            Ok(id) => match seen.entry(id) {
+                Entry::Occupied(entry) => groups[*entry.get()].links.push(path),
+                Entry::Vacant(entry) => {
+                    entry.insert(groups.len());
+                    groups.push(LinkGroup {
+                        path,
+                        links: Vec::new(),
+                    });
+                }
+            },
+            Err(_) => groups.push(LinkGroup {
+                path,
+                links: Vec::new(),
+            }),
+        }
+    }
+    groups
+}
+
+#[cfg(unix)]
+fn file_id(path: &Path) -> io::Result<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = fs::metadata(path)?;
+    Ok((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(windows)]
+fn file_id(path: &Path) -> io::Result<(u32, u64)> {
+    use std::mem::MaybeUninit;
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Storage::FileSystem::{
+        GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION, FILE_FLAG_BACKUP_SEMANTICS,
+    };
+
+    // Directories can only be opened with backup semantics.
+    let file = fs::OpenOptions::new()
+        .access_mode(0)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+        .open(path)?;
+    let mut info = MaybeUninit::<BY_HANDLE_FILE_INFORMATION>::uninit();
+    // SAFETY: the handle stays open for the duration of the call, and `info` is large enough.
+    if unsafe { GetFileInformationByHandle(file.as_raw_handle(), info.as_mut_ptr()) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: the call succeeded, so it filled `info` in.
+    let info = unsafe { info.assume_init() };
+    let index = (u64::from(info.nFileIndexHigh) << 32) | u64::from(info.nFileIndexLow);
+    Ok((info.dwVolumeSerialNumber, index))
+}
+
+#[cfg(target_os = "wasi")]
+fn file_id(path: &Path) -> io::Result<(u64, u64)> {
+    let stat = crate::checker::libc_stat(path)?;
+    Ok((stat.st_dev, stat.st_ino))
+}
+
+// Elsewhere there's no stable notion of file identity, so the resolved paths are compared.
+#[cfg(not(any(unix, windows, target_os = "wasi")))]
+fn file_id(path: &Path) -> io::Result<PathBuf> {
+    fs::canonicalize(path)
+}
@@ -0,0 +1,170 @@
+use crate::build_binary_checker;
+use crate::error::*;
+use crate::finder::{Finder, PathExtensions};
+use std::ffi::{OsStr, OsString};
+use std::iter::FusedIterator;
+use std::path::{Path, PathBuf};
+
+/// The parts of the environment a lookup depends on, `PATH`, `PATHEXT` and the working
+/// directory, captured once, along with any other variables set with [`EnvSnapshot::var`].
+///
+/// Looking names up through a snapshot reads nothing from the environment, so every lookup
+/// made through it agrees even if the environment changes in the meantime, and a batch of
+/// lookups doesn't read the same variables over and over.
+///
+/// # Example
+///
+/// ```no_run
+/// use which::EnvSnapshot;
+///
+/// let env = EnvSnapshot::capture();
+/// let tools: Vec<_> = ["cc", "ld", "ar"]
+///     .iter()
+///     .map(|name| env.which(name))
+///     .collect();
+/// ```
+#[derive(Clone)]
+pub struct EnvSnapshot {
+    path: Option<OsString>,
+    pathext: Option<OsString>,
+    path_extensions: PathExtensions,
+    cwd: Option<PathBuf>,
+    /// Other variables, `None` meaning unset.
+    vars: Vec<(OsString, Option<OsString>)>,
+}
+
+impl EnvSnapshot {
+    /// Captures the current process's environment.
+    pub fn capture() -> EnvSnapshot {
+        EnvSnapshot::new(
+            crate::environment::var_os("PATH"),
+            crate::environment::var_os("PATHEXT"),
+            crate::environment::current_dir(),
+        )
+    }
+
+    /// Uses the given values instead of the process's, `None` meaning unset. `pathext` is only
+    /// looked at on Windows.
+    pub fn new(
+        path: Option<OsString>,
+        pathext: Option<OsString>,
+        cwd: Option<PathBuf>,
+    ) -> EnvSnapshot {
+        EnvSnapshot {
+            path_extensions: PathExtensions::parse(pathext.as_deref()),
+            path,
+            pathext,
+            cwd,
+            vars: Vec::new(),
+        }
+    }
+
+    /// Sets the variable `name` to `value`, `None` meaning unset, for the lookups that read
+    /// others than `PATH` and `PATHEXT`, such as [`crate::python::active_environment`]
+    /// reading `VIRTUAL_ENV`. Names are compared case-insensitively on Windows.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use which::EnvSnapshot;
+    ///
+    /// let env = EnvSnapshot::new(Some("/usr/bin".into()), None, Some("/tmp".into()))
+    ///     .var("CARGO_TARGET_DIR", Some("/tmp/target"));
+    /// ```
+    pub fn var<K, V>(mut self, name: K, value: Option<V>) -> EnvSnapshot
+    where
+        K: Into<OsString>,
+        V: Into<OsString>,
+    {
+        let name = name.into();
+        let value = value.map(Into::into);
+        if crate::environment::same_var(&name, "PATH") {
+            self.path = value;
+        } else if crate::environment::same_var(&name, "PATHEXT") {
+            self.path_extensions = PathExtensions::parse(value.as_deref());
+            self.pathext = value;
+        } else {
+            self.vars
+                .retain(|(set, _)| !crate::environment::same_var(set, &name));
+            self.vars.push((name, value));
+        }
+        self
+    }
+
+    /// The value of the variable `name`, or `None` if it's unset, or isn't one the snapshot has
+    /// a value for.
+    ///
+    /// Only `PATH`, `PATHEXT` and the variables set with [`EnvSnapshot::var`] have one.
+    pub fn var_os<K: AsRef<OsStr>>(&self, name: K) -> Option<&OsStr> {
+        self.lookup(name.as_ref()).flatten()
+    }
+
+    /// The value of `name`, `Some(None)` if it's unset, or `None` if the snapshot doesn't cover
+    /// it.
+    pub(crate) fn lookup(&self, name: &OsStr) -> Option<Option<&OsStr>> {
+        if crate::environment::same_var(name, "PATH") {
+            Some(self.path())
+        } else if crate::environment::same_var(name, "PATHEXT") {
+            Some(self.pathext())
+        } else {
+            self.vars
+                .iter()
+                .find(|(set, _)| crate::environment::same_var(set, name))
+                .map(|(_, value)| value.as_deref())
+        }
+    }
+
+    /// The captured `PATH`.
+    pub fn path(&self) -> Option<&OsStr> {
+        self.path.as_deref()
+    }
+
+    /// The captured `PATHEXT`.
+    pub fn pathext(&self) -> Option<&OsStr> {
+        self.pathext.as_deref()
+    }
+
+    /// The captured working directory.
+    pub fn cwd(&self) -> Option<&Path> {
+        self.cwd.as_deref()
+    }
+
+    /// The extensions the captured `PATHEXT` lists, empty other than on Windows.
+    pub(crate) fn path_extensions(&self) -> &PathExtensions {
+        &self.path_extensions
+    }
+
+    /// Like [`crate::which`], but against this snapshot.
+    pub fn which<T: AsRef<OsStr>>(&self, binary_name: T) -> Result<PathBuf> {
+        self.which_all(binary_name)
+            .and_then(|mut i| i.next().ok_or(Error::CannotFindBinaryPath))
+    }
+
+    /// Like [`crate::which_all`], but against this snapshot.
+    pub fn which_all<T: AsRef<OsStr>>(
+        &self,
+        binary_name: T,
+    ) -> Result<impl FusedIterator<Item = PathBuf>> {
+        let binary_checker = build_binary_checker();
+
+        let finder = Finder::new().path_extensions(Some(self.path_extensions.clone()));
+
+        finder.find(
+            binary_name,
+            self.path.as_ref(),
+            self.cwd.as_ref(),
+            binary_checker,
+        )
+    }
+}
+
+impl std::fmt::Debug for EnvSnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EnvSnapshot")
+            .field("path", &self.path)
+            .field("pathext", &self.pathext)
+            .field("cwd", &self.cwd)
+            .field("vars", &self.vars)
+            .finish()
+    }
+}
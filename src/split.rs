@@ -0,0 +1,267 @@
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::iter::FusedIterator;
+use std::path::{Component, Path, PathBuf};
+
+// Redox can't use `:`, which separates a path's scheme from the rest, as in `file:/bin`.
+#[cfg(any(windows, target_os = "redox"))]
+pub(crate) const PATH_SEPARATOR: u8 = b';';
+#[cfg(not(any(windows, target_os = "redox")))]
+pub(crate) const PATH_SEPARATOR: u8 = b':';
+
+/// An owning, lazy equivalent of `std::env::split_paths`.
+///
+/// `env::split_paths` borrows the string it splits, which forces anything returning an owning
+/// iterator to collect every entry up front. This yields one entry at a time instead, following
+/// the same rules as the standard library (including double quote handling on Windows).
+#[derive(Clone)]
+pub struct SplitPaths {
+    bytes: Vec<u8>,
+    pos: usize,
+    finished: bool,
+    separator: u8,
+    /// Whether double quotes group entries, as they do on Windows.
+    quotes: bool,
+    /// The directory every entry is re-rooted under, if any.
+    root: Option<PathBuf>,
+    /// Whether entries are canonicalized.
+    resolve: bool,
+}
+
+impl SplitPaths {
+    pub fn new<T: AsRef<OsStr>>(paths: T) -> SplitPaths {
+        SplitPaths::with_separator(paths, PATH_SEPARATOR, cfg!(windows))
+    }
+
+    /// Splits `paths` on `separator` rather than the platform's separator. `separator` must be
+    /// ASCII.
+    pub fn with_separator<T: AsRef<OsStr>>(paths: T, separator: u8, quotes: bool) -> SplitPaths {
+        debug_assert!(separator.is_ascii());
+        SplitPaths {
+            bytes: paths.as_ref().as_encoded_bytes().to_vec(),
+            pos: 0,
+            finished: false,
+            separator,
+            quotes,
+            root: None,
+            resolve: false,
+        }
+    }
+
+    /// Re-roots every entry under `root`, as with [`reroot`].
+    pub fn under(mut self, root: Option<PathBuf>) -> SplitPaths {
+        self.root = root;
+        self
+    }
+
+    /// Canonicalizes every entry, after re-rooting it. Entries that can't be canonicalized, e.g.
+    /// because they don't exist, are kept as they are.
+    pub fn resolved(mut self, resolve: bool) -> SplitPaths {
+        self.resolve = resolve;
+        self
+    }
+}
+
+/// Normalizes `path` without touching the filesystem: repeated separators and `.` components
+/// are removed, and `..` components cancel out the component before them.
+///
+/// This is how the filesystem resolves `..` too, unless it follows a symlink, where it leads to
+/// the parent of the symlink's target instead.
+///
+/// # Example
+///
+/// ```
+/// use std::path::Path;
+///
+/// let path = which::normalize(Path::new("/usr//local/./bin/../lib"));
+/// assert_eq!(path, Path::new("/usr/local/lib"));
+/// assert_eq!(which::normalize(Path::new("../a/../b")), Path::new("../b"));
+/// assert_eq!(which::normalize(Path::new("a/..")), Path::new("."));
+/// ```
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                // There's nothing above the root.
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {}
+                _ => normalized.push(".."),
+            },
+            component => normalized.push(component.as_os_str()),
+        }
+    }
+    // What's left of a path that cancels itself out is the directory it started from.
+    if normalized.as_os_str().is_empty() && !path.as_os_str().is_empty() {
+        normalized.push(".");
+    }
+    normalized
+}
+
+/// Resolves `path` as if `root` was the root directory, the way a chroot would, except that
+/// symlinks aren't followed. Relative paths are taken to be relative to `root`, and `..` never
+/// leads outside it.
+pub fn reroot(root: &Path, path: &Path) -> PathBuf {
+    let mut rerooted = root.to_path_buf();
+    let mut depth = 0;
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => {
+                rerooted.push(name);
+                depth += 1;
+            }
+            Component::ParentDir if depth > 0 => {
+                rerooted.pop();
+                depth -= 1;
+            }
+            _ => {}
+        }
+    }
+    rerooted
+}
+
+impl Iterator for SplitPaths {
+    type Item = PathBuf;
+
+    fn next(&mut self) -> Option<PathBuf> {
+        if self.finished {
+            return None;
+        }
+
+        let rest = &self.bytes[self.pos..];
+        let mut entry = OsString::new();
+        let mut start = 0;
+        let mut end = rest.len();
+        let mut in_quote = false;
+
+        for (i, &b) in rest.iter().enumerate() {
+            if self.quotes && b == b'"' {
+                entry.push(bytes_to_os_str(&rest[start..i]));
+                start = i + 1;
+                in_quote = !in_quote;
+            } else if b == self.separator && !in_quote {
+                end = i;
+                break;
+            }
+        }
+        entry.push(bytes_to_os_str(&rest[start..end]));
+
+        if end == rest.len() {
+            self.finished = true;
+        } else {
+            self.pos += end + 1;
+        }
+
+        let entry = match &self.root {
+            Some(root) => reroot(root, Path::new(&entry)),
+            None => PathBuf::from(entry),
+        };
+        // Win32 ignores trailing dots and spaces, so `C:\tools. ` is where `C:\tools` is.
+        #[cfg(windows)]
+        let entry = crate::win::trim_trailing_dots_and_spaces(&entry);
+        if self.resolve {
+            if let Ok(resolved) = fs::canonicalize(&entry) {
+                return Some(resolved);
+            }
+        }
+        Some(entry)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.finished {
+            return (0, Some(0));
+        }
+        // Separators within quotes don't split, so this is only exact without quote handling.
+        let separators = self.bytes[self.pos..]
+            .iter()
+            .filter(|&&b| b == self.separator)
+            .count();
+        let upper = separators + 1;
+        (if self.quotes { 1 } else { upper }, Some(upper))
+    }
+}
+
+impl FusedIterator for SplitPaths {}
+
+fn bytes_to_os_str(bytes: &[u8]) -> &OsStr {
+    // SAFETY: `bytes` always comes from `OsStr::as_encoded_bytes` and is only ever split
+    // immediately before or after an ASCII byte, which keeps every piece valid.
+    unsafe { OsStr::from_encoded_bytes_unchecked(bytes) }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+
+    fn assert_same_as_std(paths: &str) {
+        let expected: Vec<_> = env::split_paths(paths).collect();
+        let actual: Vec<_> = SplitPaths::new(paths).collect();
+        assert_eq!(actual, expected, "splitting {:?}", paths);
+    }
+
+    #[test]
+    fn test_split_matches_std() {
+        assert_same_as_std("");
+        assert_same_as_std("/usr/bin");
+        assert_same_as_std("/usr/bin:/bin");
+        assert_same_as_std("/usr/bin;/bin");
+        assert_same_as_std("/usr/bin::/bin:");
+        assert_same_as_std(":");
+        assert_same_as_std("file:/usr/bin;file:/bin");
+        assert_same_as_std(r#"c:\a;"c:\b;c";d"#);
+    }
+
+    #[test]
+    fn test_split_with_separator() {
+        let split: Vec<_> =
+            SplitPaths::with_separator(r#"C:\bin;"C:\a;b";;D:\"#, b';', true).collect();
+        let expected: Vec<PathBuf> =
+            vec![r"C:\bin".into(), r"C:\a;b".into(), "".into(), r"D:\".into()];
+        assert_eq!(split, expected);
+
+        let split: Vec<_> = SplitPaths::with_separator("/a:/b", b';', false).collect();
+        assert_eq!(split, vec![PathBuf::from("/a:/b")]);
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize(Path::new("/a/./b//c/..")), Path::new("/a/b"));
+        assert_eq!(normalize(Path::new("/../a")), Path::new("/a"));
+        assert_eq!(normalize(Path::new("./a/../../b")), Path::new("../b"));
+        assert_eq!(normalize(Path::new("a/..")), Path::new("."));
+        assert_eq!(normalize(Path::new(".")), Path::new("."));
+        assert_eq!(normalize(Path::new("")), Path::new(""));
+    }
+
+    #[test]
+    fn test_reroot() {
+        let root = Path::new("/sysroot");
+        assert_eq!(
+            reroot(root, Path::new("/usr/bin")),
+            root.join("usr").join("bin")
+        );
+        assert_eq!(reroot(root, Path::new("bin")), root.join("bin"));
+        assert_eq!(
+            reroot(root, Path::new("/../usr/./bin/..")),
+            root.join("usr")
+        );
+        assert_eq!(reroot(root, Path::new("")), root);
+    }
+
+    #[test]
+    fn test_split_size_hint() {
+        let paths = env::join_paths(["/usr/local/bin", "", "/usr/bin", "/bin"]).unwrap();
+        let mut split = SplitPaths::new(&paths);
+        for remaining in (1..=4).rev() {
+            let (lower, upper) = split.size_hint();
+            assert!(lower <= remaining && Some(remaining) == upper);
+            split.next();
+        }
+        assert_eq!(split.size_hint(), (0, Some(0)));
+        assert_eq!(split.next(), None);
+    }
+}
@@ -0,0 +1,236 @@
+use crate::finder::Origin;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Statistics about a search, for attributing slow lookups to the `PATH` entries causing them.
+///
+/// Returned by [`WhichConfig::first_result_with_stats`](crate::WhichConfig::first_result_with_stats)
+/// and [`WhichConfig::all_results_with_stats`](crate::WhichConfig::all_results_with_stats).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Stats {
+    /// How many directories were looked at, including the working directory for names
+    /// containing a separator.
+    pub directories_scanned: usize,
+    /// How many candidate paths were checked.
+    pub candidates: usize,
+    /// How many filesystem calls (metadata lookups and directory reads) the search made itself.
+    /// Calls made by custom checkers aren't included.
+    pub syscalls: usize,
+    /// The time spent searching, excluding time spent by the caller between results.
+    pub elapsed: Duration,
+    /// A breakdown per directory, in search order.
+    pub directories: Vec<DirStats>,
+}
+
+/// Statistics about the part of a search spent in one directory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DirStats {
+    /// The directory searched.
+    pub dir: PathBuf,
+    /// Where the directory is in the search. Subdirectories searched because of
+    /// [`WhichConfig::depth`](crate::WhichConfig::depth) share the origin of their entry.
+    pub origin: Origin,
+    /// How many candidate paths were checked in this directory.
+    pub candidates: usize,
+    /// The time spent searching this directory.
+    pub elapsed: Duration,
+}
+
+impl Stats {
+    /// The time spent in each part of the search, such as each `PATH` entry along with any
+    /// subdirectories of it that were searched, in search order. Parts that were searched
+    /// more than once, like a directory listed twice, are only reported once.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use which::{Origin, WhichConfig};
+    ///
+    /// let (_, stats) = WhichConfig::new()
+    ///     .binary_name("cargo".into())
+    ///     .first_result_with_stats();
+    /// for (origin, elapsed) in stats.elapsed_by_origin() {
+    ///     if elapsed > Duration::from_millis(100) {
+    ///         println!("{:?} is slow: {:?}", origin, elapsed);
+    ///     }
+    /// }
+    /// ```
+    pub fn elapsed_by_origin(&self) -> Vec<(Origin, Duration)> {
+        let mut totals: Vec<(Origin, Duration)> = Vec::new();
+        for dir in &self.directories {
+            match totals.iter_mut().find(|(origin, _)| *origin == dir.origin) {
+                Some((_, elapsed)) => *elapsed += dir.elapsed,
+                None => totals.push((dir.origin, dir.elapsed)),
+            }
+        }
+        totals
+    }
+
+    fn enter_dir(&mut self, dir: &Path, origin: Origin) {
+        self.directories_scanned += 1;
+        self.directories.push(DirStats {
+            dir: dir.to_path_buf(),
+            origin,
+            candidates: 0,
+            elapsed: Duration::ZERO,
+        });
+    }
+
+    fn candidate(&mut self, syscalls: usize) {
+        self.candidates += 1;
+        self.syscalls += syscalls;
+        if let Some(dir) = self.directories.last_mut() {
+            dir.candidates += 1;
+        }
+    }
+
+    fn record_time(&mut self, elapsed: Duration) {
+        self.elapsed += elapsed;
+        if let Some(dir) = self.directories.last_mut() {
+            dir.elapsed += elapsed;
+        }
+    }
+}
+
+/// A summary line, then a line per directory with the time spent in it, e.g. for a `--debug`
+/// flag of a program that looks tools up.
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "searched {} directories, checking {} candidates with {} filesystem calls, in {:?}",
+            self.directories_scanned, self.candidates, self.syscalls, self.elapsed
+        )?;
+        for dir in &self.directories {
+            write!(
+                f,
+                "\n{:>12?}  {} ({} candidates)",
+                dir.elapsed,
+                dir.dir.display(),
+                dir.candidates
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A callback told about each directory whose search took at least a threshold, and how long.
+pub(crate) type SlowDirCallback = Arc<dyn Fn(&Path, Duration) + Send + Sync>;
+
+/// Reports the directories that take at least `threshold` to search, once each.
+struct SlowDirs {
+    threshold: Duration,
+    callback: SlowDirCallback,
+    /// The directory being searched and the time spent in it so far, if not reported yet.
+    current: Option<(PathBuf, Duration)>,
+}
+
+impl SlowDirs {
+    fn record(&mut self, elapsed: Duration) {
+        if let Some((dir, spent)) = &mut self.current {
+            *spent += elapsed;
+            if *spent >= self.threshold {
+                (self.callback)(dir, *spent);
+                self.current = None;
+            }
+        }
+    }
+}
+
+/// Collects [`Stats`] for a search when asked to, and otherwise does nothing (not even reading
+/// the clock) unless it's watching for slow directories.
+pub(crate) struct Recorder {
+    stats: Option<Stats>,
+    slow_dirs: Option<SlowDirs>,
+    started: Option<Instant>,
+}
+
+impl Recorder {
+    pub fn new(enabled: bool) -> Recorder {
+        Recorder {
+            stats: if enabled {
+                Some(Stats::default())
+            } else {
+                None
+            },
+            slow_dirs: None,
+            started: None,
+        }
+    }
+
+    /// Calls `callback` for each directory that takes at least `threshold` to search, as soon
+    /// as it has.
+    pub fn slow_dirs(mut self, slow_dirs: Option<(Duration, SlowDirCallback)>) -> Recorder {
+        self.slow_dirs = slow_dirs.map(|(threshold, callback)| SlowDirs {
+            threshold,
+            callback,
+            current: None,
+        });
+        self
+    }
+
+    fn timing(&self) -> bool {
+        self.stats.is_some() || self.slow_dirs.is_some()
+    }
+
+    fn record_time(&mut self, since: Instant) {
+        let elapsed = since.elapsed();
+        if let Some(stats) = &mut self.stats {
+            stats.record_time(elapsed);
+        }
+        if let Some(slow_dirs) = &mut self.slow_dirs {
+            slow_dirs.record(elapsed);
+        }
+    }
+
+    /// Marks the start of a stretch of searching, i.e. a call to `next`.
+    pub fn begin(&mut self) {
+        if self.timing() {
+            self.started = Some(Instant::now());
+        }
+    }
+
+    /// Marks the end of a stretch of searching.
+    pub fn end(&mut self) {
+        if let Some(started) = self.started.take() {
+            self.record_time(started);
+        }
+    }
+
+    pub fn enter_dir(&mut self, dir: &Path, origin: Origin) {
+        if !self.timing() {
+            return;
+        }
+        // Attribute the time so far to the previous directory.
+        if let Some(started) = self.started.replace(Instant::now()) {
+            self.record_time(started);
+        }
+        if let Some(stats) = &mut self.stats {
+            stats.enter_dir(dir, origin);
+        }
+        if let Some(slow_dirs) = &mut self.slow_dirs {
+            slow_dirs.current = Some((dir.to_path_buf(), Duration::ZERO));
+        }
+    }
+
+    pub fn candidate(&mut self, fetched_metadata: bool) {
+        if let Some(stats) = &mut self.stats {
+            stats.candidate(fetched_metadata as usize);
+        }
+    }
+
+    pub fn listing(&mut self) {
+        if let Some(stats) = &mut self.stats {
+            stats.syscalls += 1;
+        }
+    }
+
+    pub fn into_stats(self) -> Option<Stats> {
+        self.stats
+    }
+}
@@ -0,0 +1,209 @@
+//! Searches as [`Stream`]s, for async code that wants results as they're found without blocking
+//! its executor on the filesystem.
+//!
+//! Only available with the `futures-core` feature. The search runs in the background and hands
+//! results over one at a time, so consumers can stop at any point by dropping the stream. It
+//! works with any executor, since waking the consumer is all the search needs from it. By
+//! default each search gets a thread of its own, but a [`Spawner`] can run it elsewhere, such as
+//! on the blocking pool of the async runtime in use.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # async fn run() -> which::Result<()> {
+//! use futures_core::Stream;
+//! use std::pin::Pin;
+//!
+//! let mut pythons = which::stream::which_all("python3")?;
+//! while let Some(python) =
+//!     std::future::poll_fn(|cx| Pin::new(&mut pythons).poll_next(cx)).await
+//! {
+//!     println!("{}", python.display());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{Result, WhichConfig};
+use futures_core::{FusedStream, Stream};
+use std::collections::VecDeque;
+use std::ffi::OsStr;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+/// Find all binaries with `binary_name` like [`crate::which_all`], as a stream.
+///
+/// The search is set up before this returns, so errors such as an invalid name are reported
+/// right away, and only the search itself runs in the background.
+pub fn which_all<T: AsRef<OsStr>>(binary_name: T) -> Result<WhichStream> {
+    which_all_with(binary_name, &ThreadSpawner)
+}
+
+/// Like [`which_all`], but running the search with `spawner`.
+pub fn which_all_with<T: AsRef<OsStr>>(
+    binary_name: T,
+    spawner: &dyn Spawner,
+) -> Result<WhichStream> {
+    WhichConfig::new()
+        .binary_name(binary_name.as_ref().to_owned())
+        .all_results_stream_with(spawner)
+}
+
+/// Runs the blocking part of background searches.
+///
+/// Closures taking the task are spawners, so adapting a runtime's own way of running blocking
+/// code takes one line:
+///
+/// ```ignore
+/// let spawner = |task| {
+///     tokio::task::spawn_blocking(task);
+/// };
+/// let pythons = which::stream::which_all_with("python3", &spawner)?;
+/// ```
+pub trait Spawner {
+    /// Runs `task` somewhere it may block, eventually. The stream is woken up as results come
+    /// in, so it's fine for `task` to only start later, or to run right away, but a task that
+    /// never runs leaves the stream pending forever.
+    fn spawn_blocking(&self, task: Box<dyn FnOnce() + Send>);
+}
+
+impl<F> Spawner for F
+where
+    F: Fn(Box<dyn FnOnce() + Send>),
+{
+    fn spawn_blocking(&self, task: Box<dyn FnOnce() + Send>) {
+        self(task)
+    }
+}
+
+/// Runs each task on a new thread. The default.
+///
+/// Where threads can't be spawned, such as on `wasm32-unknown-unknown`, the task runs right
+/// away instead, so the search is over by the time the stream is returned.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ThreadSpawner;
+
+impl Spawner for ThreadSpawner {
+    fn spawn_blocking(&self, task: Box<dyn FnOnce() + Send>) {
+        // The standard library gives the closure back on failure, so keep it reachable.
+        let task = Arc::new(Mutex::new(Some(task)));
+        let spawned = task.clone();
+        let result = thread::Builder::new().spawn(move || {
+            if let Some(task) = spawned.lock().unwrap().take() {
+                task();
+            }
+        });
+        if result.is_err() {
+            if let Some(task) = task.lock().unwrap().take() {
+                task();
+            }
+        }
+    }
+}
+
+/// How many results a search gets ahead of the stream before it waits for them to be taken.
+const BUFFERED: usize = 16;
+
+/// The results of a search running in the background, as a [`Stream`].
+///
+/// Dropping the stream cancels the search once the result it's working on is found, or it
+/// finishes. Up to 16 results found but not yet taken are buffered, and the search waits for
+/// room beyond that, unless it runs before the stream is returned, as a spawner running tasks
+/// right away has it, in which case every result is.
+pub struct WhichStream {
+    channel: Arc<Channel>,
+}
+
+/// How the search and the stream exchange results.
+struct Channel {
+    shared: Mutex<Shared>,
+    /// Signalled as results are taken or the stream is dropped.
+    room: Condvar,
+}
+
+#[derive(Default)]
+struct Shared {
+    results: VecDeque<PathBuf>,
+    /// Whether the search is over.
+    done: bool,
+    /// Whether the stream was dropped, so nobody wants any more results.
+    cancelled: bool,
+    /// Whether the stream is still being set up, so nobody can take results yet.
+    spawning: bool,
+    /// Who to wake when a result comes in or the search is over.
+    waker: Option<Waker>,
+}
+
+impl WhichStream {
+    pub(crate) fn spawn(
+        search: Box<dyn Iterator<Item = PathBuf> + Send>,
+        spawner: &dyn Spawner,
+    ) -> WhichStream {
+        let channel = Arc::new(Channel {
+            shared: Mutex::new(Shared {
+                spawning: true,
+                ..Shared::default()
+            }),
+            room: Condvar::new(),
+        });
+        let producer = channel.clone();
+        spawner.spawn_blocking(Box::new(move || {
+            for path in search {
+                let mut shared = producer.shared.lock().unwrap();
+                while shared.results.len() >= BUFFERED && !shared.spawning && !shared.cancelled {
+                    shared = producer.room.wait(shared).unwrap();
+                }
+                if shared.cancelled {
+                    return;
+                }
+                shared.results.push_back(path);
+                if let Some(waker) = shared.waker.take() {
+                    waker.wake();
+                }
+            }
+            let mut shared = producer.shared.lock().unwrap();
+            shared.done = true;
+            if let Some(waker) = shared.waker.take() {
+                waker.wake();
+            }
+        }));
+        channel.shared.lock().unwrap().spawning = false;
+        WhichStream { channel }
+    }
+}
+
+impl Stream for WhichStream {
+    type Item = PathBuf;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<PathBuf>> {
+        let mut shared = self.channel.shared.lock().unwrap();
+        if let Some(path) = shared.results.pop_front() {
+            self.channel.room.notify_one();
+            return Poll::Ready(Some(path));
+        }
+        if shared.done {
+            return Poll::Ready(None);
+        }
+        shared.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl FusedStream for WhichStream {
+    fn is_terminated(&self) -> bool {
+        let shared = self.channel.shared.lock().unwrap();
+        shared.done && shared.results.is_empty()
+    }
+}
+
+impl Drop for WhichStream {
+    fn drop(&mut self) {
+        let mut shared = self.channel.shared.lock().unwrap();
+        shared.cancelled = true;
+        shared.results.clear();
+        self.channel.room.notify_one();
+    }
+}
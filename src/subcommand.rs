@@ -0,0 +1,258 @@
+use crate::error::*;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+
+/// An external subcommand of a program, such as `git-lfs` for `git lfs`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Subcommand {
+    /// The name of the subcommand, like `lfs`.
+    pub name: OsString,
+    /// The executable that implements it, like `/usr/bin/git-lfs`.
+    pub path: PathBuf,
+}
+
+/// How the executables implementing a program's external subcommands are named, for plugin
+/// systems that follow other conventions than `git`'s `<program>-<name>`.
+///
+/// A file name is a prefix, then the subcommand name with some characters replaced, then a
+/// suffix. On Windows, it's also followed by an executable extension.
+///
+/// # Example
+///
+/// `kubectl` separates the words of nested subcommands with `-` in file names, and spells `-`
+/// within a word as `_`:
+///
+/// ```
+/// use which::SubcommandScheme;
+///
+/// let kubectl = SubcommandScheme::new("kubectl-").replace('-', ' ').replace('_', '-');
+/// assert_eq!(kubectl.file_name("view secret".as_ref()).unwrap(), "kubectl-view-secret");
+/// assert_eq!(kubectl.name("kubectl-view_all".as_ref()).unwrap(), "view-all");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SubcommandScheme {
+    prefix: OsString,
+    suffix: OsString,
+    lowercase: bool,
+    /// Characters of file names, and what they stand for in subcommand names.
+    replacements: Vec<(char, char)>,
+}
+
+impl SubcommandScheme {
+    /// File names that start with `prefix`, like `cargo-`, followed by the subcommand name.
+    pub fn new<T: Into<OsString>>(prefix: T) -> SubcommandScheme {
+        SubcommandScheme {
+            prefix: prefix.into(),
+            suffix: OsString::new(),
+            lowercase: false,
+            replacements: Vec::new(),
+        }
+    }
+
+    /// The scheme of `git` and `cargo`: `<program>-<name>`.
+    pub fn dashed<T: AsRef<OsStr>>(program: T) -> SubcommandScheme {
+        let mut prefix = program.as_ref().to_owned();
+        prefix.push("-");
+        SubcommandScheme::new(prefix)
+    }
+
+    /// Expects file names to end with `suffix` too, before any executable extension, like
+    /// `-plugin`.
+    pub fn suffix<T: Into<OsString>>(mut self, suffix: T) -> SubcommandScheme {
+        self.suffix = suffix.into();
+        self
+    }
+
+    /// Whether subcommand names are lowercase, so that a file for `Lint` is reported as `lint`,
+    /// and looking up `lint` or `LINT` finds it. Disabled by default.
+    ///
+    /// Since files may spell such names any way, [`subcommand_with`] lists the directories on the
+    /// path to look one up.
+    pub fn lowercase(mut self, lowercase: bool) -> SubcommandScheme {
+        self.lowercase = lowercase;
+        self
+    }
+
+    /// Spells `in_name` in subcommand names as `in_file` in file names. Each character is
+    /// replaced once, so replacements can swap characters.
+    pub fn replace(mut self, in_file: char, in_name: char) -> SubcommandScheme {
+        self.replacements.push((in_file, in_name));
+        self
+    }
+
+    /// The file name for the subcommand `name`, without any executable extension. `None` if
+    /// characters need replacing or lowercasing but `name` isn't valid Unicode.
+    pub fn file_name(&self, name: &OsStr) -> Option<OsString> {
+        let mut file_name = self.prefix.clone();
+        file_name.push(self.transform(name, |(in_file, in_name)| (in_name, in_file))?);
+        file_name.push(&self.suffix);
+        Some(file_name)
+    }
+
+    /// The subcommand the file `file_name` implements, without any executable extension, or
+    /// `None` if it doesn't follow the scheme or names no subcommand.
+    pub fn name(&self, file_name: &OsStr) -> Option<OsString> {
+        let bytes = file_name.as_encoded_bytes();
+        let name = bytes
+            .strip_prefix(self.prefix.as_encoded_bytes())?
+            .strip_suffix(self.suffix.as_encoded_bytes())?;
+        if name.is_empty() {
+            return None;
+        }
+        // SAFETY: the prefix and suffix are whole `OsStr`s, so what's between them splits
+        // `file_name` where those do.
+        let name = unsafe { OsStr::from_encoded_bytes_unchecked(name) };
+        self.transform(name, |replacement| replacement)
+    }
+
+    /// `name` with the replacements `direction` orients applied, and lowercased if enabled.
+    fn transform<F>(&self, name: &OsStr, direction: F) -> Option<OsString>
+    where
+        F: Fn((char, char)) -> (char, char),
+    {
+        if self.replacements.is_empty() && !self.lowercase {
+            return Some(name.to_owned());
+        }
+        let name = name.to_str()?;
+        let name = if self.lowercase {
+            name.to_lowercase()
+        } else {
+            name.to_owned()
+        };
+        let transformed = name
+            .chars()
+            .map(|c| {
+                self.replacements
+                    .iter()
+                    .map(|&replacement| direction(replacement))
+                    .find(|&(from, _)| from == c)
+                    .map_or(c, |(_, to)| to)
+            })
+            .collect::<String>();
+        Some(transformed.into())
+    }
+}
+
+/// Find the external subcommands of `program` in `$PATH`: the executables named `<program>-*`,
+/// the way `git` and `cargo` find theirs.
+///
+/// Each subcommand is reported once, with the executable that takes precedence, and they're
+/// sorted by name. On Windows, names are reported without their executable extension.
+///
+/// # Example
+///
+/// ```no_run
+/// for subcommand in which::subcommands("git").unwrap() {
+///     println!("git {:?}: {}", subcommand.name, subcommand.path.display());
+/// }
+/// ```
+pub fn subcommands<T: AsRef<OsStr>>(program: T) -> Result<Vec<Subcommand>> {
+    subcommands_in(program, crate::environment::var_os("PATH"))
+}
+
+/// Find the external subcommands of `program` in the path list `paths`.
+///
+/// See [`subcommands`] for details.
+pub fn subcommands_in<T, U>(program: T, paths: Option<U>) -> Result<Vec<Subcommand>>
+where
+    T: AsRef<OsStr>,
+    U: AsRef<OsStr>,
+{
+    subcommands_with(&SubcommandScheme::dashed(program), paths)
+}
+
+/// Find the external subcommands named by `scheme` in the path list `paths`.
+///
+/// See [`subcommands`] for details.
+pub fn subcommands_with<U: AsRef<OsStr>>(
+    scheme: &SubcommandScheme,
+    paths: Option<U>,
+) -> Result<Vec<Subcommand>> {
+    let matches = crate::which_matching_in(
+        |file_name: &OsStr| scheme.name(strip_extension(file_name)).is_some(),
+        paths,
+    )?;
+
+    let mut found: Vec<Subcommand> = Vec::new();
+    for path in matches {
+        let name = match path
+            .file_name()
+            .and_then(|file_name| scheme.name(strip_extension(file_name)))
+        {
+            Some(name) => name,
+            None => continue,
+        };
+        if !found.iter().any(|subcommand| subcommand.name == name) {
+            found.push(Subcommand { name, path });
+        }
+    }
+    found.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(found)
+}
+
+/// Find the executable implementing the subcommand `name` of `program` in `$PATH`, like
+/// `git-lfs` for `git lfs`.
+pub fn subcommand<T: AsRef<OsStr>, U: AsRef<OsStr>>(program: T, name: U) -> Result<PathBuf> {
+    let file_name = SubcommandScheme::dashed(program)
+        .file_name(name.as_ref())
+        .ok_or(Error::CannotFindBinaryPath)?;
+    crate::which(file_name)
+}
+
+/// Find the executable implementing the subcommand `name` of `program` in the path list
+/// `paths`, using `cwd` to resolve relative paths.
+///
+/// See [`subcommand`] for details.
+pub fn subcommand_in<T, U, V, W>(program: T, name: U, paths: Option<V>, cwd: W) -> Result<PathBuf>
+where
+    T: AsRef<OsStr>,
+    U: AsRef<OsStr>,
+    V: AsRef<OsStr>,
+    W: AsRef<Path>,
+{
+    subcommand_with(&SubcommandScheme::dashed(program), name, paths, cwd)
+}
+
+/// Find the executable implementing the subcommand `name` named by `scheme` in the path list
+/// `paths`, using `cwd` to resolve relative paths.
+///
+/// See [`subcommand`] for details.
+pub fn subcommand_with<U, V, W>(
+    scheme: &SubcommandScheme,
+    name: U,
+    paths: Option<V>,
+    cwd: W,
+) -> Result<PathBuf>
+where
+    U: AsRef<OsStr>,
+    V: AsRef<OsStr>,
+    W: AsRef<Path>,
+{
+    let name = name.as_ref();
+    // Files may spell a lowercase subcommand any way, so the name can't be turned into one.
+    if scheme.lowercase {
+        let name = OsString::from(
+            name.to_str()
+                .ok_or(Error::CannotFindBinaryPath)?
+                .to_lowercase(),
+        );
+        return crate::which_matching_in(
+            |file_name: &OsStr| scheme.name(strip_extension(file_name)).as_ref() == Some(&name),
+            paths,
+        )?
+        .next()
+        .ok_or(Error::CannotFindBinaryPath);
+    }
+    let file_name = scheme.file_name(name).ok_or(Error::CannotFindBinaryPath)?;
+    crate::which_in(file_name, paths, cwd)
+}
+
+/// `file_name` without its executable extension on Windows.
+fn strip_extension(file_name: &OsStr) -> &OsStr {
+    let path = Path::new(file_name);
+    match path.file_stem() {
+        Some(stem) if cfg!(windows) && crate::win::is_executable_name(path) => stem,
+        _ => file_name,
+    }
+}
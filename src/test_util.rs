@@ -0,0 +1,42 @@
+//! Helpers for testing code that looks executables up, without touching the process's
+//! environment.
+//!
+//! Only available with the `test-util` feature. Tests that point `PATH` somewhere with
+//! [`std::env::set_var`] race with every other test running at the same time, since the
+//! environment is shared by the whole process. [`with_env`] substitutes `PATH`, `PATHEXT`, the
+//! working directory and any other variables set with [`EnvSnapshot::var`] for lookups on the
+//! current thread only, so tests running in parallel each see their own.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use which::EnvSnapshot;
+//!
+//! let env = EnvSnapshot::new(Some("/opt/fake/bin".into()), None, Some("/tmp".into()));
+//! let found = which::test_util::with_env(env, || which::which("mytool"));
+//! assert_eq!(found.unwrap(), std::path::Path::new("/opt/fake/bin/mytool"));
+//! ```
+
+use crate::snapshot::EnvSnapshot;
+
+/// Runs `f` with lookups on this thread seeing `env`'s `PATH`, `PATHEXT`, working directory and
+/// [variables](EnvSnapshot::var) instead of the process's, `None` meaning unset.
+///
+/// Everything in this crate that reads those sees the substitutes: the free functions like
+/// [`which`](crate::which), [`WhichConfig`](crate::WhichConfig) searches, the toolchain lookups
+/// like [`crate::python::active_environment`], [`EnvSnapshot::capture`] and so on. Variables
+/// `env` doesn't set are read from the process as usual.
+///
+/// Lookups started on other threads aren't affected. Streams from [`crate::stream`] are
+/// started on this one: they read `PATH`, `PATHEXT` and the working directory before they
+/// return, so their searches see the substitutes even though they run in the background.
+/// Calls nest, and the previous environment is restored once `f` returns or panics.
+///
+/// Results cached by [`crate::cache`] are keyed by the `PATH`, `PATHEXT` and working directory
+/// lookups saw, so they don't leak between substituted environments and the process's.
+pub fn with_env<F, R>(env: EnvSnapshot, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    crate::environment::substitute(env, f)
+}
@@ -0,0 +1,155 @@
+//! Batched metadata lookups through io_uring, so that checking many candidates costs a handful
+//! of syscalls rather than one `stat` each.
+
+use crate::checker::FileInfo;
+use io_uring::{opcode, types, IoUring, Probe};
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::io;
+use std::mem::MaybeUninit;
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+
+/// How many lookups are in flight at once.
+const RING_SIZE: u32 = 64;
+
+thread_local! {
+    /// The thread's ring, set up by its first lookup. `Some(None)` once io_uring or `statx`
+    /// turned out to be unavailable, so that the setup isn't retried on every search.
+    static RING: RefCell<Option<Option<IoUring>>> = const { RefCell::new(None) };
+}
+
+/// Sets up a ring that supports `statx`, or `None` if that isn't possible.
+fn new_ring() -> Option<IoUring> {
+    let ring = IoUring::new(RING_SIZE).ok()?;
+    let mut probe = Probe::new();
+    ring.submitter().register_probe(&mut probe).ok()?;
+    Some(ring).filter(|_| probe.is_supported(opcode::Statx::CODE))
+}
+
+/// Looks up the metadata of every path in `paths`, following symlinks like `fs::metadata`.
+///
+/// The result has an entry per path: `Some(Some(info))` for files that exist, `Some(None)` for
+/// files that definitely don't, and `None` for lookups that failed for any other reason and
+/// should be retried the usual way. Fails if io_uring or its `statx` operation is unavailable,
+/// e.g. on kernels older than 5.6 or under seccomp filters.
+///
+/// Each thread sets its ring up once and reuses it for later lookups.
+pub fn statx(paths: &[PathBuf]) -> io::Result<Vec<Option<Option<FileInfo>>>> {
+    RING.with(|ring| {
+        let mut ring = ring.borrow_mut();
+        let result = match ring.get_or_insert_with(new_ring) {
+            Some(ring) => statx_with(ring, paths),
+            None => return Err(io::Error::from(io::ErrorKind::Unsupported)),
+        };
+        // Lookups may still be in flight after a failure, so the ring can't be trusted with new
+        // ones. The next lookup sets up a fresh one.
+        if result.is_err() {
+            *ring = None;
+        }
+        result
+    })
+}
+
+fn statx_with(ring: &mut IoUring, paths: &[PathBuf]) -> io::Result<Vec<Option<Option<FileInfo>>>> {
+    let mut results = vec![None; paths.len()];
+    let mut bufs: Vec<MaybeUninit<libc::statx>> = Vec::with_capacity(RING_SIZE as usize);
+
+    for (chunk_index, chunk) in paths.chunks(RING_SIZE as usize).enumerate() {
+        let offset = chunk_index * RING_SIZE as usize;
+        // Both have to stay alive and in place until the submissions complete.
+        let names = chunk
+            .iter()
+            .map(|path| CString::new(path.as_os_str().as_bytes()).ok())
+            .collect::<Vec<_>>();
+        bufs.clear();
+        bufs.resize_with(chunk.len(), MaybeUninit::uninit);
+
+        let mut submitted = 0;
+        for (i, name) in names.iter().enumerate() {
+            let name = match name {
+                Some(name) => name,
+                // Paths with interior nul bytes can't exist.
+                None => {
+                    results[offset + i] = Some(None);
+                    continue;
+                }
+            };
+            let entry = opcode::Statx::new(
+                types::Fd(libc::AT_FDCWD),
+                name.as_ptr(),
+                bufs[i].as_mut_ptr().cast::<types::statx>(),
+            )
+            .flags(libc::AT_STATX_SYNC_AS_STAT)
+            .mask(
+                libc::STATX_TYPE
+                    | libc::STATX_MODE
+                    | libc::STATX_UID
+                    | libc::STATX_GID
+                    | libc::STATX_SIZE,
+            )
+            .build()
+            .user_data(i as u64);
+
+            // SAFETY: the path and buffer outlive the submission, since the loop below waits for
+            // every entry to complete before either is dropped or reused.
+            unsafe { ring.submission().push(&entry) }
+                .expect("the submission queue holds a whole chunk");
+            submitted += 1;
+        }
+
+        let mut completed = 0;
+        while completed < submitted {
+            match ring.submit_and_wait(submitted - completed) {
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    // Some lookups may still be in flight, so their memory must never be freed.
+                    std::mem::forget(names);
+                    std::mem::forget(bufs);
+                    return Err(e);
+                }
+            }
+            for cqe in ring.completion() {
+                completed += 1;
+                let i = cqe.user_data() as usize;
+                results[offset + i] = match -cqe.result() {
+                    0 => {
+                        // SAFETY: the kernel filled the buffer in, as the lookup succeeded.
+                        let buf = unsafe { bufs[i].assume_init_ref() };
+                        Some(Some(FileInfo::from_statx(buf)))
+                    }
+                    libc::ENOENT | libc::ENOTDIR => Some(None),
+                    _ => None,
+                };
+            }
+        }
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::checker::FileKind;
+    use std::{env, fs};
+
+    #[test]
+    fn test_statx_matches_metadata() {
+        let exe = env::current_exe().unwrap();
+        let paths = [exe.clone(), exe.with_extension("missing")];
+        let infos = match statx(&paths) {
+            Ok(infos) => infos,
+            // Nothing to compare against where io_uring is unavailable.
+            Err(_) => return,
+        };
+
+        let metadata = fs::metadata(&exe).unwrap();
+        let info = infos[0].clone().unwrap().unwrap();
+        assert_eq!(info.kind(), FileKind::File);
+        assert_eq!(info.mode(), std::os::unix::fs::MetadataExt::mode(&metadata));
+        assert_eq!(info.size(), Some(metadata.len()));
+        assert!(matches!(infos[1], Some(None)));
+    }
+}
@@ -0,0 +1,49 @@
+//! Variants of the lookup functions returning [`camino`] paths, for callers that only deal in
+//! UTF-8 paths.
+
+use crate::error::*;
+use camino::Utf8PathBuf;
+use std::ffi::OsStr;
+use std::iter::FusedIterator;
+use std::path::{Path, PathBuf};
+
+/// Like [`which`](crate::which), but returns a [`Utf8PathBuf`].
+///
+/// Fails with [`Error::NonUtf8Path`] if the match isn't valid UTF-8, rather than converting it
+/// lossily. Only available when feature `camino` is enabled.
+///
+/// # Example
+///
+/// ```no_run
+/// let cargo = which::which_utf8("cargo").unwrap();
+/// println!("cargo is at {}", cargo);
+/// ```
+pub fn which_utf8<T: AsRef<OsStr>>(binary_name: T) -> Result<Utf8PathBuf> {
+    crate::which(binary_name).and_then(to_utf8)
+}
+
+/// Like [`which_all`](crate::which_all), but yields [`Utf8PathBuf`]s, or
+/// [`Error::NonUtf8Path`] for each match that isn't valid UTF-8.
+///
+/// Only available when feature `camino` is enabled.
+pub fn which_all_utf8<T: AsRef<OsStr>>(
+    binary_name: T,
+) -> Result<impl FusedIterator<Item = Result<Utf8PathBuf>>> {
+    crate::which_all(binary_name).map(|i| i.map(to_utf8))
+}
+
+/// Like [`which_in`](crate::which_in), but returns a [`Utf8PathBuf`].
+///
+/// Only available when feature `camino` is enabled.
+pub fn which_in_utf8<T, U, V>(binary_name: T, paths: Option<U>, cwd: V) -> Result<Utf8PathBuf>
+where
+    T: AsRef<OsStr>,
+    U: AsRef<OsStr>,
+    V: AsRef<Path>,
+{
+    crate::which_in(binary_name, paths, cwd).and_then(to_utf8)
+}
+
+fn to_utf8(path: PathBuf) -> Result<Utf8PathBuf> {
+    Utf8PathBuf::from_path_buf(path).map_err(|_| Error::NonUtf8Path)
+}
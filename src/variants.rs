@@ -0,0 +1,70 @@
+use std::ffi::{OsStr, OsString};
+
+/// A set of variations of a name to look up, in order of precedence, for
+/// [`which_variants`](crate::which_variants).
+///
+/// Each variant is a prefix and a suffix put around the name, for versioned names like
+/// `clang-18`, wrappers like `ccache-gcc` or explicit extensions like `.exe`. Variants are tried
+/// prefix by prefix, and for each prefix suffix by suffix, both in the order they were added.
+/// Without any prefixes or suffixes, the name is used as it is; add an empty one to include the
+/// plain name among others.
+///
+/// # Example
+///
+/// ```
+/// use which::NameVariants;
+///
+/// let variants = NameVariants::new().suffix("-18").suffix("-17").suffix("");
+/// assert_eq!(variants.names("clang"), ["clang-18", "clang-17", "clang"]);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NameVariants {
+    prefixes: Vec<OsString>,
+    suffixes: Vec<OsString>,
+}
+
+impl NameVariants {
+    pub fn new() -> NameVariants {
+        NameVariants::default()
+    }
+
+    /// Adds a prefix, tried after those added before.
+    pub fn prefix<T: Into<OsString>>(mut self, prefix: T) -> NameVariants {
+        self.prefixes.push(prefix.into());
+        self
+    }
+
+    /// Adds a suffix, tried after those added before.
+    pub fn suffix<T: Into<OsString>>(mut self, suffix: T) -> NameVariants {
+        self.suffixes.push(suffix.into());
+        self
+    }
+
+    /// The variants of `name`, in order of precedence, without duplicates.
+    pub fn names<T: AsRef<OsStr>>(&self, name: T) -> Vec<OsString> {
+        let empty = [OsString::new()];
+        let prefixes = if self.prefixes.is_empty() {
+            &empty[..]
+        } else {
+            &self.prefixes
+        };
+        let suffixes = if self.suffixes.is_empty() {
+            &empty[..]
+        } else {
+            &self.suffixes
+        };
+
+        let mut names: Vec<OsString> = Vec::new();
+        for prefix in prefixes {
+            for suffix in suffixes {
+                let mut variant = prefix.clone();
+                variant.push(name.as_ref());
+                variant.push(suffix);
+                if !names.contains(&variant) {
+                    names.push(variant);
+                }
+            }
+        }
+        names
+    }
+}
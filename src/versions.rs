@@ -0,0 +1,58 @@
+//! Picking the newest of side-by-side installations kept in directories named after their
+//! versions, such as Android's `build-tools/34.0.0` or the Windows SDK's `bin/10.0.22621.0`.
+
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The subdirectory of `dir` named after the highest version.
+pub(crate) fn newest(dir: &Path) -> Option<PathBuf> {
+    fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| Some((parse(&entry.file_name())?, entry.path())))
+        .filter(|(_, path)| path.is_dir())
+        .max()
+        .map(|(_, path)| path)
+}
+
+/// Parses a version directory name, ignoring suffixes like `-rc1`.
+fn parse(name: &OsStr) -> Option<Vec<u32>> {
+    name.to_str()?
+        .split('-')
+        .next()?
+        .split('.')
+        .map(|part| part.parse().ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(OsStr::new("34.0.0")), Some(vec![34, 0, 0]));
+        assert_eq!(
+            parse(OsStr::new("10.0.22621.0")),
+            Some(vec![10, 0, 22621, 0])
+        );
+        assert_eq!(parse(OsStr::new("35.0.0-rc1")), Some(vec![35, 0, 0]));
+        assert_eq!(parse(OsStr::new("docs")), None);
+        assert_eq!(parse(OsStr::new("")), None);
+    }
+
+    #[test]
+    fn test_newest() {
+        let root = tempfile::tempdir().unwrap();
+        for dir in ["10.0.9.0", "10.0.22621.0", "10.0.19041.0", "arm64", "wdf"] {
+            fs::create_dir(root.path().join(dir)).unwrap();
+        }
+        // Files aren't installations, whatever they're named.
+        fs::write(root.path().join("10.0.99999.0"), "").unwrap();
+
+        assert_eq!(newest(root.path()), Some(root.path().join("10.0.22621.0")));
+        assert_eq!(newest(&root.path().join("wdf")), None);
+        assert_eq!(newest(&root.path().join("missing")), None);
+    }
+}
@@ -0,0 +1,379 @@
+//! Searching a directory tree other than the real filesystem, e.g. on `wasm32-unknown-unknown`,
+//! where there is no filesystem to search.
+
+use crate::checker::{FileInfo, FileKind};
+use crate::win::fold_case;
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// A source of file metadata and directory listings that a search runs against instead of the
+/// real filesystem.
+///
+/// Paths are handed in the way the search assembles them from the path list and the working
+/// directory, without any normalization.
+pub trait Filesystem: Send + Sync {
+    /// Returns the metadata of `path`, with symlinks followed, or `None` if it doesn't exist.
+    fn metadata(&self, path: &Path) -> Option<FileInfo>;
+
+    /// Returns the names of the entries of directory `dir`.
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<OsString>>;
+
+    /// Opens the regular file `path` for reading, for checks that look at what files contain,
+    /// such as [`WhichConfig::verify_signature`](crate::WhichConfig::verify_signature). By
+    /// default files can't be opened, and such checks reject every candidate.
+    fn open(&self, path: &Path) -> io::Result<Box<dyn io::Read>> {
+        let _ = path;
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
+
+impl fmt::Debug for dyn Filesystem + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Filesystem")
+    }
+}
+
+/// An in-memory directory tree, for running searches where there is no real filesystem.
+///
+/// Adding a file adds its parent directories too. Paths are matched case-insensitively on
+/// Windows, like the real filesystem there.
+///
+/// # Example
+///
+/// ```
+/// use which::{FileInfo, FileKind, VirtualFs, WhichConfig};
+///
+/// let mut fs = VirtualFs::new();
+/// fs.add("/usr/bin/rustc", FileInfo::new(FileKind::File));
+///
+/// let path = WhichConfig::new()
+///     .filesystem(fs)
+///     .binary_name("rustc".into())
+///     .custom_path_list("/usr/local/bin:/usr/bin".into())
+///     .path_separator(':')
+///     .system_cwd(false)
+///     .first_result();
+/// # #[cfg(not(windows))]
+/// assert_eq!(path.unwrap(), std::path::Path::new("/usr/bin/rustc"));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct VirtualFs {
+    /// Every entry, keyed by its lookup key, along with the path as it was added.
+    entries: BTreeMap<OsString, (PathBuf, FileInfo)>,
+}
+
+impl VirtualFs {
+    pub fn new() -> VirtualFs {
+        VirtualFs::default()
+    }
+
+    /// Adds `path` with the given metadata, replacing any entry already there, and its parents
+    /// as directories unless they already exist.
+    pub fn add<P: Into<PathBuf>>(&mut self, path: P, info: FileInfo) -> &mut VirtualFs {
+        let path = path.into();
+        for parent in path.ancestors().skip(1) {
+            if parent.as_os_str().is_empty() {
+                break;
+            }
+            self.entries
+                .entry(lookup_key(parent.as_os_str()))
+                .or_insert_with(|| (parent.to_path_buf(), FileInfo::new(FileKind::Dir)));
+        }
+        self.entries
+            .insert(lookup_key(path.as_os_str()), (path, info));
+        self
+    }
+}
+
+impl Filesystem for VirtualFs {
+    fn metadata(&self, path: &Path) -> Option<FileInfo> {
+        self.entries
+            .get(&lookup_key(path.as_os_str()))
+            .map(|(_, info)| info.clone())
+    }
+
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<OsString>> {
+        match self.metadata(dir).map(|info| info.kind()) {
+            Some(FileKind::Dir) => {}
+            Some(_) => return Err(io::Error::other("not a directory")),
+            None => return Err(io::Error::from(io::ErrorKind::NotFound)),
+        }
+
+        let key = lookup_key(dir.as_os_str());
+        Ok(self
+            .entries
+            .values()
+            .filter(|(path, _)| {
+                path.parent()
+                    .is_some_and(|parent| lookup_key(parent.as_os_str()) == key)
+            })
+            .filter_map(|(path, _)| path.file_name().map(OsStr::to_os_string))
+            .collect())
+    }
+}
+
+/// The most symlinks [`RootedFs`] follows for one path, as `MAXSYMLINKS` on Linux.
+const MAX_SYMLINKS: usize = 40;
+
+/// The real filesystem as seen from inside a chroot at `root`: symlinks, absolute ones included,
+/// are resolved within `root` rather than leading outside it.
+pub(crate) struct RootedFs {
+    root: PathBuf,
+}
+
+impl RootedFs {
+    pub fn new(root: PathBuf) -> RootedFs {
+        RootedFs { root }
+    }
+
+    /// Resolves every symlink in `path`, which must be inside the root, the way the kernel
+    /// would inside the chroot. Fails if any component doesn't exist.
+    fn resolve(&self, path: &Path) -> io::Result<PathBuf> {
+        let relative = path
+            .strip_prefix(&self.root)
+            .map_err(|_| io::Error::from(io::ErrorKind::NotFound))?;
+        // Components still to resolve, innermost first.
+        let mut pending: Vec<OsString> = relative
+            .components()
+            .rev()
+            .map(|c| c.as_os_str().to_os_string())
+            .collect();
+        let mut resolved = self.root.clone();
+        let mut depth = 0;
+        let mut links = 0;
+
+        while let Some(name) = pending.pop() {
+            match Path::new(&name).components().next() {
+                Some(Component::Normal(_)) => {}
+                Some(Component::ParentDir) => {
+                    if depth > 0 {
+                        resolved.pop();
+                        depth -= 1;
+                    }
+                    continue;
+                }
+                // The root, a prefix or `.`, which all stay where they are.
+                _ => continue,
+            }
+
+            let next = resolved.join(&name);
+            if !fs::symlink_metadata(&next)?.file_type().is_symlink() {
+                resolved = next;
+                depth += 1;
+                continue;
+            }
+
+            links += 1;
+            if links > MAX_SYMLINKS {
+                return Err(io::Error::other("too many levels of symbolic links"));
+            }
+            let target = fs::read_link(&next)?;
+            if target.has_root() {
+                resolved = self.root.clone();
+                depth = 0;
+            }
+            pending.extend(
+                target
+                    .components()
+                    .rev()
+                    .map(|c| c.as_os_str().to_os_string()),
+            );
+        }
+
+        Ok(resolved)
+    }
+}
+
+impl Filesystem for RootedFs {
+    fn metadata(&self, path: &Path) -> Option<FileInfo> {
+        let resolved = self.resolve(path).ok()?;
+        fs::symlink_metadata(resolved)
+            .ok()
+            .map(|metadata| FileInfo::from_metadata(&metadata))
+    }
+
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<OsString>> {
+        fs::read_dir(self.resolve(dir)?)?
+            .map(|entry| entry.map(|entry| entry.file_name()))
+            .collect()
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn io::Read>> {
+        Ok(Box::new(fs::File::open(self.resolve(path)?)?))
+    }
+}
+
+/// Another filesystem, or the real one, with the last component of paths matched
+/// case-insensitively as on Windows.
+pub(crate) struct CaseInsensitiveFs {
+    inner: Option<Arc<dyn Filesystem>>,
+    /// The entries of each directory looked into, by their case-folded names.
+    folded: Mutex<HashMap<PathBuf, Arc<HashMap<OsString, OsString>>>>,
+}
+
+impl CaseInsensitiveFs {
+    pub fn new(inner: Option<Arc<dyn Filesystem>>) -> CaseInsensitiveFs {
+        CaseInsensitiveFs {
+            inner,
+            folded: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn exact_metadata(&self, path: &Path) -> Option<FileInfo> {
+        match &self.inner {
+            Some(inner) => inner.metadata(path),
+            None => fs::metadata(path)
+                .ok()
+                .map(|metadata| FileInfo::from_metadata(&metadata)),
+        }
+    }
+
+    /// The entries of `dir` by their case-folded names, listed once, or `None` if it can't be
+    /// listed.
+    fn folded(&self, dir: &Path) -> Option<Arc<HashMap<OsString, OsString>>> {
+        if let Some(folded) = self.folded.lock().unwrap().get(dir) {
+            return Some(folded.clone());
+        }
+        let mut folded = HashMap::new();
+        for name in self.read_dir(dir).ok()? {
+            folded.entry(fold_case(&name)).or_insert(name);
+        }
+        let folded = Arc::new(folded);
+        self.folded
+            .lock()
+            .unwrap()
+            .insert(dir.to_path_buf(), folded.clone());
+        Some(folded)
+    }
+
+    /// `path` with its last component spelled as the entry it matches case-insensitively, if
+    /// it isn't spelled as one already.
+    fn respelled(&self, path: &Path) -> Option<PathBuf> {
+        let file_name = path.file_name()?;
+        let name = self
+            .folded(path.parent()?)?
+            .get(&fold_case(file_name))?
+            .clone();
+        (name != file_name).then(|| path.with_file_name(name))
+    }
+}
+
+impl Filesystem for CaseInsensitiveFs {
+    fn metadata(&self, path: &Path) -> Option<FileInfo> {
+        self.exact_metadata(path)
+            .or_else(|| self.exact_metadata(&self.respelled(path)?))
+    }
+
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<OsString>> {
+        match &self.inner {
+            Some(inner) => inner.read_dir(dir),
+            None => fs::read_dir(dir)?
+                .map(|entry| entry.map(|entry| entry.file_name()))
+                .collect(),
+        }
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn io::Read>> {
+        let path = match self.exact_metadata(path) {
+            Some(_) => path.to_path_buf(),
+            None => self.respelled(path).unwrap_or_else(|| path.to_path_buf()),
+        };
+        match &self.inner {
+            Some(inner) => inner.open(&path),
+            None => Ok(Box::new(fs::File::open(path)?)),
+        }
+    }
+}
+
+/// `path` with its last component spelled as the entry of `filesystem` it matches
+/// case-insensitively, preferring an exact match, or as it is if there's none.
+pub(crate) fn respell(filesystem: &dyn Filesystem, mut path: PathBuf) -> PathBuf {
+    let (parent, file_name) = match (path.parent(), path.file_name()) {
+        (Some(parent), Some(file_name)) => (parent, file_name),
+        _ => return path,
+    };
+    let names = match filesystem.read_dir(parent) {
+        Ok(names) => names,
+        Err(_) => return path,
+    };
+    if names.iter().any(|name| name == file_name) {
+        return path;
+    }
+    let folded = fold_case(file_name);
+    if let Some(name) = names.into_iter().find(|name| fold_case(name) == folded) {
+        path.set_file_name(name);
+    }
+    path
+}
+
+#[cfg(windows)]
+fn lookup_key(path: &OsStr) -> OsString {
+    fold_case(path)
+}
+
+#[cfg(not(windows))]
+fn lookup_key(path: &OsStr) -> OsString {
+    path.to_os_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_virtual_fs_read_dir() {
+        let mut fs = VirtualFs::new();
+        fs.add("/a/bin/one", FileInfo::new(FileKind::File))
+            .add("/a/bin/two", FileInfo::new(FileKind::File))
+            .add("/a/lib/three", FileInfo::new(FileKind::File));
+
+        let mut names = fs.read_dir(Path::new("/a/bin")).unwrap();
+        names.sort();
+        assert_eq!(names, ["one", "two"]);
+        assert_eq!(fs.read_dir(Path::new("/a")).unwrap(), ["bin", "lib"]);
+        assert_eq!(
+            fs.metadata(Path::new("/a/lib")).map(|info| info.kind()),
+            Some(FileKind::Dir)
+        );
+        assert!(fs.read_dir(Path::new("/a/bin/one")).is_err());
+        assert!(fs.read_dir(Path::new("/missing")).is_err());
+    }
+
+    /// Counts the listings it's asked for.
+    struct Counting(VirtualFs, std::sync::atomic::AtomicUsize);
+
+    impl Filesystem for Counting {
+        fn metadata(&self, path: &Path) -> Option<FileInfo> {
+            self.0.metadata(path)
+        }
+
+        fn read_dir(&self, dir: &Path) -> io::Result<Vec<OsString>> {
+            self.1.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            self.0.read_dir(dir)
+        }
+    }
+
+    #[test]
+    fn test_case_insensitive_fs() {
+        let mut fs = VirtualFs::new();
+        fs.add("/bin/Été", FileInfo::new(FileKind::File))
+            .add("/bin/TOOL", FileInfo::new(FileKind::File));
+        let counting = Arc::new(Counting(fs, Default::default()));
+        let fs = CaseInsensitiveFs::new(Some(counting.clone()));
+
+        assert!(fs.metadata(Path::new("/bin/été")).is_some());
+        assert!(fs.metadata(Path::new("/bin/tool")).is_some());
+        assert!(fs.metadata(Path::new("/bin/missing")).is_none());
+        assert!(counting.1.load(std::sync::atomic::Ordering::Relaxed) <= 1);
+
+        assert_eq!(
+            respell(&fs, PathBuf::from("/bin/ÉTÉ")),
+            Path::new("/bin/Été")
+        );
+    }
+}
@@ -0,0 +1,83 @@
+use crate::environment::Environment;
+use crate::split::{SplitPaths, PATH_SEPARATOR};
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+/// Directories tools are conventionally installed to, but which aren't always on `PATH`, e.g. for
+/// GUI apps and daemons that don't inherit a user's shell environment.
+///
+/// On Unix, these are `~/.cargo/bin`, `~/.local/bin`, `/usr/local/bin` and, on macOS,
+/// `/opt/homebrew/bin`. On Windows, they are `%USERPROFILE%\.cargo\bin` and
+/// `%LOCALAPPDATA%\Programs`. Directories depending on an unset variable are left out, but
+/// whether the others exist isn't checked.
+pub fn well_known_dirs() -> Vec<PathBuf> {
+    well_known_dirs_in(&Environment::Process)
+}
+
+/// Like [`well_known_dirs`], with the variables they depend on read from `env`.
+pub(crate) fn well_known_dirs_in(env: &Environment) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    #[cfg(windows)]
+    {
+        if let Some(profile) = env.var_os("USERPROFILE") {
+            dirs.push(PathBuf::from(profile).join(".cargo").join("bin"));
+        }
+        if let Some(local) = env.var_os("LOCALAPPDATA") {
+            dirs.push(PathBuf::from(local).join("Programs"));
+        }
+    }
+    #[cfg(not(windows))]
+    {
+        if let Some(home) = env.var_os("HOME").filter(|home| !home.is_empty()) {
+            let home = PathBuf::from(home);
+            dirs.push(home.join(".cargo").join("bin"));
+            dirs.push(home.join(".local").join("bin"));
+        }
+        dirs.push(PathBuf::from("/usr/local/bin"));
+        #[cfg(target_os = "macos")]
+        dirs.push(PathBuf::from("/opt/homebrew/bin"));
+    }
+    dirs
+}
+
+/// Appends the entries of `dirs` that `paths` doesn't have already, in order.
+pub(crate) fn append_missing(
+    paths: Option<OsString>,
+    separator: Option<u8>,
+    dirs: Vec<PathBuf>,
+) -> Option<OsString> {
+    let separator = separator.unwrap_or(PATH_SEPARATOR);
+    let existing: Vec<PathBuf> = match &paths {
+        Some(paths) => SplitPaths::with_separator(paths, separator, separator == b';').collect(),
+        None => Vec::new(),
+    };
+    let mut joined = paths.unwrap_or_default();
+    for dir in dirs {
+        if existing.contains(&dir) {
+            continue;
+        }
+        if !joined.is_empty() {
+            joined.push(String::from(char::from(separator)));
+        }
+        joined.push(dir);
+    }
+    Some(joined)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_append_missing() {
+        let dirs = || vec![PathBuf::from("/usr/local/bin"), PathBuf::from("/opt/bin")];
+        assert_eq!(
+            append_missing(Some("/usr/local/bin:/usr/bin".into()), Some(b':'), dirs()),
+            Some("/usr/local/bin:/usr/bin:/opt/bin".into())
+        );
+        assert_eq!(
+            append_missing(None, Some(b':'), dirs()),
+            Some("/usr/local/bin:/opt/bin".into())
+        );
+    }
+}
@@ -0,0 +1,219 @@
+//! The rules Windows uses to decide which files are executables, for filtering file listings
+//! exactly the way searches on Windows do.
+//!
+//! These are available on every platform, e.g. for inspecting a Windows system from elsewhere,
+//! but only searches on Windows apply them.
+
+use std::ffi::{OsStr, OsString};
+use std::path::{Component, Path, PathBuf};
+
+/// Check if given path has extension which in the given vector.
+///
+/// Extensions are compared case-insensitively, with or without their leading dot, as in
+/// `[".COM", ".EXE"]`.
+pub fn has_executable_extension<T: AsRef<Path>, S: AsRef<str>>(path: T, pathext: &[S]) -> bool {
+    let ext = path.as_ref().extension().and_then(|e| e.to_str());
+    match ext {
+        Some(ext) => pathext.iter().any(|e| {
+            let e = e.as_ref();
+            ext.eq_ignore_ascii_case(e.strip_prefix('.').unwrap_or(e))
+        }),
+        _ => false,
+    }
+}
+
+/// Splits a `%PATHEXT%` value such as `.COM;.EXE;.BAT` into its extensions, dots included.
+/// Entries that don't start with a dot are invalid and skipped.
+pub fn parse_pathext(pathext: &str) -> Vec<String> {
+    pathext
+        .split(';')
+        .filter(|s| s.as_bytes().first() == Some(&b'.'))
+        .map(str::to_owned)
+        .collect()
+}
+
+/// The extensions listed by the current process's `%PATHEXT%`, or none if it's unset or not
+/// valid Unicode.
+pub fn path_extensions() -> Vec<String> {
+    crate::environment::var_os("PATHEXT")
+        .as_deref()
+        .and_then(OsStr::to_str)
+        .map(parse_pathext)
+        .unwrap_or_default()
+}
+
+/// Reports whether `path` would be considered an executable on Windows, i.e. whether its
+/// extension is one of the current process's `%PATHEXT%`. The file itself isn't looked at.
+pub fn is_executable_name<T: AsRef<Path>>(path: T) -> bool {
+    has_executable_extension(path, &path_extensions())
+}
+
+/// Reports whether `path` names one of the DOS devices, such as `CON`, `NUL` or `COM1`, which
+/// Windows resolves to the device rather than to a file in any directory, whatever extension
+/// follows.
+pub fn is_reserved_name<T: AsRef<Path>>(path: T) -> bool {
+    let name = match path.as_ref().file_name().and_then(OsStr::to_str) {
+        Some(name) => name,
+        None => return false,
+    };
+    // Everything from the first dot on is ignored, and so are spaces before it.
+    let base = name.split('.').next().unwrap_or(name).trim_end_matches(' ');
+    let upper = base.to_ascii_uppercase();
+    match upper.as_str() {
+        "CON" | "PRN" | "AUX" | "NUL" | "CONIN$" | "CONOUT$" => true,
+        _ => upper
+            .strip_prefix("COM")
+            .or_else(|| upper.strip_prefix("LPT"))
+            .is_some_and(|digit| {
+                let mut chars = digit.chars();
+                matches!(
+                    chars.next(),
+                    Some('1'..='9' | '\u{b9}' | '\u{b2}' | '\u{b3}')
+                ) && chars.next().is_none()
+            }),
+    }
+}
+
+/// Strips the trailing dots and spaces Win32 ignores from every component of `path`, so that
+/// joining onto the result yields the path Windows would actually open. Components made of
+/// dots only, such as `..`, and verbatim paths, as in `\\?\C:\dir.`, are kept as they are.
+pub fn trim_trailing_dots_and_spaces(path: &Path) -> PathBuf {
+    // Prefixes are only parsed on Windows, so look at the text.
+    if path.to_str().is_some_and(|p| p.starts_with(r"\\?\")) {
+        return path.to_path_buf();
+    }
+    let mut trimmed = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => match name.to_str() {
+                Some(name) if !name.chars().all(|c| c == '.') => {
+                    let name = name.trim_end_matches(['.', ' ']);
+                    trimmed.push(if name.is_empty() { "." } else { name });
+                }
+                _ => trimmed.push(name),
+            },
+            component => trimmed.push(component.as_os_str()),
+        }
+    }
+    trimmed
+}
+
+/// `name` with its case folded the way NTFS compares names, so that two names are the same to
+/// the filesystem exactly when their folded forms are equal, e.g. to key a directory listing.
+///
+/// NTFS uppercases names with a table of one UTF-16 unit to another, so characters are only
+/// folded if their uppercase is a single one within the Basic Multilingual Plane: `ß` stays as it
+/// is rather than becoming `SS`. Unlike an ASCII-only comparison, `é` and `É` are the same.
+pub(crate) fn fold_case(name: &OsStr) -> OsString {
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::{OsStrExt, OsStringExt};
+
+        let wide: Vec<u16> = name.encode_wide().collect();
+        let mut folded = Vec::with_capacity(wide.len());
+        for unit in char::decode_utf16(wide.iter().copied()) {
+            match unit {
+                Ok(c) => {
+                    let mut buf = [0; 2];
+                    folded.extend_from_slice(upcase(c).encode_utf16(&mut buf));
+                }
+                // Unpaired surrogates are names' own, and have no case.
+                Err(unpaired) => folded.push(unpaired.unpaired_surrogate()),
+            }
+        }
+        OsString::from_wide(&folded)
+    }
+    #[cfg(not(windows))]
+    match name.to_str() {
+        Some(name) => name.chars().map(upcase).collect::<String>().into(),
+        None => name.to_os_string(),
+    }
+}
+
+/// `c` uppercased the way NTFS's upcase table does, see [`fold_case`].
+fn upcase(c: char) -> char {
+    if u32::from(c) > 0xffff {
+        return c;
+    }
+    let mut upper = c.to_uppercase();
+    match (upper.next(), upper.next()) {
+        (Some(u), None) if u32::from(u) <= 0xffff => u,
+        _ => c,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_extension_in_extension_vector() {
+        // Case insensitive
+        assert!(has_executable_extension(
+            PathBuf::from("foo.exe"),
+            &[".COM", ".EXE", ".CMD"]
+        ));
+
+        assert!(has_executable_extension(
+            PathBuf::from("foo.CMD"),
+            &[".COM", ".EXE", ".CMD"]
+        ));
+    }
+
+    #[test]
+    fn test_parse_pathext() {
+        assert_eq!(
+            parse_pathext(".COM;.EXE;;BAT;.CMD"),
+            [".COM", ".EXE", ".CMD"]
+        );
+        assert!(has_executable_extension("foo.exe", &["exe"]));
+    }
+
+    #[test]
+    fn test_extension_not_in_extension_vector() {
+        assert!(!has_executable_extension(
+            PathBuf::from("foo.bar"),
+            &[".COM", ".EXE", ".CMD"]
+        ));
+    }
+
+    #[test]
+    fn test_fold_case() {
+        let fold = |name: &str| fold_case(OsStr::new(name));
+        assert_eq!(fold("Tool.Exe"), fold("TOOL.exe"));
+        assert_eq!(fold("café"), fold("CAFÉ"));
+        assert_eq!(fold("Ωmega"), fold("ωMEGA"));
+        assert_ne!(fold("straße"), fold("STRASSE"));
+        assert_ne!(fold("tool"), fold("tool2"));
+    }
+
+    #[test]
+    fn test_is_reserved_name() {
+        for name in [
+            "CON",
+            "nul",
+            "Aux.exe",
+            "com1.tar.gz",
+            "LPT9",
+            "PRN .bat",
+            "com\u{b9}",
+        ] {
+            assert!(is_reserved_name(name), "{}", name);
+        }
+        for name in [
+            "CONSOLE", "NULL.exe", "COM0", "COM10", "LPT", "PRNT", "bin.CON",
+        ] {
+            assert!(!is_reserved_name(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_trim_trailing_dots_and_spaces() {
+        let trim = |p: &str| trim_trailing_dots_and_spaces(Path::new(p));
+        assert_eq!(trim("tools. /bin ./rg."), Path::new("tools/bin/rg"));
+        assert_eq!(trim("../a/./b"), Path::new("../a/./b"));
+        assert_eq!(trim("rg.exe"), Path::new("rg.exe"));
+        assert_eq!(trim(r"\\?\C:\tools."), Path::new(r"\\?\C:\tools."));
+    }
+}
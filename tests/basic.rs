@@ -6,6 +6,8 @@ use std::ffi::{OsStr, OsString};
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::{env, vec};
 use tempfile::TempDir;
 
@@ -44,7 +46,7 @@ fn touch(dir: &Path, path: &str, extension: &str) -> io::Result<PathBuf> {
     fs::File::create(&b).and_then(|_f| b.canonicalize())
 }
 
-#[cfg(windows)]
+#[cfg(not(unix))]
 fn mk_bin(dir: &Path, path: &str, extension: &str) -> io::Result<PathBuf> {
     touch(dir, path, extension)
 }
@@ -101,7 +103,7 @@ fn _which<T: AsRef<OsStr>>(f: &TestFixture, path: T) -> which::Result<which::Can
 fn _which_all<'a, T: AsRef<OsStr> + 'a>(
     f: &'a TestFixture,
     path: T,
-) -> which::Result<impl Iterator<Item = which::Result<which::CanonicalPath>> + '_> {
+) -> which::Result<impl Iterator<Item = which::Result<which::CanonicalPath>> + 'a> {
     which::CanonicalPath::all_in(path, Some(f.paths.clone()), f.tempdir.path())
 }
 
@@ -182,6 +184,34 @@ fn test_which_re_accepts_owned_and_borrow() {
         .for_each(drop);
 }
 
+#[test]
+#[cfg(all(unix, feature = "regex"))]
+fn test_which_re_bytes_in_non_utf8() {
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let f = TestFixture::new();
+    let name = OsStr::from_bytes(b"r\xe9sum\xe9");
+    let bin = f.tempdir.path().join("b").join(name);
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o755)
+        .open(&bin)
+        .unwrap();
+
+    let re = regex::bytes::Regex::new(r"(?-u)^r\xe9sum\xe9$").unwrap();
+    let result: Vec<PathBuf> = which::which_re_bytes_in(re, Some(&f.paths))
+        .unwrap()
+        .collect();
+    assert_eq!(result, vec![bin]);
+
+    // The text regex can't see the name at all.
+    let re = Regex::new(r"^r.sum.$").unwrap();
+    let result: Vec<PathBuf> = which::which_re_in(re, Some(&f.paths)).unwrap().collect();
+    assert_eq!(result, Vec::<PathBuf>::new());
+}
+
 #[test]
 #[cfg(unix)]
 fn test_which_extension() {
@@ -248,157 +278,2896 @@ fn test_which_all() {
 
 #[test]
 #[cfg(unix)]
-fn test_which_absolute() {
+fn test_which_last() {
     let f = TestFixture::new();
-    assert_eq!(
-        _which(&f, &f.bins[3]).unwrap(),
-        f.bins[3].canonicalize().unwrap()
-    );
+    let last = which::WhichConfig::new()
+        .custom_path_list(f.paths.clone())
+        .binary_name(BIN_NAME.into())
+        .last_result()
+        .unwrap();
+    assert_eq!(last.canonicalize().unwrap(), f.bins[6]);
+
+    let missing = which::WhichConfig::new()
+        .custom_path_list(f.paths.clone())
+        .binary_name("missing".into())
+        .last_result();
+    assert_eq!(missing, Err(which::Error::CannotFindBinaryPath));
 }
 
 #[test]
-#[cfg(windows)]
-fn test_which_absolute() {
+fn test_which_limit() {
+    struct CountChecks(Arc<AtomicUsize>);
+
+    impl which::Checker for CountChecks {
+        fn is_valid(&self, _candidate: &which::Candidate<'_>) -> bool {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            true
+        }
+    }
+
     let f = TestFixture::new();
-    assert_eq!(
-        _which(&f, &f.bins[4]).unwrap(),
-        f.bins[4].canonicalize().unwrap()
-    );
+    let checks = Arc::new(AtomicUsize::new(0));
+    let found: Vec<_> = which::WhichConfig::new()
+        .custom_path_list(f.paths.clone())
+        .binary_name(BIN_NAME.into())
+        .checker(CountChecks(checks.clone()))
+        .limit(2)
+        .all_results()
+        .unwrap()
+        .collect();
+    assert_eq!(found.len(), 2);
+    assert_eq!(checks.load(Ordering::SeqCst), 2);
 }
 
 #[test]
-#[cfg(windows)]
-fn test_which_absolute_path_case() {
-    // Test that an absolute path with an uppercase extension
-    // is accepted.
+fn test_which_origin() {
     let f = TestFixture::new();
-    let p = &f.bins[4];
-    assert_eq!(_which(&f, p).unwrap(), f.bins[4].canonicalize().unwrap());
+    let origins: Vec<_> = which::WhichConfig::new()
+        .custom_path_list(f.paths.clone())
+        .binary_name(BIN_NAME.into())
+        .all_results_with_origin()
+        .unwrap()
+        .map(|(path, origin)| (path.canonicalize().unwrap(), origin))
+        .collect();
+    let per_dir = origins.len() / SUBDIRS.len();
+    for (i, (path, origin)) in origins.iter().enumerate() {
+        assert_eq!(*origin, which::Origin::PathEntry(i / per_dir));
+        assert!(path.starts_with(f.tempdir.path().join(SUBDIRS[i / per_dir])));
+    }
+
+    let (path, origin) = which::WhichConfig::new()
+        .custom_path_list(f.paths.clone())
+        .custom_cwd(f.tempdir.path().to_path_buf())
+        .binary_name(Path::new("b").join(BIN_NAME).into())
+        .all_results_with_origin()
+        .unwrap()
+        .next()
+        .unwrap();
+    assert!(path.starts_with(f.tempdir.path().join("b")));
+    assert_eq!(origin, which::Origin::Cwd);
+    assert!(origin.is_cwd_relative());
+
+    let (_, origin) = which::WhichConfig::new()
+        .custom_path_list(f.paths.clone())
+        .custom_cwd(f.tempdir.path().to_path_buf())
+        .binary_name(path.into())
+        .all_results_with_origin()
+        .unwrap()
+        .next()
+        .unwrap();
+    assert_eq!(origin, which::Origin::Absolute);
+    assert!(!origin.is_cwd_relative());
 }
 
 #[test]
 #[cfg(unix)]
-fn test_which_absolute_extension() {
+fn test_which_all_results_with_errors() {
+    // Symlinks to themselves can't be looked at, even by root.
     let f = TestFixture::new();
-    // Don't append EXE_EXTENSION here.
-    let b = f.bins[3].parent().unwrap().join(BIN_NAME);
-    assert_eq!(_which(&f, b).unwrap(), f.bins[3].canonicalize().unwrap());
-}
+    let looped = f.tempdir.path().join("a/tool");
+    std::os::unix::fs::symlink("tool", &looped).unwrap();
+    let tool = f.mk_bin("b/tool", "").unwrap();
+    let looped_dir = f.tempdir.path().join("looped");
+    std::os::unix::fs::symlink("looped", &looped_dir).unwrap();
+    let mut dirs: Vec<_> = env::split_paths(&f.paths).collect();
+    dirs.push(looped_dir.clone());
+    let paths = env::join_paths(dirs).unwrap();
 
-#[test]
-#[cfg(windows)]
-fn test_which_absolute_extension() {
-    let f = TestFixture::new();
-    // Don't append EXE_EXTENSION here.
-    let b = f.bins[4].parent().unwrap().join(BIN_NAME);
-    assert_eq!(_which(&f, b).unwrap(), f.bins[4].canonicalize().unwrap());
+    let is_loop = |result: &Result<PathBuf, which::CandidateError>, path: &Path| match result {
+        Err(error) => error.path == path && error.error.raw_os_error() == Some(libc::ELOOP),
+        Ok(_) => false,
+    };
+
+    let results: Vec<_> = which::WhichConfig::new()
+        .custom_path_list(paths.clone())
+        .binary_name("tool".into())
+        .all_results_with_errors()
+        .unwrap()
+        .collect();
+    assert_eq!(results.len(), 3, "{:?}", results);
+    assert!(is_loop(&results[0], &looped));
+    assert_eq!(results[1].as_ref().unwrap(), &tool);
+    assert!(is_loop(&results[2], &looped_dir.join("tool")));
+
+    // Skipped otherwise.
+    let results: Vec<_> = which::WhichConfig::new()
+        .custom_path_list(paths.clone())
+        .binary_name("tool".into())
+        .all_results()
+        .unwrap()
+        .collect();
+    assert_eq!(results, vec![tool.clone()]);
+
+    #[cfg(feature = "regex")]
+    {
+        let results: Vec<_> = which::WhichConfig::new()
+            .custom_path_list(paths)
+            .system_cwd(false)
+            .regex(Regex::new("^tool$").unwrap())
+            .all_results_with_errors()
+            .unwrap()
+            .collect();
+        assert_eq!(results.len(), 3, "{:?}", results);
+        assert!(is_loop(&results[0], &looped));
+        assert_eq!(results[1].as_ref().unwrap().canonicalize().unwrap(), tool);
+        assert!(is_loop(&results[2], &looped_dir));
+    }
 }
 
 #[test]
 #[cfg(unix)]
-fn test_which_relative() {
+fn test_unreadable_policy() {
+    use std::sync::Mutex;
+    use which::UnreadablePolicy;
+
     let f = TestFixture::new();
+    let looped = f.tempdir.path().join("a/tool");
+    std::os::unix::fs::symlink("tool", &looped).unwrap();
+    let tool = f.mk_bin("b/tool", "").unwrap();
+    let config = |policy| {
+        which::WhichConfig::new()
+            .custom_path_list(f.paths.clone())
+            .binary_name("tool".into())
+            .unreadable_policy(policy)
+    };
+
+    assert_eq!(config(UnreadablePolicy::Skip).first_result().unwrap(), tool);
+
+    let warnings = Arc::new(Mutex::new(Vec::new()));
+    let collected = warnings.clone();
+    let warn = UnreadablePolicy::Warn(Arc::new(move |error: &which::CandidateError| {
+        collected.lock().unwrap().push(error.path.clone());
+    }));
     assert_eq!(
-        _which(&f, "b/bin").unwrap(),
-        f.bins[3].canonicalize().unwrap()
+        config(warn).all_results().unwrap().collect::<Vec<_>>(),
+        vec![tool.clone()]
+    );
+    assert_eq!(*warnings.lock().unwrap(), vec![looped.clone()]);
+
+    match config(UnreadablePolicy::Fail).first_result() {
+        Err(which::Error::Unreadable(error)) => assert_eq!(error.path, looped),
+        result => panic!("{:?}", result),
+    }
+    assert!(config(UnreadablePolicy::Fail).last_result().is_err());
+    assert_eq!(
+        config(UnreadablePolicy::Fail)
+            .all_results()
+            .unwrap()
+            .count(),
+        0
+    );
+    let results: Vec<_> = config(UnreadablePolicy::Fail)
+        .all_results_with_errors()
+        .unwrap()
+        .collect();
+    match &results[..] {
+        [Err(error)] => assert_eq!(error.path, looped),
+        results => panic!("{:?}", results),
+    }
+
+    // A result before the path is still found.
+    let paths = env::join_paths([f.tempdir.path().join("b"), f.tempdir.path().join("a")]).unwrap();
+    assert_eq!(
+        config(UnreadablePolicy::Fail)
+            .custom_path_list(paths)
+            .first_result()
+            .unwrap(),
+        tool
     );
 }
 
 #[test]
-#[cfg(windows)]
-fn test_which_relative() {
+fn test_which_directory() {
     let f = TestFixture::new();
+    fs::create_dir(f.tempdir.path().join("b").join("toolchain")).unwrap();
+    let found = which::WhichConfig::new()
+        .custom_path_list(f.paths.clone())
+        .binary_name("toolchain".into())
+        .directory(true)
+        .first_result()
+        .unwrap();
     assert_eq!(
-        _which(&f, "b/bin").unwrap(),
-        f.bins[4].canonicalize().unwrap()
+        found.canonicalize().unwrap(),
+        f.tempdir
+            .path()
+            .join("b")
+            .join("toolchain")
+            .canonicalize()
+            .unwrap()
     );
+
+    // Directories aren't executables, and executables aren't directories.
+    assert!(which::which_in("toolchain", Some(&f.paths), f.tempdir.path()).is_err());
+    assert!(which::WhichConfig::new()
+        .custom_path_list(f.paths.clone())
+        .binary_name(BIN_NAME.into())
+        .directory(true)
+        .first_result()
+        .is_err());
 }
 
 #[test]
-#[cfg(unix)]
-fn test_which_relative_extension() {
-    // test_which_relative tests a relative path without an extension,
-    // so test a relative path with an extension here.
+fn test_which_depth() {
     let f = TestFixture::new();
-    let b = Path::new("b/bin").with_extension(env::consts::EXE_EXTENSION);
-    assert_eq!(_which(&f, b).unwrap(), f.bins[3].canonicalize().unwrap());
+    fs::create_dir_all(f.tempdir.path().join("a").join("sdk").join("bin")).unwrap();
+    let nested = f
+        .mk_bin("a/sdk/bin/nested", env::consts::EXE_EXTENSION)
+        .unwrap();
+
+    let search = |depth| {
+        which::WhichConfig::new()
+            .custom_path_list(f.paths.clone())
+            .binary_name("nested".into())
+            .depth(depth)
+            .all_results_with_origin()
+            .unwrap()
+            .map(|(path, origin)| (path.canonicalize().unwrap(), origin))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(search(0), vec![]);
+    assert_eq!(search(1), vec![]);
+    assert_eq!(search(2), vec![(nested, which::Origin::PathEntry(0))]);
 }
 
 #[test]
-#[cfg(windows)]
-fn test_which_relative_extension() {
-    // test_which_relative tests a relative path without an extension,
-    // so test a relative path with an extension here.
+fn test_find_file_in_var() {
     let f = TestFixture::new();
-    let b = Path::new("b/bin").with_extension("cmd");
-    assert_eq!(_which(&f, b).unwrap(), f.bins[5].canonicalize().unwrap());
+    let page = f.touch("b/bin", "1").unwrap();
+
+    let found: Vec<_> = which::find_file_in("bin.1", Some(&f.paths), |candidate| {
+        candidate.info().is_some()
+    })
+    .unwrap()
+    .map(|p| p.canonicalize().unwrap())
+    .collect();
+    assert_eq!(found, vec![page.clone()]);
+
+    #[cfg(feature = "test-util")]
+    {
+        let env =
+            which::EnvSnapshot::new(None, None, None).var("WHICH_TEST_MANPATH", Some(&f.paths));
+        let found: Vec<_> = which::test_util::with_env(env, || {
+            which::find_file_in_var("bin.1", "WHICH_TEST_MANPATH", |candidate| {
+                candidate.info().is_some()
+            })
+            .unwrap()
+            .collect()
+        });
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].canonicalize().unwrap(), page);
+    }
+
+    let missing = which::find_file_in_var("bin.1", "WHICH_TEST_UNSET", |_| true);
+    assert!(matches!(missing, Err(which::Error::CannotFindBinaryPath)));
 }
 
 #[test]
-#[cfg(windows)]
-fn test_which_relative_extension_case() {
-    // Test that a relative path with an uppercase extension
-    // is accepted.
+fn test_lib_find_in() {
     let f = TestFixture::new();
-    let b = Path::new("b/bin").with_extension("EXE");
-    assert_eq!(_which(&f, b).unwrap(), f.bins[4].canonicalize().unwrap());
+    let lib_name = format!("{}foo", env::consts::DLL_PREFIX);
+    let lib = f
+        .touch(&format!("c/{}", lib_name), &env::consts::DLL_SUFFIX[1..])
+        .unwrap();
+
+    let found = which::lib::find_in("foo", Some(&f.paths)).unwrap();
+    assert_eq!(found.canonicalize().unwrap(), lib);
+    let found = which::lib::find_in(lib.file_name().unwrap(), Some(&f.paths)).unwrap();
+    assert_eq!(found.canonicalize().unwrap(), lib);
+    assert!(which::lib::find_in("bar", Some(&f.paths)).is_err());
 }
 
 #[test]
-#[cfg(unix)]
-fn test_which_relative_leading_dot() {
+fn test_which_path_separator() {
+    let f = TestFixture::new();
+    let paths: Vec<_> = env::split_paths(&f.paths)
+        .map(|p| p.into_os_string().into_string().unwrap())
+        .collect();
+    let found = which::WhichConfig::new()
+        .custom_path_list(format!("|{}", paths[1..].join("|")).into())
+        .path_separator('|')
+        .binary_name(BIN_NAME.into())
+        .first_result()
+        .unwrap();
+
+    #[cfg(not(windows))]
+    let bin = f.bins[3].clone();
+    #[cfg(windows)]
+    let bin = f.bins[4].clone();
+    assert_eq!(found.canonicalize().unwrap(), bin);
+}
+
+#[test]
+fn test_which_virtual_fs() {
+    use which::{FileInfo, FileKind, VirtualFs};
+
+    let root = Path::new("/virtual");
+    let exe = format!("{}{}", BIN_NAME, env::consts::EXE_SUFFIX);
+    let mut fs = VirtualFs::new();
+    fs.add(root.join("a").join(&exe), FileInfo::new(FileKind::Dir))
+        .add(root.join("b").join(&exe), FileInfo::new(FileKind::File))
+        .add(root.join("c").join(&exe), FileInfo::new(FileKind::File));
+    #[cfg(unix)]
+    fs.add(
+        root.join("b").join(&exe),
+        FileInfo::new(FileKind::File).with_mode(0o644),
+    );
+
+    let paths = env::join_paths(["a", "b", "c"].iter().map(|d| root.join(d))).unwrap();
+    let found: Vec<_> = which::WhichConfig::new()
+        .filesystem(fs)
+        .custom_path_list(paths)
+        .system_cwd(false)
+        .binary_name(BIN_NAME.into())
+        .all_results()
+        .unwrap()
+        .collect();
+
+    // Directories never match, and neither do files without execute permissions on Unix.
+    #[cfg(unix)]
+    let expected = vec![root.join("c").join(&exe)];
+    #[cfg(not(unix))]
+    let expected = vec![root.join("b").join(&exe), root.join("c").join(&exe)];
+    assert_eq!(found, expected);
+}
+
+#[test]
+fn test_which_system_fallback() {
+    let found = which::WhichConfig::new()
+        .custom_path_list(OsString::new())
+        .system_fallback(true)
+        .binary_name("sh".into())
+        .first_result();
+
+    #[cfg(target_os = "android")]
+    assert_eq!(found.unwrap(), PathBuf::from("/system/bin/sh"));
+    #[cfg(not(target_os = "android"))]
+    assert_eq!(found, Err(which::Error::CannotFindBinaryPath));
+}
+
+#[test]
+fn test_which_observer() {
+    let f = TestFixture::new();
+    let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let observed = Arc::clone(&seen);
+    let found: Vec<_> = which::WhichConfig::new()
+        .custom_path_list(f.paths.clone())
+        .binary_name(BIN_NAME.into())
+        .observer(move |path, valid| observed.lock().unwrap().push((path.to_path_buf(), valid)))
+        .all_results()
+        .unwrap()
+        .collect();
+
+    let seen = seen.lock().unwrap();
+    let accepted: Vec<_> = seen
+        .iter()
+        .filter(|(_, valid)| *valid)
+        .map(|(path, _)| path.clone())
+        .collect();
+    assert_eq!(accepted, found);
+    assert!(seen.len() >= found.len());
+}
+
+#[test]
+fn test_which_sysroot() {
+    let f = TestFixture::new();
+    let root = f.tempdir.path().to_path_buf();
+    let search = |name: &str, cwd: &str| {
+        which::WhichConfig::new()
+            .sysroot(root.clone())
+            .custom_path_list(env::join_paths(["/missing", "/../b", "c"]).unwrap())
+            .custom_cwd(cwd.into())
+            .binary_name(name.into())
+            .all_results()
+            .unwrap()
+            .map(|p| p.canonicalize().unwrap())
+            .collect::<Vec<_>>()
+    };
+
+    #[cfg(not(windows))]
+    let (b, c) = (f.bins[3].clone(), f.bins[6].clone());
+    #[cfg(windows)]
+    let (b, c) = (f.bins[4].clone(), f.bins[7].clone());
+    assert_eq!(search(BIN_NAME, "/"), vec![b, c]);
+
+    #[cfg(not(windows))]
+    let a = f.bins[0].clone();
+    #[cfg(windows)]
+    let a = f.bins[1].clone();
+    assert_eq!(search(&format!("a/{}", BIN_NAME), "/"), vec![a.clone()]);
+    assert_eq!(search(&format!("/a/{}", BIN_NAME), "/c"), vec![a]);
+}
+
+#[test]
+fn test_which_in_rootfs() {
     let f = TestFixture::new();
+    let root = f.tempdir.path();
+    fs::create_dir_all(root.join("usr/bin")).unwrap();
+    let bin = f.mk_bin("usr/bin/tool", "").unwrap();
+
+    let found = which::which_in_rootfs("tool", root, Option::<&str>::None).unwrap();
+    assert_eq!(found.canonicalize().unwrap(), bin);
+    let found = which::which_in_rootfs("tool", root, Some("/sbin:/usr/bin")).unwrap();
+    assert_eq!(found.canonicalize().unwrap(), bin);
     assert_eq!(
-        _which(&f, "./b/bin").unwrap(),
-        f.bins[3].canonicalize().unwrap()
+        which::which_in_rootfs("tool", root, Some("/sbin")),
+        Err(which::Error::CannotFindBinaryPath)
+    );
+
+    // Absolute symlinks resolve inside the root, not on the host.
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink("/usr/bin/tool", root.join("a/link")).unwrap();
+        let found = which::which_in_rootfs("link", root, Some("/a")).unwrap();
+        assert_eq!(found, root.join("a/link"));
+    }
+}
+
+#[test]
+fn test_env_snapshot() {
+    let f = TestFixture::new();
+    let snapshot = which::EnvSnapshot::new(
+        Some(f.paths.clone()),
+        Some(".CMD".into()),
+        Some(f.tempdir.path().to_path_buf()),
     );
+    assert_eq!(snapshot.path(), Some(f.paths.as_os_str()));
+
+    #[cfg(not(windows))]
+    let (a, b) = (f.bins[0].clone(), f.bins[3].clone());
+    #[cfg(windows)]
+    let (a, b) = (f.bins[2].clone(), f.bins[5].clone());
+    let found = snapshot.which(BIN_NAME).unwrap();
+    assert_eq!(found.canonicalize().unwrap(), a);
+    let found = snapshot.which(Path::new("b").join(BIN_NAME)).unwrap();
+    assert_eq!(found.canonicalize().unwrap(), b);
+    assert_eq!(snapshot.which_all(BIN_NAME).unwrap().count(), 3);
 }
 
 #[test]
-#[cfg(windows)]
-fn test_which_relative_leading_dot() {
+fn test_path_diff() {
+    use which::{path_diff, Placement};
+
     let f = TestFixture::new();
+    let b = env::split_paths(&f.paths).nth(1).unwrap();
+    let new = f.tempdir.path().join("new");
+    fs::create_dir(&new).unwrap();
+    let extension = if cfg!(windows) { "exe" } else { "" };
+    f.mk_bin("new/bin", extension).unwrap();
+    f.mk_bin("new/fresh", extension).unwrap();
+    f.touch("new/readme", "").unwrap();
+
+    #[cfg(not(windows))]
+    let file = |dir: &Path, name: &str| dir.join(name);
+    #[cfg(windows)]
+    let file = |dir: &Path, name: &str| dir.join(format!("{}.exe", name));
+
+    let diff = path_diff(Some(&b), &new, Placement::Prepend).unwrap();
+    assert_eq!(diff.reachable, vec![("fresh".into(), file(&new, "fresh"))]);
     assert_eq!(
-        _which(&f, "./b/bin").unwrap(),
-        f.bins[4].canonicalize().unwrap()
+        diff.changed,
+        vec![("bin".into(), file(&b, "bin"), file(&new, "bin"))]
+    );
+    assert!(diff.shadowed.is_empty());
+
+    let diff = path_diff(Some(&b), &new, Placement::Append).unwrap();
+    assert_eq!(diff.reachable, vec![("fresh".into(), file(&new, "fresh"))]);
+    assert!(diff.changed.is_empty());
+    assert_eq!(
+        diff.shadowed,
+        vec![("bin".into(), file(&b, "bin"), file(&new, "bin"))]
     );
 }
 
 #[test]
-#[cfg(unix)]
-fn test_which_non_executable() {
-    // Shouldn't return non-executable files.
+fn test_same() {
     let f = TestFixture::new();
-    f.touch("b/another", "").unwrap();
-    assert!(_which(&f, "another").is_err());
+    #[cfg(not(windows))]
+    let (a, b) = (f.bins[0].clone(), f.bins[3].clone());
+    #[cfg(windows)]
+    let (a, b) = (f.bins[1].clone(), f.bins[4].clone());
+    assert_eq!(which::same(&a, &a), Ok(true));
+    assert_eq!(which::same(&a, &b), Ok(false));
+
+    let link = f.tempdir.path().join("c").join("hard-link");
+    fs::hard_link(&a, &link).unwrap();
+    assert!(which::same_file(&a, &link).unwrap());
+    assert!(!which::same_file(&b, &link).unwrap());
+    assert!(which::same_file(&a, f.tempdir.path().join("missing")).is_err());
 }
 
 #[test]
-#[cfg(unix)]
-fn test_which_absolute_non_executable() {
-    // Shouldn't return non-executable files, even if given an absolute path.
+fn test_which_all_by_file() {
     let f = TestFixture::new();
-    let b = f.touch("b/another", "").unwrap();
-    assert!(_which(&f, b).is_err());
+    #[cfg(not(windows))]
+    let (a, c) = (f.bins[0].clone(), f.bins[6].clone());
+    #[cfg(windows)]
+    let (a, c) = (f.bins[1].clone(), f.bins[7].clone());
+    fs::remove_file(&c).unwrap();
+    fs::hard_link(&a, &c).unwrap();
+
+    let groups = which::WhichConfig::new()
+        .custom_path_list(f.paths.clone())
+        .binary_name(BIN_NAME.into())
+        .all_results_by_file()
+        .unwrap();
+    let groups: Vec<_> = groups
+        .into_iter()
+        .map(|group| {
+            let links: Vec<_> = group
+                .links
+                .iter()
+                .map(|p| p.canonicalize().unwrap())
+                .collect();
+            (group.path.canonicalize().unwrap(), links)
+        })
+        .collect();
+
+    #[cfg(not(windows))]
+    let b = f.bins[3].clone();
+    #[cfg(windows)]
+    let b = f.bins[4].clone();
+    assert_eq!(groups, vec![(a, vec![c]), (b, vec![])]);
 }
 
 #[test]
-#[cfg(unix)]
-fn test_which_relative_non_executable() {
-    // Shouldn't return non-executable files.
+fn test_is_executable() {
     let f = TestFixture::new();
-    f.touch("b/another", "").unwrap();
-    assert!(_which(&f, "b/another").is_err());
+    #[cfg(not(windows))]
+    let (bin, not_bin) = (f.bins[0].clone(), f.touch("a/plain", "").unwrap());
+    #[cfg(windows)]
+    let (bin, not_bin) = (f.bins[1].clone(), f.bins[0].clone());
+    assert!(which::is_executable(bin));
+    assert!(!which::is_executable(not_bin));
+    assert!(!which::is_executable(f.tempdir.path().join("a")));
+    assert!(!which::is_executable(f.tempdir.path().join("missing")));
 }
 
 #[test]
-fn test_failure() {
+fn test_exists_in() {
     let f = TestFixture::new();
+    assert!(which::exists_in(BIN_NAME, Some(&f.paths), f.tempdir.path()));
+    assert!(which::exists_in(
+        Path::new("a").join(BIN_NAME),
+        Some(&f.paths),
+        f.tempdir.path()
+    ));
+    assert!(!which::exists_in(
+        "missing",
+        Some(&f.paths),
+        f.tempdir.path()
+    ));
+    assert!(!which::exists_in(
+        BIN_NAME,
+        Option::<&str>::None,
+        f.tempdir.path()
+    ));
+}
 
-    let run = || -> which::Result<PathBuf> {
-        let p = _which(&f, "./b/bin")?;
-        Ok(p.into_path_buf())
+#[test]
+fn test_which_in_global_strict() {
+    let f = TestFixture::new();
+    #[cfg(not(windows))]
+    let b = f.bins[3].clone();
+    #[cfg(windows)]
+    let b = f.bins[4].clone();
+    // Neither the empty nor the relative entry may resolve against the working directory.
+    let mut paths = vec![PathBuf::new(), PathBuf::from("a")];
+    paths.extend(env::split_paths(&f.paths).skip(1));
+    let paths = env::join_paths(paths).unwrap();
+
+    let found = which::which_in_global_strict(BIN_NAME, Some(&paths)).unwrap();
+    assert_eq!(found.canonicalize().unwrap(), b);
+    assert_eq!(
+        which::which_in_global_strict(Path::new("b").join(BIN_NAME), Some(&paths)),
+        Err(which::Error::BadRelativePath)
+    );
+    assert_eq!(
+        which::which_in_global_strict(&b, Some(&paths)),
+        Err(which::Error::BadAbsolutePath)
+    );
+    assert_eq!(
+        which::which_in_global_strict(".", Some(&paths)),
+        Err(which::Error::BadRelativePath)
+    );
+    assert_eq!(
+        which::which_in_global_strict(BIN_NAME, Some("a")),
+        Err(which::Error::CannotFindBinaryPath)
+    );
+
+    // Quoted because it holds the separator, and still searched as a whole.
+    #[cfg(windows)]
+    {
+        let odd = f.tempdir.path().join("x;y");
+        fs::create_dir(&odd).unwrap();
+        let bin = mk_bin(&odd, BIN_NAME, "exe").unwrap();
+        let paths = format!("\"{}\"", odd.display());
+        assert_eq!(
+            which::which_in_global_strict(BIN_NAME, Some(&paths)),
+            Ok(bin)
+        );
+    }
+}
+
+#[test]
+fn test_which_matching_in() {
+    let f = TestFixture::new();
+    let found: Vec<_> = which::which_matching_in(
+        |name: &OsStr| name.to_string_lossy().starts_with("win-"),
+        Some(&f.paths),
+    )
+    .unwrap()
+    .map(|p| p.canonicalize().unwrap())
+    .collect();
+    assert_eq!(found, vec![f.bins[9].clone()]);
+
+    let found = which::which_matching_in(|name: &OsStr| name == BIN_NAME, Some(&f.paths))
+        .unwrap()
+        .count();
+    // On Windows, names without an extension from `PATHEXT` aren't executables.
+    #[cfg(not(windows))]
+    assert_eq!(found, 3);
+    #[cfg(windows)]
+    assert_eq!(found, 0);
+}
+
+#[test]
+fn test_which_pattern_in() {
+    let f = TestFixture::new();
+    let find = |pattern| -> Vec<PathBuf> {
+        which::which_pattern_in(pattern, Some(&f.paths), Some(f.tempdir.path()))
+            .unwrap()
+            .map(|p| p.canonicalize().unwrap())
+            .collect()
     };
 
-    let _ = run();
+    let by_name = find(BIN_NAME);
+    assert_eq!(
+        by_name,
+        which::which_in_all(BIN_NAME, Some(&f.paths), f.tempdir.path())
+            .unwrap()
+            .map(|p| p.canonicalize().unwrap())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(by_name.len(), 3);
+    #[cfg(not(windows))]
+    assert_eq!(by_name[0], f.bins[0]);
+    #[cfg(windows)]
+    assert_eq!(by_name[0], f.bins[1]);
+
+    let by_predicate: Vec<_> = which::which_pattern_in(
+        which::Predicate(|name: &OsStr| name.to_string_lossy().starts_with("win-")),
+        Some(&f.paths),
+        Option::<&Path>::None,
+    )
+    .unwrap()
+    .map(|p| p.canonicalize().unwrap())
+    .collect();
+    assert_eq!(by_predicate, vec![f.bins[9].clone()]);
+
+    #[cfg(feature = "regex")]
+    {
+        let re = regex::Regex::new("^win-").unwrap();
+        let by_regex: Vec<_> = which::which_pattern_in(&re, Some(&f.paths), Option::<&Path>::None)
+            .unwrap()
+            .map(|p| p.canonicalize().unwrap())
+            .collect();
+        assert_eq!(by_regex, by_predicate);
+    }
+
+    #[cfg(feature = "glob")]
+    {
+        let glob = glob::Pattern::new("win-*").unwrap();
+        let by_glob: Vec<_> = which::which_pattern_in(&glob, Some(&f.paths), Option::<&Path>::None)
+            .unwrap()
+            .map(|p| p.canonicalize().unwrap())
+            .collect();
+        assert_eq!(by_glob, by_predicate);
+
+        // Wildcards don't match the leading dot of hidden files.
+        let hidden = f.mk_bin("a/.win-hidden", "exe").unwrap();
+        let find = |glob| -> Vec<PathBuf> {
+            which::which_pattern_in(
+                glob::Pattern::new(glob).unwrap(),
+                Some(&f.paths),
+                Option::<&Path>::None,
+            )
+            .unwrap()
+            .map(|p| p.canonicalize().unwrap())
+            .collect()
+        };
+        assert_eq!(find("*win-*"), by_predicate);
+        assert_eq!(find(".win-*"), vec![hidden]);
+    }
+}
+
+#[test]
+fn test_which_invalid_name() {
+    let f = TestFixture::new();
+    for name in ["", "bin\0"] {
+        assert_eq!(
+            which::which_in(name, Some(&f.paths), f.tempdir.path()),
+            Err(which::Error::InvalidName)
+        );
+    }
+
+    let config = || {
+        which::WhichConfig::new()
+            .binary_name("bin\n".into())
+            .custom_path_list(f.paths.clone())
+    };
+    assert_eq!(
+        config().first_result(),
+        Err(which::Error::CannotFindBinaryPath)
+    );
+    assert_eq!(
+        config().reject_newlines(true).first_result(),
+        Err(which::Error::InvalidName)
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn test_which_rejects_fifo() {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let f = TestFixture::new();
+    let fifo = f.tempdir.path().join("b").join("fifo");
+    let c_fifo = CString::new(fifo.as_os_str().as_bytes()).unwrap();
+    assert_eq!(unsafe { libc::mkfifo(c_fifo.as_ptr(), 0o755) }, 0);
+
+    assert_eq!(
+        which::which_in("fifo", Some(&f.paths), f.tempdir.path()),
+        Err(which::Error::CannotFindBinaryPath)
+    );
+    assert_eq!(
+        which::which_in(&fifo, Some(&f.paths), f.tempdir.path()),
+        Err(which::Error::CannotFindBinaryPath)
+    );
+
+    let found = which::WhichConfig::new()
+        .binary_name("fifo".into())
+        .custom_path_list(f.paths.clone())
+        .special_files(true)
+        .first_result()
+        .unwrap();
+    assert_eq!(found, fifo);
+}
+
+#[test]
+fn test_which_reject_empty() {
+    let f = TestFixture::new();
+    let config = || {
+        which::WhichConfig::new()
+            .binary_name(BIN_NAME.into())
+            .custom_path_list(f.paths.clone())
+            .reject_empty(true)
+    };
+    // Every binary in the fixture is empty.
+    assert_eq!(
+        config().first_result(),
+        Err(which::Error::CannotFindBinaryPath)
+    );
+
+    #[cfg(not(windows))]
+    let bin = &f.bins[3];
+    #[cfg(windows)]
+    let bin = &f.bins[4];
+    fs::write(bin, b"#!/bin/sh\n").unwrap();
+    assert_eq!(
+        config().first_result().unwrap().canonicalize().unwrap(),
+        *bin
+    );
+}
+
+#[test]
+fn test_which_verify_signature() {
+    let f = TestFixture::new();
+    let config = || {
+        which::WhichConfig::new()
+            .binary_name(BIN_NAME.into())
+            .custom_path_list(f.paths.clone())
+            .verify_signature(true)
+    };
+
+    #[cfg(not(windows))]
+    let (data, script) = (&f.bins[0], &f.bins[3]);
+    #[cfg(windows)]
+    let (data, script) = (&f.bins[1], &f.bins[4]);
+    fs::write(data, b"PNG data").unwrap();
+    fs::write(script, b"#!/bin/sh\n").unwrap();
+    assert_eq!(
+        config().first_result().unwrap().canonicalize().unwrap(),
+        *script
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn test_which_verify_signature_special_files() {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let f = TestFixture::new();
+    let root = f.tempdir.path();
+    let fifo = root.join("a").join("tool");
+    let c_fifo = CString::new(fifo.as_os_str().as_bytes()).unwrap();
+    assert_eq!(unsafe { libc::mkfifo(c_fifo.as_ptr(), 0o755) }, 0);
+    let script = f.mk_bin("b/tool", "").unwrap();
+    fs::write(&script, b"#!/bin/sh\n").unwrap();
+
+    // Opening the FIFO would block until something writes to it.
+    let found = which::WhichConfig::new()
+        .binary_name("tool".into())
+        .custom_path_list(f.paths.clone())
+        .special_files(true)
+        .verify_signature(true)
+        .first_result()
+        .unwrap();
+    assert_eq!(found, script);
+
+    // Absolute symlinks are read from inside the sysroot.
+    std::os::unix::fs::symlink("/b/tool", root.join("c/tool")).unwrap();
+    let found = which::WhichConfig::new()
+        .sysroot(root.to_path_buf())
+        .binary_name("tool".into())
+        .custom_path_list("/c".into())
+        .verify_signature(true)
+        .first_result()
+        .unwrap();
+    assert_eq!(found, root.join("c/tool"));
+}
+
+#[test]
+#[cfg(all(unix, feature = "regex"))]
+fn test_which_hidden_files() {
+    let f = TestFixture::new();
+    let hidden = f.mk_bin("a/.bin_0", "").unwrap();
+    let visible = f.mk_bin("b/bin_0", "").unwrap();
+    let config = || {
+        which::WhichConfig::new()
+            .system_cwd(false)
+            .regex(Regex::new(r"bin_0$").unwrap())
+            .custom_path_list(f.paths.clone())
+    };
+
+    let all: Vec<_> = config().all_results().unwrap().collect();
+    assert_eq!(all.len(), 2);
+    assert_eq!(all[0].canonicalize().unwrap(), hidden);
+
+    let visible_only: Vec<_> = config()
+        .hidden_files(false)
+        .all_results()
+        .unwrap()
+        .map(|p| p.canonicalize().unwrap())
+        .collect();
+    assert_eq!(visible_only, vec![visible]);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_which_resolve_path_entries() {
+    let f = TestFixture::new();
+    let link = f.tempdir.path().join("link");
+    std::os::unix::fs::symlink(f.tempdir.path().join("b"), &link).unwrap();
+
+    let config = || {
+        which::WhichConfig::new()
+            .binary_name(BIN_NAME.into())
+            .custom_path_list(link.clone().into())
+    };
+    assert_eq!(config().first_result().unwrap(), link.join(BIN_NAME));
+    assert_eq!(
+        config().resolve_path_entries(true).first_result().unwrap(),
+        f.bins[3]
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn test_which_path_form() {
+    let f = TestFixture::new();
+    let link = f.tempdir.path().join("link");
+    std::os::unix::fs::symlink(f.tempdir.path().join("b"), &link).unwrap();
+    let entry = link.join("..").join(".").join("a");
+
+    let found = |form| {
+        which::WhichConfig::new()
+            .binary_name(BIN_NAME.into())
+            .custom_path_list(entry.clone().into())
+            .path_form(form)
+            .first_result()
+            .unwrap()
+    };
+    assert_eq!(found(which::PathForm::AsConstructed), entry.join(BIN_NAME));
+    assert_eq!(
+        found(which::PathForm::Lexical),
+        f.tempdir.path().join("a").join(BIN_NAME)
+    );
+    assert_eq!(found(which::PathForm::Canonical), f.bins[0]);
+
+    // Candidates are checked as normalized, so `..` is taken lexically even past a symlink
+    // to a directory elsewhere.
+    let deep = f.tempdir.path().join("c").join("deep");
+    fs::create_dir(&deep).unwrap();
+    let deep_link = f.tempdir.path().join("deep-link");
+    std::os::unix::fs::symlink(&deep, &deep_link).unwrap();
+    let config = |form| {
+        which::WhichConfig::new()
+            .binary_name(BIN_NAME.into())
+            .custom_path_list(deep_link.join("..").join("a").into())
+            .path_form(form)
+    };
+    assert!(config(which::PathForm::AsConstructed)
+        .first_result()
+        .is_err());
+    assert_eq!(
+        config(which::PathForm::Lexical).first_result().unwrap(),
+        f.tempdir.path().join("a").join(BIN_NAME)
+    );
+}
+
+#[test]
+fn test_which_relative_to() {
+    let f = TestFixture::new();
+    let found = |base: &Path| {
+        which::WhichConfig::new()
+            .binary_name(BIN_NAME.into())
+            .custom_path_list(f.paths.clone())
+            .relative_to(base.to_path_buf())
+            .first_result()
+            .unwrap()
+    };
+
+    #[cfg(not(windows))]
+    let name = BIN_NAME.to_owned();
+    #[cfg(windows)]
+    let name = format!("{}.exe", BIN_NAME);
+    assert_eq!(
+        found(f.tempdir.path()),
+        Path::new(".").join("a").join(&name)
+    );
+    assert_eq!(
+        found(&f.tempdir.path().join("a")),
+        Path::new(".").join(&name)
+    );
+    assert_eq!(
+        found(&f.tempdir.path().join("b")),
+        f.tempdir.path().join("a").join(&name)
+    );
+}
+
+#[test]
+#[cfg(windows)]
+fn test_which_trailing_dots_and_spaces() {
+    let f = TestFixture::new();
+    let entry = OsString::from(format!("{}. ", f.tempdir.path().join("a").display()));
+    assert_eq!(
+        which::which_in("bin. ", Some(&entry), f.tempdir.path())
+            .unwrap()
+            .canonicalize()
+            .unwrap(),
+        f.bins[1]
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn test_xcode_toolchain() {
+    let f = TestFixture::new();
+    let dev = f.tempdir.path().join("Developer");
+    let bin = dev.join("usr").join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    let clang = mk_bin(&bin, "clang", "").unwrap();
+    let env =
+        which::EnvSnapshot::new(Some(f.paths.clone()), None, None).var("DEVELOPER_DIR", Some(&dev));
+
+    let config = || which::WhichConfig::hermetic(env.clone()).binary_name("clang".into());
+    assert_eq!(
+        config().first_result().unwrap_err(),
+        which::Error::CannotFindBinaryPath
+    );
+    let (path, origin) = config()
+        .xcode_toolchain(true)
+        .all_results_with_origin()
+        .unwrap()
+        .next()
+        .unwrap();
+    assert_eq!(path, clang);
+    assert_eq!(origin, which::Origin::Toolchain);
+
+    // Matches on the path list still come first.
+    let (_, origin) = which::WhichConfig::hermetic(env)
+        .binary_name(BIN_NAME.into())
+        .xcode_toolchain(true)
+        .all_results_with_origin()
+        .unwrap()
+        .next()
+        .unwrap();
+    assert_eq!(origin, which::Origin::PathEntry(0));
+}
+
+#[test]
+#[cfg(unix)]
+fn test_homebrew() {
+    let f = TestFixture::new();
+    let prefix = f.tempdir.path().join("homebrew");
+    let bin = prefix.join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    let tool = mk_bin(&bin, "brewed", "").unwrap();
+
+    let results = |paths: OsString| {
+        let env =
+            which::EnvSnapshot::new(Some(paths), None, None).var("HOMEBREW_PREFIX", Some(&prefix));
+        which::WhichConfig::hermetic(env)
+            .binary_name("brewed".into())
+            .homebrew(true)
+            .all_results_with_origin()
+            .unwrap()
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        results(f.paths.clone()),
+        vec![(tool.clone(), which::Origin::Homebrew)]
+    );
+
+    // Not searched twice when the path list has it already.
+    assert_eq!(
+        results(bin.clone().into()),
+        vec![(tool, which::Origin::PathEntry(0))]
+    );
+}
+
+#[test]
+fn test_which_variants() {
+    let f = TestFixture::new();
+    let variants = which::NameVariants::new()
+        .prefix("missing-")
+        .prefix("win-")
+        .prefix("")
+        .suffix(".exe");
+    // `win-bin.exe` wins over `bin.exe`, even though its directory comes last.
+    let found = which::which_variants_in("bin", &variants, Some(&f.paths), ".").unwrap();
+    assert_eq!(found, f.bins[9]);
+
+    let variants = which::NameVariants::new().suffix("-missing").suffix(".exe");
+    let found = which::which_variants_in("bin", &variants, Some(&f.paths), ".").unwrap();
+    assert_eq!(found, f.bins[1]);
+
+    let variants = which::NameVariants::new().suffix("-missing");
+    let found = which::which_variants_in("bin", &variants, Some(&f.paths), ".");
+    assert_eq!(found.unwrap_err(), which::Error::CannotFindBinaryPath);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_windows_extensions() {
+    let f = TestFixture::new();
+    let tool = f.touch("b/tool", "exe").unwrap();
+
+    let config = || {
+        which::WhichConfig::new()
+            .custom_path_list(f.paths.clone())
+            .system_cwd(false)
+    };
+    assert_eq!(
+        config()
+            .binary_name("tool".into())
+            .first_result()
+            .unwrap_err(),
+        which::Error::CannotFindBinaryPath
+    );
+    // Found without an execute bit, like on Windows.
+    assert_eq!(
+        config()
+            .binary_name("tool".into())
+            .windows_extensions(true)
+            .first_result()
+            .unwrap(),
+        tool
+    );
+    // Names are still tried as they are first.
+    assert_eq!(
+        config()
+            .binary_name("win-bin".into())
+            .windows_extensions(true)
+            .all_results()
+            .unwrap()
+            .collect::<Vec<_>>(),
+        vec![f.bins[9].clone()]
+    );
+    assert_eq!(
+        config()
+            .binary_name(BIN_NAME.into())
+            .windows_extensions(true)
+            .first_result()
+            .unwrap(),
+        f.bins[0]
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn test_extension_policy() {
+    let f = TestFixture::new();
+    let dir = f.tempdir.path().join("tools");
+    fs::create_dir(&dir).unwrap();
+    f.touch("tools/tool", "exe").unwrap();
+    f.touch("tools/tool", "cmd").unwrap();
+    let (exe, cmd) = (dir.join("tool.exe"), dir.join("tool.cmd"));
+    let search = |policy: which::ExtensionPolicy| -> Vec<PathBuf> {
+        which::WhichConfig::new()
+            .system_cwd(false)
+            .custom_path_list(dir.clone().into_os_string())
+            .binary_name("tool".into())
+            .windows_extensions(true)
+            .extension_policy(policy)
+            .all_results()
+            .unwrap()
+            .collect()
+    };
+
+    assert_eq!(
+        search(which::ExtensionPolicy::All),
+        vec![exe.clone(), cmd.clone()]
+    );
+    assert_eq!(search(which::ExtensionPolicy::PathextOrder), vec![exe]);
+    assert_eq!(
+        search(which::ExtensionPolicy::Priority(vec!["CMD".into()])),
+        vec![cmd]
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn test_all_extension_variants() {
+    let f = TestFixture::new();
+    for dir in ["tools", "more"] {
+        fs::create_dir(f.tempdir.path().join(dir)).unwrap();
+    }
+    f.touch("tools/tool", "exe").unwrap();
+    f.touch("tools/tool", "cmd").unwrap();
+    f.touch("more/tool", "bat").unwrap();
+    let (tools, more) = (
+        f.tempdir.path().join("tools"),
+        f.tempdir.path().join("more"),
+    );
+
+    let groups = which::WhichConfig::new()
+        .system_cwd(false)
+        .custom_path_list(env::join_paths([&tools, &more]).unwrap())
+        .binary_name("tool".into())
+        .windows_extensions(true)
+        .extension_policy(which::ExtensionPolicy::Priority(vec![".cmd".into()]))
+        .all_extension_variants()
+        .unwrap();
+    let groups: Vec<_> = groups
+        .into_iter()
+        .map(|group| (group.path, group.origin, group.shadowed))
+        .collect();
+    assert_eq!(
+        groups,
+        vec![
+            (
+                tools.join("tool.cmd"),
+                which::Origin::PathEntry(0),
+                vec![tools.join("tool.exe")]
+            ),
+            (more.join("tool.bat"), which::Origin::PathEntry(1), vec![]),
+        ]
+    );
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_case_collisions() {
+    let f = TestFixture::new();
+    let upper = f.mk_bin("a/Tool", "").unwrap();
+    let lower = f.mk_bin("a/tool", "").unwrap();
+    f.mk_bin("b/tool", "").unwrap();
+    // Not executable, so it doesn't collide with anything.
+    f.touch("b/TOOL", "").unwrap();
+
+    let collisions = which::WhichConfig::new()
+        .custom_path_list(f.paths.clone())
+        .binary_name("TOOL".into())
+        .case_collisions()
+        .unwrap();
+    assert_eq!(collisions.len(), 1);
+    assert_eq!(collisions[0].dir, f.tempdir.path().join("a"));
+    assert_eq!(collisions[0].origin, which::Origin::PathEntry(0));
+    let paths: Vec<_> = collisions[0]
+        .paths
+        .iter()
+        .map(|path| path.canonicalize().unwrap())
+        .collect();
+    assert_eq!(paths, vec![upper, lower]);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_platform_profile() {
+    let f = TestFixture::new();
+    let paths: Vec<_> = SUBDIRS
+        .iter()
+        .map(|d| {
+            f.tempdir
+                .path()
+                .join(d)
+                .into_os_string()
+                .into_string()
+                .unwrap()
+        })
+        .collect();
+    let windows_paths = paths.join(";");
+    fs::write(f.tempdir.path().join("b").join("script.CMD"), "").unwrap();
+
+    let config = |profile: which::PlatformProfile, name: &str| {
+        which::WhichConfig::new()
+            .platform(profile)
+            .custom_path_list(windows_paths.clone().into())
+            .system_cwd(false)
+            .binary_name(name.into())
+            .all_results()
+            .unwrap()
+            .collect::<Vec<_>>()
+    };
+    let windows = || which::PlatformProfile::windows(Some(OsStr::new(".EXE;.CMD")));
+
+    // `bin` itself isn't a Windows executable, and `bin.EXE` matches `bin.exe`.
+    assert_eq!(
+        config(windows(), BIN_NAME),
+        [1, 2, 4, 5, 7, 8].map(|i| f.bins[i].clone())
+    );
+    assert_eq!(
+        config(windows(), "script"),
+        vec![f.tempdir.path().join("b").join("script.CMD")]
+    );
+    assert_eq!(
+        config(windows().case_sensitive(true), BIN_NAME),
+        Vec::<PathBuf>::new()
+    );
+    // Unix rules, but with the path list still separated by `;`.
+    assert_eq!(
+        which::WhichConfig::new()
+            .platform(which::PlatformProfile::unix())
+            .path_separator(';')
+            .custom_path_list(windows_paths.clone().into())
+            .system_cwd(false)
+            .binary_name(BIN_NAME.into())
+            .first_result()
+            .unwrap(),
+        f.bins[0]
+    );
+}
+
+#[test]
+fn test_search_dirs() {
+    let f = TestFixture::new();
+    let dirs: Vec<PathBuf> = env::split_paths(&f.paths).collect();
+    assert_eq!(
+        which::WhichConfig::new()
+            .custom_path_list(f.paths.clone())
+            .search_dirs(),
+        dirs
+    );
+
+    let sysroot = f.tempdir.path().join("root");
+    assert_eq!(
+        which::WhichConfig::new()
+            .custom_path_list("/usr/bin".into())
+            .path_separator(':')
+            .sysroot(sysroot.clone())
+            .search_dirs(),
+        vec![sysroot.join("usr").join("bin")]
+    );
+}
+
+#[test]
+fn test_missing_dirs() {
+    let f = TestFixture::new();
+    let missing = f.tempdir.path().join("missing");
+    let paths = env::join_paths(vec![
+        f.tempdir.path().join("a"),
+        missing.clone(),
+        f.bins[0].clone(),
+    ])
+    .unwrap();
+    assert_eq!(
+        which::path_audit::missing_dirs_in(paths),
+        vec![missing, f.bins[0].clone()]
+    );
+    assert_eq!(
+        which::path_audit::missing_dirs_in(&f.paths),
+        Vec::<PathBuf>::new()
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn test_requirements() {
+    use which::doctor::{Requirement, Requirements, Status};
+
+    let f = TestFixture::new();
+    let tool = f.mk_bin("b/tool", "").unwrap();
+    fs::write(&tool, "#!/bin/sh\necho \"tool version 1.4.2\"\n").unwrap();
+
+    let report = Requirements::new()
+        .require(Requirement::new("missing").or(BIN_NAME))
+        .require(
+            Requirement::new("tool")
+                .min_version("1.4")
+                .probe_version(true),
+        )
+        .require(
+            Requirement::new("tool")
+                .min_version("1.10")
+                .probe_version(true),
+        )
+        .require(Requirement::new("missing").optional(true))
+        .require(Requirement::new("tool").min_version("1.4"))
+        .check_in(&f.paths, f.tempdir.path());
+    let statuses: Vec<Status> = report.checks.iter().map(|check| check.status).collect();
+    assert_eq!(
+        statuses,
+        [
+            Status::Found,
+            Status::Found,
+            Status::Outdated,
+            Status::Missing,
+            Status::UnknownVersion,
+        ]
+    );
+    assert_eq!(report.checks[0].name, BIN_NAME);
+    assert_eq!(report.checks[0].path.as_ref(), Some(&f.bins[0]));
+    assert_eq!(report.checks[1].version.as_deref(), Some("1.4.2"));
+    assert!(!report.is_satisfied());
+    assert_eq!(report.problems().count(), 3);
+
+    let report = Requirements::new()
+        .require(
+            Requirement::new("tool")
+                .min_version("1.4")
+                .probe_version(true),
+        )
+        .require(Requirement::new("missing").optional(true))
+        .check_in(&f.paths, f.tempdir.path());
+    assert!(report.is_satisfied());
+}
+
+#[test]
+#[should_panic(expected = "minimum version must be a version")]
+fn test_requirement_invalid_min_version() {
+    let _ = which::doctor::Requirement::new("tool").min_version("latest");
+}
+
+#[test]
+#[cfg(all(unix, feature = "serde"))]
+fn test_manifest() {
+    use which::doctor::Status;
+    use which::manifest::Manifest;
+
+    let f = TestFixture::new();
+    let tool = f.mk_bin("b/tool", "").unwrap();
+    fs::write(&tool, "#!/bin/sh\necho \"tool version 1.4.2\"\n").unwrap();
+
+    let manifest: Manifest = toml::from_str(&format!(
+        r#"
+        [search]
+        path = ["{c}", "{b}"]
+        hidden_files = false
+
+        [[tool]]
+        name = "missing"
+        or = ["{bin}"]
+
+        [[tool]]
+        name = "tool"
+        version = ">= 1.4, < 2"
+        probe_version = true
+
+        [[tool]]
+        name = "missing"
+        optional = true
+        "#,
+        c = f.tempdir.path().join("c").display(),
+        b = f.tempdir.path().join("b").display(),
+        bin = BIN_NAME,
+    ))
+    .unwrap();
+    assert_eq!(manifest.search.hidden_files, Some(false));
+    assert_eq!(manifest.config().search_dirs().len(), 2);
+
+    let report = manifest.check();
+    let statuses: Vec<Status> = report.checks.iter().map(|check| check.status).collect();
+    assert_eq!(statuses, [Status::Found, Status::Found, Status::Missing]);
+    assert_eq!(report.checks[0].path.as_ref(), Some(&f.bins[6]));
+    assert_eq!(report.checks[1].path.as_ref(), Some(&tool));
+    assert!(report.is_satisfied());
+
+    // A directory with the path separator in its name is searched as a whole.
+    let odd = f.tempdir.path().join("x:y");
+    fs::create_dir(&odd).unwrap();
+    let odd_tool = mk_bin(&odd, "odd", "").unwrap();
+    let mut search = which::manifest::Search::default();
+    search.path = Some(vec![f.tempdir.path().join("b"), odd]);
+    assert_eq!(search.config().search_dirs().len(), 2);
+    assert_eq!(
+        search
+            .config()
+            .binary_name("odd".into())
+            .first_result()
+            .unwrap(),
+        odd_tool
+    );
+
+    let manifest: Manifest =
+        serde_json::from_str(r#"{"tool": [{"name": "tool", "version": "< 1.4"}]}"#).unwrap();
+    assert_eq!(manifest.tools[0].requirement(), {
+        let req = which::doctor::VersionReq::parse("< 1.4").unwrap();
+        which::doctor::Requirement::new("tool").version(req)
+    });
+    assert!(serde_json::from_str::<Manifest>(r#"{"tools": []}"#).is_err());
+    assert!(
+        serde_json::from_str::<Manifest>(r#"{"tool": [{"name": "a", "version": "x"}]}"#).is_err()
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn test_to_command() {
+    let f = TestFixture::new();
+    let tool = f.mk_bin("b/tool", "").unwrap();
+    fs::write(&tool, "#!/bin/sh\nexit \"$1\"\n").unwrap();
+
+    let path = which::Path::new_in("tool", Some(&f.paths), f.tempdir.path()).unwrap();
+    let mut command = path.to_command();
+    assert_eq!(command.get_program(), tool.as_os_str());
+    assert_eq!(command.arg("3").status().unwrap().code(), Some(3));
+}
+
+#[test]
+#[cfg(windows)]
+fn test_to_command_interpreter() {
+    let f = TestFixture::new();
+    let script = f.mk_bin("b/serve", "js").unwrap();
+    fs::write(&script, "#!/usr/bin/env node\n").unwrap();
+    let node = f.mk_bin("c/node", "exe").unwrap();
+
+    // Found where the script was, not on the process's PATH.
+    let path = which::Path::new_in("serve.js", Some(&f.paths), f.tempdir.path()).unwrap();
+    let command = path.to_command();
+    assert_eq!(command.get_program(), node.as_os_str());
+    let args: Vec<_> = command.get_args().collect();
+    assert_eq!(args, vec![script.as_os_str()]);
+
+    let script = f.mk_bin("a/build", "ps1").unwrap();
+    let path = which::Path::new_in("build.ps1", Some(&f.paths), f.tempdir.path()).unwrap();
+    let args: Vec<_> = path.to_command().get_args().map(|a| a.to_owned()).collect();
+    assert_eq!(
+        args,
+        vec!["-NoProfile".into(), "-File".into(), script.into_os_string()]
+    );
+}
+
+#[test]
+fn test_install_hints() {
+    use which::doctor::{Requirement, Requirements};
+    use which::hints::{which_with_hint, MissingTool};
+
+    let hints = |name: &OsStr| (name == "missing").then(|| "try `get missing`".to_owned());
+    let error = which_with_hint("missing", &hints).unwrap_err();
+    assert_eq!(error.hint.as_deref(), Some("try `get missing`"));
+    assert_eq!(
+        error.to_string(),
+        "cannot find `missing`: try `get missing`"
+    );
+    let error = which_with_hint("unknown", &hints).unwrap_err();
+    assert_eq!(error.to_string(), "cannot find `unknown`");
+
+    let error = MissingTool::new("missing", which::Error::InvalidName, &hints);
+    assert_eq!(error.hint, None);
+
+    let f = TestFixture::new();
+    let report = Requirements::new()
+        .require(Requirement::new(BIN_NAME))
+        .require(Requirement::new("missing"))
+        .check_in(&f.paths, f.tempdir.path());
+    assert_eq!(report.checks[0].hint(&hints), None);
+    assert_eq!(
+        report.checks[1].hint(&hints).as_deref(),
+        Some("try `get missing`")
+    );
+}
+
+#[test]
+#[cfg(all(target_os = "linux", feature = "install-hints"))]
+fn test_default_install_hints() {
+    use which::hints::{DefaultHints, InstallHint};
+
+    assert_eq!(
+        DefaultHints.hint("rg".as_ref()).as_deref(),
+        Some("install it with `apt install ripgrep` or `cargo install ripgrep`")
+    );
+    assert_eq!(
+        DefaultHints.hint("git".as_ref()).as_deref(),
+        Some("install it with `apt install git`")
+    );
+    assert_eq!(DefaultHints.hint("no-such-tool".as_ref()), None);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_env_override() {
+    let f = TestFixture::new();
+    let tool = f.mk_bin("b/tool", "").unwrap();
+    fs::create_dir(f.tempdir.path().join("custom")).unwrap();
+    let custom = f.mk_bin("custom/tool", "").unwrap();
+
+    let env = which::EnvSnapshot::new(
+        Some(f.paths.clone()),
+        None,
+        Some(f.tempdir.path().to_path_buf()),
+    )
+    .var("WHICH_TEST_TOOL", Some("custom/tool"))
+    .var("WHICH_TEST_EMPTY", Some(""))
+    .var(
+        "WHICH_TEST_NOT_EXECUTABLE",
+        Some(f.touch("c/tool", "").unwrap()),
+    );
+    let find = |vars: &[&str]| {
+        vars.iter()
+            .fold(which::WhichConfig::hermetic(env.clone()), |config, var| {
+                config.env_override(var.into())
+            })
+            .binary_name("tool".into())
+            .all_results_with_origin()
+            .unwrap()
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        find(&[
+            "WHICH_TEST_UNSET",
+            "WHICH_TEST_EMPTY",
+            "WHICH_TEST_NOT_EXECUTABLE",
+            "WHICH_TEST_TOOL"
+        ]),
+        [
+            (custom, which::Origin::EnvVar(3)),
+            (tool.clone(), which::Origin::PathEntry(1)),
+        ]
+    );
+    assert_eq!(find(&[]), [(tool, which::Origin::PathEntry(1))]);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_hermetic() {
+    let f = TestFixture::new();
+    let tool = f.mk_bin("b/tool", "").unwrap();
+    let env = which::EnvSnapshot::new(
+        Some(f.paths.clone()),
+        None,
+        Some(f.tempdir.path().join("c")),
+    );
+    let config = || which::WhichConfig::hermetic(env.clone());
+
+    // Nothing is read from the process, whose PATH doesn't have the fixture on it.
+    assert_eq!(
+        config().binary_name("tool".into()).first_result().unwrap(),
+        tool
+    );
+    assert_eq!(
+        config()
+            .binary_name("./bin".into())
+            .first_result()
+            .unwrap()
+            .canonicalize()
+            .unwrap(),
+        f.bins[6]
+    );
+    // Overrides are read from the snapshot too.
+    let overridden = |env: which::EnvSnapshot| {
+        which::WhichConfig::hermetic(env)
+            .env_override("WHICH_TEST_HERMETIC".into())
+            .binary_name("tool".into())
+            .all_results()
+            .unwrap()
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(overridden(env.clone()), vec![tool.clone()]);
+    assert_eq!(
+        overridden(env.clone().var("WHICH_TEST_HERMETIC", Some(&f.bins[0]))),
+        vec![f.bins[0].clone(), tool]
+    );
+    assert!(config().search_dirs().contains(&f.tempdir.path().join("a")));
+
+    let empty = which::EnvSnapshot::new(None, None, None);
+    assert_eq!(
+        which::WhichConfig::hermetic(empty)
+            .binary_name("sh".into())
+            .first_result(),
+        Err(which::Error::CannotFindBinaryPath)
+    );
+}
+
+#[test]
+#[cfg(feature = "test-util")]
+fn test_with_env() {
+    use which::test_util::with_env;
+
+    let f = TestFixture::new();
+    let tool = f.mk_bin("b/tool", "").unwrap();
+    let env = which::EnvSnapshot::new(
+        Some(f.paths.clone()),
+        None,
+        Some(f.tempdir.path().join("c")),
+    );
+
+    // The process's PATH doesn't have the fixture on it, but lookups on this thread see env's.
+    assert!(which::which("tool").is_err());
+    with_env(env.clone(), || {
+        assert_eq!(which::which("tool").unwrap(), tool);
+        assert_eq!(
+            which::which("./bin").unwrap().canonicalize().unwrap(),
+            f.bins[6]
+        );
+        assert_eq!(
+            which::WhichConfig::new()
+                .binary_name("tool".into())
+                .first_result()
+                .unwrap(),
+            tool
+        );
+        assert_eq!(which::EnvSnapshot::capture().path(), Some(&*f.paths));
+
+        // Other threads see the process's.
+        assert!(std::thread::spawn(|| which::which("tool"))
+            .join()
+            .unwrap()
+            .is_err());
+
+        let empty = which::EnvSnapshot::new(None, None, None);
+        assert_eq!(
+            with_env(empty, || which::which("tool")),
+            Err(which::Error::CannotFindBinaryPath)
+        );
+        assert_eq!(which::which("tool").unwrap(), tool);
+    });
+    assert!(which::which("tool").is_err());
+
+    // Other variables are substituted when the snapshot sets them, and read from the process
+    // otherwise.
+    let home = f.tempdir.path().join("jdk");
+    fs::create_dir_all(home.join("bin")).unwrap();
+    let javac = f.mk_bin("jdk/bin/javac", "").unwrap();
+    let java_home = env.clone().var("JAVA_HOME", Some(&home));
+    assert_eq!(java_home.var_os("java_home").is_some(), cfg!(windows));
+    assert_eq!(
+        with_env(java_home, || which::java::find_tool("javac"))
+            .unwrap()
+            .0,
+        javac
+    );
+    assert_eq!(
+        with_env(env.clone().var("PATH", Option::<&str>::None), || {
+            which::which("tool")
+        }),
+        Err(which::Error::CannotFindBinaryPath)
+    );
+
+    // A panic restores the process's too.
+    let panicked = std::panic::catch_unwind(|| with_env(env, || panic!("in the closure")));
+    assert!(panicked.is_err());
+    assert!(which::which("tool").is_err());
+}
+
+#[test]
+#[cfg(all(feature = "test-util", feature = "futures-core"))]
+fn test_with_env_stream() {
+    use futures_core::Stream;
+    use std::pin::Pin;
+
+    let f = TestFixture::new();
+    let tool = f.mk_bin("b/tool", "").unwrap();
+    let env = which::EnvSnapshot::new(Some(f.paths.clone()), None, None);
+
+    // The search is set up on this thread, so it sees env's PATH even though it runs on
+    // another one.
+    let mut stream = which::test_util::with_env(env, || which::stream::which_all("tool")).unwrap();
+    let streamed = futures_executor::block_on(async {
+        let mut found = Vec::new();
+        while let Some(path) = std::future::poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await
+        {
+            found.push(path);
+        }
+        found
+    });
+    assert_eq!(streamed, [tool]);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_project_dir() {
+    use which::Origin;
+
+    let f = TestFixture::new();
+    fs::create_dir_all(f.tempdir.path().join("proj/bin")).unwrap();
+    let local = f.mk_bin("proj/bin/tool", "").unwrap();
+    let global = f.mk_bin("c/tool", "").unwrap();
+
+    let config = || {
+        which::WhichConfig::new()
+            .project_dir("proj/bin".into())
+            .project_dir(f.tempdir.path().join("c"))
+            .custom_path_list(f.paths.clone())
+            .custom_cwd(f.tempdir.path().to_path_buf())
+    };
+    let found: Vec<_> = config()
+        .binary_name("tool".into())
+        .all_results_with_origin()
+        .unwrap()
+        .collect();
+    assert_eq!(
+        found,
+        [(local, Origin::Project(0)), (global, Origin::Project(1))]
+    );
+
+    let found: Vec<_> = config()
+        .binary_name(BIN_NAME.into())
+        .all_results_with_origin()
+        .unwrap()
+        .map(|(_, origin)| origin)
+        .collect();
+    assert_eq!(
+        found,
+        [
+            Origin::Project(1),
+            Origin::PathEntry(0),
+            Origin::PathEntry(1)
+        ]
+    );
+
+    let dirs = config().search_dirs();
+    assert_eq!(dirs[0], f.tempdir.path().join("proj/bin"));
+    assert_eq!(dirs[1], f.tempdir.path().join("c"));
+    assert_eq!(dirs.len(), 5);
+
+    let found = which::WhichConfig::new()
+        .project_dir(f.tempdir.path().join("proj/bin"))
+        .custom_path_list("".into())
+        .binary_name("tool".into())
+        .first_result();
+    assert!(found.is_ok());
+}
+
+#[test]
+#[cfg(unix)]
+fn test_node_bin_dirs() {
+    use which::Origin;
+
+    let f = TestFixture::new();
+    let root = f.tempdir.path();
+    fs::create_dir_all(root.join("node_modules/.bin")).unwrap();
+    fs::create_dir_all(root.join("pkg/sub/node_modules/.bin")).unwrap();
+    fs::create_dir_all(root.join("pkg/sub/src")).unwrap();
+    let outer = f.mk_bin("node_modules/.bin/eslint", "").unwrap();
+    let inner = f.mk_bin("pkg/sub/node_modules/.bin/tsc", "").unwrap();
+    f.mk_bin("node_modules/.bin/tsc", "").unwrap();
+
+    let start = root.join("pkg/sub/src");
+    let dirs = which::node::bin_dirs(&start);
+    assert_eq!(dirs[0], root.join("pkg/sub/node_modules/.bin"));
+    assert_eq!(dirs[1], root.join("node_modules/.bin"));
+
+    assert_eq!(
+        which::node::find_with_origin("tsc", &start).unwrap(),
+        (inner, Origin::Project(0))
+    );
+    assert_eq!(
+        which::node::find_with_origin("eslint", &start).unwrap(),
+        (outer, Origin::Project(1))
+    );
+    assert!(which::node::find("which-test-no-such-tool", &start).is_err());
+
+    // Relative starts are searched from the working directory up, once each.
+    assert_eq!(
+        which::node::bin_dirs("."),
+        which::node::bin_dirs(env::current_dir().unwrap())
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn test_python_environment() {
+    use which::python::{active_environment_in, find_in_environment_in, EnvironmentKind};
+
+    let f = TestFixture::new();
+    let root = f.tempdir.path();
+    fs::create_dir_all(root.join("venv/bin")).unwrap();
+    fs::create_dir_all(root.join("conda/bin")).unwrap();
+    let venv = f.mk_bin("venv/bin/which-test-pytest", "").unwrap();
+    let conda = f.mk_bin("conda/bin/which-test-pytest", "").unwrap();
+
+    let env = which::EnvSnapshot::new(None, None, None)
+        .var("VIRTUAL_ENV", Some(root.join("venv")))
+        .var("CONDA_PREFIX", Some(root.join("conda")))
+        .var("CONDA_DEFAULT_ENV", Some("test"));
+    let environment = active_environment_in(&env).unwrap();
+    assert_eq!(environment.kind, EnvironmentKind::Virtualenv);
+    assert_eq!(environment.name.as_deref(), Some("venv"));
+    let (path, environment) = find_in_environment_in("which-test-pytest", &env).unwrap();
+    assert_eq!(path, venv);
+    assert_eq!(environment.unwrap().prefix, root.join("venv"));
+
+    let env = env.var("VIRTUAL_ENV", Option::<&str>::None);
+    let (path, environment) = find_in_environment_in("which-test-pytest", &env).unwrap();
+    assert_eq!(path, conda);
+    let environment = environment.unwrap();
+    assert_eq!(environment.kind, EnvironmentKind::Conda);
+    assert_eq!(environment.name.as_deref(), Some("test"));
+
+    let env = env.var("CONDA_PREFIX", Option::<&str>::None);
+    assert_eq!(active_environment_in(&env), None);
+    assert!(find_in_environment_in("which-test-pytest", &env).is_err());
+}
+
+#[test]
+#[cfg(unix)]
+fn test_cargo_find_built() {
+    use std::time::{Duration, SystemTime};
+
+    let f = TestFixture::new();
+    let root = f.tempdir.path();
+    fs::create_dir_all(root.join("target/debug")).unwrap();
+    fs::create_dir_all(root.join("target/release")).unwrap();
+    let debug = f.mk_bin("target/debug/which-test-cli", "").unwrap();
+    let release = f.mk_bin("target/release/which-test-cli", "").unwrap();
+    let old = SystemTime::now() - Duration::from_secs(60);
+    fs::File::options()
+        .write(true)
+        .open(&debug)
+        .unwrap()
+        .set_modified(old)
+        .unwrap();
+
+    let target = root.join("target");
+    assert_eq!(which::cargo::profile_dirs_in(&target).len(), 2);
+    assert_eq!(
+        which::cargo::find_built_in("which-test-cli", &target).unwrap(),
+        release
+    );
+
+    // Binaries built for another target are in a directory of their own.
+    let triple = target.join("x86_64-unknown-linux-musl");
+    fs::create_dir_all(triple.join("release")).unwrap();
+    let cross = mk_bin(&triple.join("release"), "which-test-cli", "").unwrap();
+    assert_eq!(
+        which::cargo::profile_dirs_in(&target),
+        vec![
+            target.join("debug"),
+            target.join("release"),
+            triple.join("release")
+        ]
+    );
+    assert_eq!(
+        which::cargo::find_built_in("which-test-cli", &target).unwrap(),
+        cross
+    );
+
+    assert_eq!(
+        which::cargo::profile_dirs_in(root.join("elsewhere")),
+        Vec::<PathBuf>::new()
+    );
+    assert!(which::cargo::find_built_in("which-test-cli", root.join("elsewhere")).is_err());
+
+    // A relative CARGO_TARGET_DIR is relative to the working directory, like Cargo takes it.
+    #[cfg(feature = "test-util")]
+    {
+        use which::test_util::with_env;
+
+        let env = which::EnvSnapshot::new(None, None, Some(root.join("c")));
+        let unset = env.clone().var("CARGO_TARGET_DIR", Option::<&str>::None);
+        assert_eq!(with_env(unset, || which::cargo::target_dir(root)), target);
+        let relative = env.var("CARGO_TARGET_DIR", Some("out"));
+        assert_eq!(
+            with_env(relative, || which::cargo::target_dir(root)),
+            root.join("c/out")
+        );
+    }
+}
+
+#[test]
+#[cfg(unix)]
+fn test_subcommands() {
+    let f = TestFixture::new();
+    let lfs = f.mk_bin("b/tool-lfs", "").unwrap();
+    let flow = f.mk_bin("a/tool-flow", "").unwrap();
+    f.mk_bin("c/tool-lfs", "").unwrap();
+    f.mk_bin("c/tool-", "").unwrap();
+    f.touch("c/tool-data", "").unwrap();
+
+    let found = which::subcommands_in("tool", Some(&f.paths)).unwrap();
+    let found: Vec<_> = found
+        .into_iter()
+        .map(|subcommand| (subcommand.name, subcommand.path))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (OsString::from("flow"), flow),
+            (OsString::from("lfs"), lfs.clone())
+        ]
+    );
+    assert_eq!(
+        which::subcommand_in("tool", "lfs", Some(&f.paths), f.tempdir.path()).unwrap(),
+        lfs
+    );
+    assert!(which::subcommand_in("tool", "data", Some(&f.paths), f.tempdir.path()).is_err());
+}
+
+#[test]
+#[cfg(unix)]
+fn test_subcommand_scheme() {
+    use which::SubcommandScheme;
+
+    let f = TestFixture::new();
+    let nested = f.mk_bin("a/kube-view-all_secrets", "").unwrap();
+    let upper = f.mk_bin("b/Tool-Lint-plugin", "").unwrap();
+    f.mk_bin("b/Tool-lint", "").unwrap();
+
+    let kube = SubcommandScheme::new("kube-")
+        .replace('-', ' ')
+        .replace('_', '-');
+    let found = which::subcommands_with(&kube, Some(&f.paths)).unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].name, "view all-secrets");
+    assert_eq!(
+        which::subcommand_with(&kube, "view all-secrets", Some(&f.paths), f.tempdir.path())
+            .unwrap(),
+        nested
+    );
+
+    let plugins = SubcommandScheme::dashed("Tool")
+        .suffix("-plugin")
+        .lowercase(true);
+    let found = which::subcommands_with(&plugins, Some(&f.paths)).unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(
+        (&found[0].name, &found[0].path),
+        (&OsString::from("lint"), &upper)
+    );
+    for name in ["lint", "LINT"] {
+        assert_eq!(
+            which::subcommand_with(&plugins, name, Some(&f.paths), f.tempdir.path()).unwrap(),
+            upper
+        );
+    }
+    assert!(which::subcommand_with(&plugins, "tool", Some(&f.paths), f.tempdir.path()).is_err());
+}
+
+#[test]
+#[cfg(unix)]
+fn test_completer() {
+    use which::Completer;
+
+    let f = TestFixture::new();
+    let first = f.mk_bin("b/cargo-fmt", "").unwrap();
+    f.mk_bin("c/cargo-fmt", "").unwrap();
+    let clippy = f.mk_bin("c/cargo-clippy", "").unwrap();
+    let data = f.touch("a/cargo-data", "").unwrap();
+    fs::create_dir(f.tempdir.path().join("a/cargo-dir")).unwrap();
+
+    let complete = |verify: bool| {
+        Completer::new()
+            .paths(f.paths.clone())
+            .verify(verify)
+            .complete("cargo-")
+            .into_iter()
+            .map(|completion| (completion.name, completion.path))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        complete(false),
+        [
+            (OsString::from("cargo-clippy"), clippy.clone()),
+            (OsString::from("cargo-data"), data),
+            (OsString::from("cargo-fmt"), first.clone()),
+        ]
+    );
+    assert_eq!(
+        complete(true),
+        [
+            (OsString::from("cargo-clippy"), clippy),
+            (OsString::from("cargo-fmt"), first),
+        ]
+    );
+    assert_eq!(
+        Completer::new()
+            .paths(f.paths.clone())
+            .complete("no-such-prefix"),
+        []
+    );
+}
+
+#[test]
+fn test_error_source() {
+    use std::error::Error as _;
+
+    let error =
+        which::Error::CannotCanonicalize(io::Error::from(io::ErrorKind::PermissionDenied).into());
+    let source = error.source().unwrap().downcast_ref::<io::Error>().unwrap();
+    assert_eq!(source.kind(), io::ErrorKind::PermissionDenied);
+    assert_eq!(error.clone(), error);
+    assert_ne!(
+        error,
+        which::Error::CannotCanonicalize(io::Error::from(io::ErrorKind::NotFound).into())
+    );
+    assert!(which::Error::CannotFindBinaryPath.source().is_none());
+}
+
+#[test]
+fn test_error_kind() {
+    let f = TestFixture::new();
+    let missing = which::which_in("missing", Some(&f.paths), f.tempdir.path()).unwrap_err();
+    assert_eq!(missing.kind(), which::ErrorKind::NotFound);
+    assert_eq!(
+        which::which_global_strict("./bin").unwrap_err().kind(),
+        which::ErrorKind::BadPath
+    );
+    assert_eq!(
+        which::Error::CannotCanonicalize(io::Error::from(io::ErrorKind::NotFound).into()).kind(),
+        which::ErrorKind::Io
+    );
+}
+
+#[test]
+fn test_which_unique() {
+    let f = TestFixture::new();
+    let only = f.mk_bin("b/only", env::consts::EXE_EXTENSION).unwrap();
+    let b = f.tempdir.path().join("b");
+    let mut dirs: Vec<_> = env::split_paths(&f.paths).collect();
+    dirs.push(b.clone());
+    #[cfg(unix)]
+    {
+        let linked = f.tempdir.path().join("linked");
+        std::os::unix::fs::symlink(&b, &linked).unwrap();
+        dirs.push(linked);
+    }
+    let paths = env::join_paths(dirs).unwrap();
+
+    // The same file through different entries counts once.
+    assert_eq!(
+        which::which_unique_in("only", Some(&paths), f.tempdir.path()).unwrap(),
+        only
+    );
+    assert_eq!(
+        which::WhichConfig::new()
+            .custom_path_list(paths.clone())
+            .binary_name("only".into())
+            .unique_result()
+            .unwrap(),
+        only
+    );
+
+    let error = which::which_unique_in(BIN_NAME, Some(&paths), f.tempdir.path()).unwrap_err();
+    assert_eq!(error.kind(), which::ErrorKind::Ambiguous);
+    match error {
+        which::Error::Ambiguous(found) => {
+            #[cfg(not(windows))]
+            assert_eq!(
+                found,
+                [f.bins[0].clone(), f.bins[3].clone(), f.bins[6].clone()]
+            );
+            #[cfg(windows)]
+            assert_eq!(found.len(), 6);
+        }
+        error => panic!("{:?}", error),
+    }
+
+    assert_eq!(
+        which::which_unique_in("missing", Some(&paths), f.tempdir.path()),
+        Err(which::Error::CannotFindBinaryPath)
+    );
+}
+
+#[test]
+#[cfg(feature = "camino")]
+fn test_which_utf8() {
+    let f = TestFixture::new();
+    let found = which::which_in_utf8(BIN_NAME, Some(&f.paths), f.tempdir.path()).unwrap();
+    assert_eq!(
+        found.canonicalize().unwrap(),
+        _which(&f, BIN_NAME).unwrap().as_path()
+    );
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+
+        let name = OsStr::from_bytes(b"bin\xff");
+        // Copying keeps the execute permission.
+        fs::copy(&f.bins[0], f.tempdir.path().join("a").join(name)).unwrap();
+        assert_eq!(
+            which::which_in_utf8(name, Some(&f.paths), f.tempdir.path()),
+            Err(which::Error::NonUtf8Path)
+        );
+    }
+}
+
+#[test]
+fn test_which_all_boxed() {
+    let f = TestFixture::new();
+    let boxed: Box<dyn Iterator<Item = PathBuf> + Send> = which::WhichConfig::new()
+        .custom_path_list(f.paths.clone())
+        .custom_cwd(f.tempdir.path().to_path_buf())
+        .binary_name(BIN_NAME.into())
+        .all_results_boxed()
+        .unwrap();
+    let expected: Vec<_> = which::which_in_all(BIN_NAME, Some(&f.paths), f.tempdir.path())
+        .unwrap()
+        .collect();
+    let found = std::thread::spawn(move || boxed.collect::<Vec<_>>())
+        .join()
+        .unwrap();
+    assert_eq!(found, expected);
+}
+
+#[test]
+fn test_which_all_iterator_traits() {
+    fn assert_traits<I: std::iter::FusedIterator + Send + Sync>(iter: I) -> I {
+        iter
+    }
+
+    let f = TestFixture::new();
+    let mut iter =
+        assert_traits(which::which_in_all(BIN_NAME, Some(&f.paths), f.tempdir.path()).unwrap());
+    let (_, upper) = iter.size_hint();
+    let found = iter.by_ref().count();
+    assert!(found > 0 && Some(found) <= upper);
+    assert_eq!(iter.size_hint(), (0, Some(0)));
+    assert_eq!(iter.next(), None);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_which_absolute() {
+    let f = TestFixture::new();
+    assert_eq!(
+        _which(&f, &f.bins[3]).unwrap(),
+        f.bins[3].canonicalize().unwrap()
+    );
+}
+
+#[test]
+#[cfg(windows)]
+fn test_which_absolute() {
+    let f = TestFixture::new();
+    assert_eq!(
+        _which(&f, &f.bins[4]).unwrap(),
+        f.bins[4].canonicalize().unwrap()
+    );
+}
+
+#[test]
+#[cfg(windows)]
+fn test_which_absolute_path_case() {
+    // Test that an absolute path with an uppercase extension
+    // is accepted.
+    let f = TestFixture::new();
+    let p = &f.bins[4];
+    assert_eq!(_which(&f, p).unwrap(), f.bins[4].canonicalize().unwrap());
+}
+
+#[test]
+#[cfg(unix)]
+fn test_which_absolute_extension() {
+    let f = TestFixture::new();
+    // Don't append EXE_EXTENSION here.
+    let b = f.bins[3].parent().unwrap().join(BIN_NAME);
+    assert_eq!(_which(&f, b).unwrap(), f.bins[3].canonicalize().unwrap());
+}
+
+#[test]
+#[cfg(windows)]
+fn test_which_absolute_extension() {
+    let f = TestFixture::new();
+    // Don't append EXE_EXTENSION here.
+    let b = f.bins[4].parent().unwrap().join(BIN_NAME);
+    assert_eq!(_which(&f, b).unwrap(), f.bins[4].canonicalize().unwrap());
+}
+
+#[test]
+#[cfg(unix)]
+fn test_which_relative() {
+    let f = TestFixture::new();
+    assert_eq!(
+        _which(&f, "b/bin").unwrap(),
+        f.bins[3].canonicalize().unwrap()
+    );
+}
+
+#[test]
+#[cfg(windows)]
+fn test_which_relative() {
+    let f = TestFixture::new();
+    assert_eq!(
+        _which(&f, "b/bin").unwrap(),
+        f.bins[4].canonicalize().unwrap()
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn test_which_relative_extension() {
+    // test_which_relative tests a relative path without an extension,
+    // so test a relative path with an extension here.
+    let f = TestFixture::new();
+    let b = Path::new("b/bin").with_extension(env::consts::EXE_EXTENSION);
+    assert_eq!(_which(&f, b).unwrap(), f.bins[3].canonicalize().unwrap());
+}
+
+#[test]
+#[cfg(windows)]
+fn test_which_relative_extension() {
+    // test_which_relative tests a relative path without an extension,
+    // so test a relative path with an extension here.
+    let f = TestFixture::new();
+    let b = Path::new("b/bin").with_extension("cmd");
+    assert_eq!(_which(&f, b).unwrap(), f.bins[5].canonicalize().unwrap());
+}
+
+#[test]
+#[cfg(windows)]
+fn test_which_relative_extension_case() {
+    // Test that a relative path with an uppercase extension
+    // is accepted.
+    let f = TestFixture::new();
+    let b = Path::new("b/bin").with_extension("EXE");
+    assert_eq!(_which(&f, b).unwrap(), f.bins[4].canonicalize().unwrap());
+}
+
+#[test]
+#[cfg(unix)]
+fn test_which_relative_leading_dot() {
+    let f = TestFixture::new();
+    assert_eq!(
+        _which(&f, "./b/bin").unwrap(),
+        f.bins[3].canonicalize().unwrap()
+    );
+}
+
+#[test]
+#[cfg(windows)]
+fn test_which_relative_leading_dot() {
+    let f = TestFixture::new();
+    assert_eq!(
+        _which(&f, "./b/bin").unwrap(),
+        f.bins[4].canonicalize().unwrap()
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn test_which_non_executable() {
+    // Shouldn't return non-executable files.
+    let f = TestFixture::new();
+    f.touch("b/another", "").unwrap();
+    assert!(_which(&f, "another").is_err());
+}
+
+#[test]
+#[cfg(unix)]
+fn test_which_absolute_non_executable() {
+    // Shouldn't return non-executable files, even if given an absolute path.
+    let f = TestFixture::new();
+    let b = f.touch("b/another", "").unwrap();
+    assert!(_which(&f, b).is_err());
+}
+
+#[test]
+#[cfg(unix)]
+fn test_which_relative_non_executable() {
+    // Shouldn't return non-executable files.
+    let f = TestFixture::new();
+    f.touch("b/another", "").unwrap();
+    assert!(_which(&f, "b/another").is_err());
+}
+
+#[test]
+fn test_failure() {
+    let f = TestFixture::new();
+
+    let run = || -> which::Result<PathBuf> {
+        let p = _which(&f, "./b/bin")?;
+        Ok(p.into_path_buf())
+    };
+
+    let _ = run();
+}
+
+struct RejectAll;
+
+impl which::Checker for RejectAll {
+    fn is_valid(&self, _candidate: &which::Candidate<'_>) -> bool {
+        false
+    }
+
+    fn cost(&self) -> which::Cost {
+        which::Cost::Free
+    }
+}
+
+struct MustNotRun;
+
+impl which::Checker for MustNotRun {
+    fn is_valid(&self, candidate: &which::Candidate<'_>) -> bool {
+        panic!("checker ran for {:?}", candidate.path())
+    }
+
+    fn cost(&self) -> which::Cost {
+        which::Cost::Io
+    }
+}
+
+#[test]
+fn test_which_custom_checker() {
+    let f = TestFixture::new();
+    let result = which::WhichConfig::new()
+        .binary_name(BIN_NAME.into())
+        .custom_path_list(f.paths.clone())
+        .checker(RejectAll)
+        .first_result();
+    assert_eq!(result, Err(which::Error::CannotFindBinaryPath));
+}
+
+#[test]
+fn test_which_checkers_short_circuit_by_cost() {
+    // The expensive checker is added first, but must never run since the free one rejects
+    // every candidate.
+    let f = TestFixture::new();
+    let results = which::WhichConfig::new()
+        .binary_name(BIN_NAME.into())
+        .custom_path_list(f.paths.clone())
+        .checker(MustNotRun)
+        .checker(RejectAll)
+        .all_results()
+        .unwrap()
+        .count();
+    assert_eq!(results, 0);
+}
+
+#[test]
+fn test_set_default_config() {
+    struct RejectName(&'static str);
+
+    impl which::Checker for RejectName {
+        fn is_valid(&self, candidate: &which::Candidate<'_>) -> bool {
+            candidate.path().file_stem() != Some(OsStr::new(self.0))
+        }
+
+        fn cost(&self) -> which::Cost {
+            which::Cost::Free
+        }
+    }
+
+    // The default configuration is shared by every test, so it only rejects a name no other
+    // test looks up.
+    let f = TestFixture::new();
+    let tool = f
+        .mk_bin("a/default-config-tool", env::consts::EXE_EXTENSION)
+        .unwrap();
+    assert_eq!(which::which(&tool).unwrap(), tool);
+    assert_eq!(which::which_cached(&tool).unwrap(), tool);
+
+    which::set_default_config(
+        which::WhichConfig::new()
+            .binary_name("ignored".into())
+            .checker(RejectName("default-config-tool")),
+    );
+    assert_eq!(which::which(&tool), Err(which::Error::CannotFindBinaryPath));
+    assert_eq!(which::which_all(&tool).unwrap().count(), 0);
+    assert!(!which::exists(&tool));
+    assert!(which::which_in(&tool, Some(&f.paths), f.tempdir.path()).is_ok());
+    // Not the result remembered with the earlier configuration.
+    assert_eq!(
+        which::which_cached(&tool),
+        Err(which::Error::CannotFindBinaryPath)
+    );
+
+    which::clear_default_config();
+    assert_eq!(which::which(&tool).unwrap(), tool);
+    assert!(which::exists(&tool));
+    assert_eq!(which::which_cached(&tool).unwrap(), tool);
+}
+
+#[test]
+fn test_which_many() {
+    let f = TestFixture::new();
+    let another = f.mk_bin("b/another", env::consts::EXE_EXTENSION).unwrap();
+    let found = which::which_many_in(
+        [BIN_NAME, "another", "missing"],
+        Some(&f.paths),
+        f.tempdir.path(),
+    )
+    .unwrap();
+    let found: Vec<_> = found
+        .into_iter()
+        .map(|p| p.map(|p| p.canonicalize().unwrap()))
+        .collect();
+
+    #[cfg(not(windows))]
+    let bin = f.bins[0].clone();
+    #[cfg(windows)]
+    let bin = f.bins[1].clone();
+    assert_eq!(found, vec![Some(bin), Some(another), None]);
+}
+
+#[test]
+fn test_which_many_without_path() {
+    let f = TestFixture::new();
+    let found = which::which_many_in(
+        ["./b/bin", BIN_NAME],
+        Option::<&str>::None,
+        f.tempdir.path(),
+    )
+    .unwrap();
+    let found: Vec<_> = found
+        .into_iter()
+        .map(|p| p.map(|p| p.canonicalize().unwrap()))
+        .collect();
+
+    #[cfg(not(windows))]
+    let bin = f.bins[3].clone();
+    #[cfg(windows)]
+    let bin = f.bins[4].clone();
+    assert_eq!(found, vec![Some(bin), None]);
+}
+
+#[test]
+fn test_which_many_relative() {
+    let f = TestFixture::new();
+    let found =
+        which::which_many_in(["./b/bin", "b/missing"], Some(&f.paths), f.tempdir.path()).unwrap();
+    let found: Vec<_> = found
+        .into_iter()
+        .map(|p| p.map(|p| p.canonicalize().unwrap()))
+        .collect();
+
+    #[cfg(not(windows))]
+    let bin = f.bins[3].clone();
+    #[cfg(windows)]
+    let bin = f.bins[4].clone();
+    assert_eq!(found, vec![Some(bin), None]);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_which_stats() {
+    let f = TestFixture::new();
+    let (result, stats) = which::WhichConfig::new()
+        .custom_path_list(f.paths.clone())
+        .binary_name(BIN_NAME.into())
+        .first_result_with_stats();
+    assert_eq!(result.unwrap().canonicalize().unwrap(), f.bins[0]);
+    assert_eq!(stats.directories_scanned, 1);
+    assert_eq!(stats.candidates, 1);
+    assert_eq!(stats.syscalls, 1);
+
+    let (results, stats) = which::WhichConfig::new()
+        .custom_path_list(f.paths.clone())
+        .binary_name("missing".into())
+        .all_results_with_stats()
+        .unwrap();
+    assert!(results.is_empty());
+    assert_eq!(stats.directories_scanned, SUBDIRS.len() + 1);
+    assert_eq!(stats.candidates, SUBDIRS.len() + 1);
+    let dirs: Vec<_> = stats.directories.iter().map(|d| d.dir.clone()).collect();
+    assert_eq!(dirs, env::split_paths(&f.paths).collect::<Vec<_>>());
+    assert!(stats.directories.iter().all(|d| d.candidates == 1));
+    assert!(
+        stats
+            .directories
+            .iter()
+            .map(|d| d.elapsed)
+            .sum::<std::time::Duration>()
+            <= stats.elapsed
+    );
+    let origins: Vec<_> = stats
+        .elapsed_by_origin()
+        .into_iter()
+        .map(|(o, _)| o)
+        .collect();
+    assert_eq!(
+        origins,
+        (0..SUBDIRS.len() + 1)
+            .map(which::Origin::PathEntry)
+            .collect::<Vec<_>>()
+    );
+    let report = stats.to_string();
+    assert_eq!(report.lines().count(), SUBDIRS.len() + 2);
+    assert!(report.contains(&format!("{} (1 candidates)", dirs[0].display())));
+
+    // Subdirectories count towards their entry.
+    let (_, stats) = which::WhichConfig::new()
+        .custom_path_list(f.tempdir.path().as_os_str().to_owned())
+        .binary_name("missing".into())
+        .depth(1)
+        .all_results_with_stats()
+        .unwrap();
+    assert_eq!(stats.directories_scanned, SUBDIRS.len() + 2);
+    assert_eq!(stats.elapsed_by_origin().len(), 1);
+}
+
+#[test]
+#[cfg(feature = "futures-core")]
+fn test_all_results_stream() {
+    use futures_core::Stream;
+    use std::pin::Pin;
+
+    let f = TestFixture::new();
+    let config = || {
+        which::WhichConfig::new()
+            .custom_path_list(f.paths.clone())
+            .binary_name(BIN_NAME.into())
+    };
+    let mut stream = config().all_results_stream().unwrap();
+    let streamed = futures_executor::block_on(async {
+        let mut found = Vec::new();
+        while let Some(path) = std::future::poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await
+        {
+            found.push(path);
+        }
+        found
+    });
+    assert_eq!(
+        streamed,
+        config().all_results().unwrap().collect::<Vec<_>>()
+    );
+
+    // Stopping early is just dropping the stream.
+    let mut stream = config().all_results_stream().unwrap();
+    let first = futures_executor::block_on(std::future::poll_fn(|cx| {
+        Pin::new(&mut stream).poll_next(cx)
+    }));
+    assert_eq!(first, Some(streamed[0].clone()));
+    drop(stream);
+
+    assert!(which::stream::which_all("").is_err());
+
+    // Searches run wherever the spawner puts them.
+    let spawned = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let counter = spawned.clone();
+    let spawner = move |task: Box<dyn FnOnce() + Send>| {
+        counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        std::thread::spawn(task);
+    };
+    let mut stream = config().all_results_stream_with(&spawner).unwrap();
+    let first = futures_executor::block_on(std::future::poll_fn(|cx| {
+        Pin::new(&mut stream).poll_next(cx)
+    }));
+    assert_eq!(first, Some(streamed[0].clone()));
+    assert_eq!(spawned.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+    // More results than are buffered, from a search that's ahead of the stream, or that ran
+    // before it was even returned.
+    let paths = env::join_paths(vec![f.tempdir.path().join("a"); 40]).unwrap();
+    let config = || {
+        which::WhichConfig::new()
+            .custom_path_list(paths.clone())
+            .binary_name(BIN_NAME.into())
+    };
+    let expected: Vec<_> = config().all_results().unwrap().collect();
+    assert_eq!(expected.len(), 40);
+    let inline = |task: Box<dyn FnOnce() + Send>| task();
+    for spawner in [
+        &which::stream::ThreadSpawner as &dyn which::stream::Spawner,
+        &inline,
+    ] {
+        let mut stream = config().all_results_stream_with(spawner).unwrap();
+        let streamed = futures_executor::block_on(async {
+            let mut found = Vec::new();
+            while let Some(path) =
+                std::future::poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await
+            {
+                found.push(path);
+            }
+            found
+        });
+        assert_eq!(streamed, expected);
+    }
+}
+
+#[test]
+fn test_slow_dir_warning() {
+    let f = TestFixture::new();
+    let search = |threshold: std::time::Duration| -> Vec<PathBuf> {
+        let slow = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let reported = slow.clone();
+        let results = which::WhichConfig::new()
+            .custom_path_list(f.paths.clone())
+            .binary_name("missing".into())
+            .slow_dir_warning(threshold, move |dir, _| {
+                reported.lock().unwrap().push(dir.to_path_buf())
+            })
+            .all_results()
+            .unwrap()
+            .count();
+        assert_eq!(results, 0);
+        let slow = slow.lock().unwrap().clone();
+        slow
+    };
+
+    assert_eq!(
+        search(std::time::Duration::ZERO),
+        env::split_paths(&f.paths).collect::<Vec<_>>()
+    );
+    assert!(search(std::time::Duration::from_secs(3600)).is_empty());
+}
+
+#[cfg(all(unix, feature = "regex"))]
+fn set_dir_mtime(dir: &Path, mtime: std::time::SystemTime) {
+    fs::File::open(dir).unwrap().set_modified(mtime).unwrap();
+}
+
+#[test]
+#[cfg(all(unix, feature = "regex"))]
+fn test_which_re_cache() {
+    let f = TestFixture::new();
+    let dir = f.tempdir.path().join("a");
+    let old = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+    let search = |cache: &which::WhichCache| -> Vec<PathBuf> {
+        which::WhichConfig::new()
+            .cache(cache.clone())
+            .system_cwd(false)
+            .custom_path_list(dir.clone().into_os_string())
+            .regex(Regex::new(r"^bin_\d$").unwrap())
+            .all_results()
+            .unwrap()
+            .collect()
+    };
+
+    f.mk_bin("a/bin_0", "").unwrap();
+    set_dir_mtime(&dir, old);
+    let cache = which::WhichCache::new();
+    assert_eq!(search(&cache), vec![dir.join("bin_0")]);
+
+    // An unchanged modification time means the cached listing is reused.
+    f.mk_bin("a/bin_1", "").unwrap();
+    set_dir_mtime(&dir, old);
+    assert_eq!(search(&cache), vec![dir.join("bin_0")]);
+
+    set_dir_mtime(&dir, old + std::time::Duration::from_secs(1));
+    let mut found = search(&cache);
+    found.sort();
+    assert_eq!(found, vec![dir.join("bin_0"), dir.join("bin_1")]);
+
+    cache.clear();
+    f.mk_bin("a/bin_2", "").unwrap();
+    set_dir_mtime(&dir, old + std::time::Duration::from_secs(1));
+    assert_eq!(search(&cache).len(), 3);
+}
+
+#[test]
+#[cfg(all(target_os = "linux", feature = "regex"))]
+fn test_which_re_listed_kinds() {
+    let f = TestFixture::new();
+    let dir = f.tempdir.path().join("a");
+    fs::create_dir(dir.join("bin_dir")).unwrap();
+    f.mk_bin("a/bin_1", "").unwrap();
+
+    let (results, stats) = which::WhichConfig::new()
+        .system_cwd(false)
+        .custom_path_list(dir.clone().into_os_string())
+        .regex(Regex::new(r"^bin_").unwrap())
+        .all_results_with_stats()
+        .unwrap();
+    assert_eq!(results, vec![dir.join("bin_1")]);
+    assert_eq!(stats.candidates, 2);
+    // One listing and a lookup of the file, the directory was rejected by its listed type.
+    assert_eq!(stats.syscalls, 2);
+}
+
+#[test]
+#[cfg(all(unix, feature = "regex", feature = "notify"))]
+fn test_which_re_watching_cache() {
+    let f = TestFixture::new();
+    let dir = f.tempdir.path().join("a");
+    let old = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+    let search = |cache: &which::WhichCache| -> usize {
+        which::WhichConfig::new()
+            .cache(cache.clone())
+            .system_cwd(false)
+            .custom_path_list(dir.clone().into_os_string())
+            .regex(Regex::new(r"^bin_\d$").unwrap())
+            .all_results()
+            .unwrap()
+            .count()
+    };
+
+    let cache = which::WhichCache::watching().unwrap();
+    f.mk_bin("a/bin_0", "").unwrap();
+    assert_eq!(search(&cache), 1);
+
+    // Hiding the change from modification time validation; only the watch can notice it.
+    f.mk_bin("a/bin_1", "").unwrap();
+    set_dir_mtime(&dir, old);
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+    while search(&cache) != 2 {
+        assert!(
+            std::time::Instant::now() < deadline,
+            "change never observed"
+        );
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+}
+
+#[test]
+#[cfg(all(target_os = "linux", feature = "regex", feature = "notify"))]
+fn test_which_re_watching_cache_recreated_dir() {
+    let f = TestFixture::new();
+    let dir = f.tempdir.path().join("a");
+    let old = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+    let search = |cache: &which::WhichCache| -> usize {
+        which::WhichConfig::new()
+            .cache(cache.clone())
+            .system_cwd(false)
+            .custom_path_list(dir.clone().into_os_string())
+            .regex(Regex::new(r"^bin_\d$").unwrap())
+            .all_results()
+            .unwrap()
+            .count()
+    };
+    let wait_for = |cache: &which::WhichCache, count: usize| {
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        while search(cache) != count {
+            assert!(
+                std::time::Instant::now() < deadline,
+                "change never observed"
+            );
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+    };
+
+    let cache = which::WhichCache::watching().unwrap();
+    f.mk_bin("a/bin_0", "").unwrap();
+    assert_eq!(search(&cache), 1);
+
+    // The watch went with the old directory, so the new one has to be watched afresh.
+    fs::remove_dir_all(&dir).unwrap();
+    fs::create_dir(&dir).unwrap();
+    wait_for(&cache, 0);
+    f.mk_bin("a/bin_0", "").unwrap();
+    f.mk_bin("a/bin_1", "").unwrap();
+    set_dir_mtime(&dir, old);
+    wait_for(&cache, 2);
+}
+
+/// Serializes tests using the process-wide cache, since they clear it.
+static GLOBAL_CACHE: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+#[test]
+fn test_which_cached() {
+    let _guard = GLOBAL_CACHE.lock().unwrap();
+    let f = TestFixture::new();
+    let bin = f.mk_bin("b/cached", env::consts::EXE_EXTENSION).unwrap();
+
+    assert_eq!(which::which_cached(&bin).unwrap(), bin);
+
+    // Still served from the cache once the file is gone.
+    fs::remove_file(&bin).unwrap();
+    assert_eq!(which::which_cached(&bin).unwrap(), bin);
+
+    which::cache::clear();
+    assert!(which::which_cached(&bin).is_err());
+}
+
+#[test]
+#[cfg(all(windows, feature = "test-util"))]
+fn test_which_cached_pathext() {
+    use which::test_util::with_env;
+
+    let _guard = GLOBAL_CACHE.lock().unwrap();
+    let f = TestFixture::new();
+    let script = f.mk_bin("b/pathext", "cmd").unwrap();
+    let env =
+        |pathext: &str| which::EnvSnapshot::new(Some(f.paths.clone()), Some(pathext.into()), None);
+
+    // A result found with one PATHEXT isn't served to lookups with another.
+    assert_eq!(
+        with_env(env(".CMD"), || which::which_cached("pathext")).unwrap(),
+        script
+    );
+    assert!(with_env(env(".EXE"), || which::which_cached("pathext")).is_err());
+
+    which::cache::clear();
+}
+
+#[test]
+fn test_which_cached_negative() {
+    let _guard = GLOBAL_CACHE.lock().unwrap();
+    let f = TestFixture::new();
+    let bin = f
+        .tempdir
+        .path()
+        .join("b/late")
+        .with_extension(env::consts::EXE_EXTENSION);
+
+    which::cache::set_negative_ttl(Some(std::time::Duration::from_secs(3600)));
+    assert!(which::which_cached(&bin).is_err());
+
+    // The failure is remembered even though the binary now exists.
+    let bin = f.mk_bin("b/late", env::consts::EXE_EXTENSION).unwrap();
+    assert!(which::which_cached(&bin).is_err());
+
+    which::cache::clear();
+    assert_eq!(which::which_cached(&bin).unwrap(), bin);
+
+    // A zero TTL expires immediately.
+    which::cache::set_negative_ttl(Some(std::time::Duration::from_secs(0)));
+    let other = f.tempdir.path().join("b/other");
+    assert!(which::which_cached(&other).is_err());
+    f.mk_bin("b/other", "").unwrap();
+    #[cfg(unix)]
+    assert!(which::which_cached(&other).is_ok());
+
+    // One too large to represent never expires.
+    which::cache::set_negative_ttl(Some(std::time::Duration::MAX));
+    let never = f.tempdir.path().join("b/never");
+    assert!(which::which_cached(&never).is_err());
+    f.mk_bin("b/never", "").unwrap();
+    assert!(which::which_cached(&never).is_err());
+
+    which::cache::set_negative_ttl(None);
+}
+
+#[test]
+fn test_refresh_env() {
+    let _guard = GLOBAL_CACHE.lock().unwrap();
+    let f = TestFixture::new();
+    let bin = f.mk_bin("b/refreshed", env::consts::EXE_EXTENSION).unwrap();
+
+    assert_eq!(which::which_cached(&bin).unwrap(), bin);
+    fs::remove_file(&bin).unwrap();
+    which::refresh_env();
+    assert!(which::which_cached(&bin).is_err());
+
+    // %PATHEXT% is read again. The extension added is only used here, so other tests running
+    // meanwhile aren't affected.
+    #[cfg(windows)]
+    {
+        let tool = f.touch("a/tool", "whichtest").unwrap();
+        let find = || which::which_in("tool", Some(&f.paths), f.tempdir.path());
+        assert!(find().is_err());
+        let pathext = env::var_os("PATHEXT").unwrap_or_default();
+        let mut with_test = pathext.clone();
+        with_test.push(";.WHICHTEST");
+        env::set_var("PATHEXT", &with_test);
+        assert!(find().is_err());
+        which::refresh_env();
+        assert_eq!(find().unwrap().canonicalize().unwrap(), tool);
+        env::set_var("PATHEXT", &pathext);
+        which::refresh_env();
+    }
 }